@@ -1,5 +1,9 @@
+pub mod probe;
+pub mod xml_float;
+
 use serde::{Deserialize, Serialize};
-use serde_with::skip_serializing_none;
+use serde_with::{serde_as, skip_serializing_none};
+pub use xml_float::XmlFloat;
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -51,6 +55,7 @@ pub struct CraftTypes {
     pub craft_template: Option<Vec<CraftTemplate>>,
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
@@ -64,7 +69,8 @@ pub struct CraftTemplate {
     #[serde(rename = "LongDescription")]
     pub long_description: String,
     #[serde(rename = "Cost")]
-    pub cost: String,
+    #[serde_as(as = "XmlFloat")]
+    pub cost: f32,
     #[serde(rename = "FrameKey")]
     pub frame_key: String,
     #[serde(rename = "TemplateKey")]
@@ -233,13 +239,13 @@ pub struct Ship {
     #[serde(rename = "Name")]
     pub name: String,
     #[serde(rename = "Cost")]
-    pub cost: String,
+    pub cost: u32,
     #[serde(rename = "Callsign")]
     pub callsign: Option<String>,
     #[serde(rename = "Number")]
-    pub number: String,
+    pub number: u32,
     #[serde(rename = "SymbolOption")]
-    pub symbol_option: String,
+    pub symbol_option: u32,
     #[serde(rename = "HullType")]
     pub hull_type: String,
     #[serde(rename = "HullConfig")]
@@ -268,12 +274,16 @@ pub struct InitialFormation {
     pub relative_position: RelativePosition,
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct RelativePosition {
+    #[serde_as(as = "XmlFloat")]
     pub x: f64,
+    #[serde_as(as = "XmlFloat")]
     pub y: f64,
+    #[serde_as(as = "XmlFloat")]
     pub z: f64,
 }
 
@@ -333,7 +343,7 @@ pub struct SegmentConfiguration {
 pub struct Dressing {
     #[serde(rename = "$text")]
     pub text: Option<String>,
-    pub int: Option<Vec<String>>,
+    pub int: Option<Vec<u8>>,
 }
 
 #[skip_serializing_none]
@@ -355,32 +365,41 @@ pub struct SecondaryStructureConfig {
     #[serde(rename = "Key")]
     pub key: String,
     #[serde(rename = "Segment")]
-    pub segment: String,
+    pub segment: u32,
     #[serde(rename = "SnapPoint")]
-    pub snap_point: String,
+    pub snap_point: u32,
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct HullTint {
     #[serde(rename = "$text")]
     pub text: Option<String>,
-    pub r: String,
-    pub g: String,
-    pub b: String,
-    pub a: String,
+    #[serde_as(as = "XmlFloat")]
+    pub r: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub g: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub b: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub a: f32,
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct TextureVariation {
     #[serde(rename = "$text")]
     pub text: Option<String>,
-    pub x: String,
-    pub y: String,
-    pub z: String,
+    #[serde_as(as = "XmlFloat")]
+    pub x: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub y: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub z: f32,
 }
 
 #[skip_serializing_none]
@@ -476,7 +495,7 @@ pub struct MissileLoadMagSaveData {
     #[serde(rename = "MunitionKey")]
     pub munition_key: String,
     #[serde(rename = "Quantity")]
-    pub quantity: String,
+    pub quantity: u32,
 }
 
 #[skip_serializing_none]
@@ -500,7 +519,7 @@ pub struct LoadMagSaveData {
     #[serde(rename = "MunitionKey")]
     pub munition_key: String,
     #[serde(rename = "Quantity")]
-    pub quantity: String,
+    pub quantity: u32,
 }
 
 #[skip_serializing_none]
@@ -554,6 +573,7 @@ pub struct MissileTypes {
     pub missile_template: Option<Vec<MissileTemplate>>,
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
@@ -575,7 +595,8 @@ pub struct MissileTemplate {
     #[serde(rename = "LongDescription")]
     pub long_description: String,
     #[serde(rename = "Cost")]
-    pub cost: String,
+    #[serde_as(as = "XmlFloat")]
+    pub cost: f32,
     #[serde(rename = "BodyKey")]
     pub body_key: String,
     #[serde(rename = "TemplateKey")]
@@ -592,28 +613,38 @@ pub struct MissileTemplate {
     pub mod_dependencies: Option<String>,
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct BaseColor {
     #[serde(rename = "$text")]
     pub text: Option<String>,
-    pub r: String,
-    pub g: String,
-    pub b: String,
-    pub a: String,
+    #[serde_as(as = "XmlFloat")]
+    pub r: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub g: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub b: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub a: f32,
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct StripeColor {
     #[serde(rename = "$text")]
     pub text: Option<String>,
-    pub r: String,
-    pub g: String,
-    pub b: String,
-    pub a: String,
+    #[serde_as(as = "XmlFloat")]
+    pub r: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub g: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub b: f32,
+    #[serde_as(as = "XmlFloat")]
+    pub a: f32,
 }
 
 #[skip_serializing_none]
@@ -633,11 +664,12 @@ pub struct MissileSocket {
     #[serde(rename = "$text")]
     pub text: Option<String>,
     #[serde(rename = "Size")]
-    pub size: String,
+    pub size: u32,
     #[serde(rename = "InstalledComponent")]
     pub installed_component: Option<InstalledComponent>,
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
@@ -649,9 +681,9 @@ pub struct InstalledComponent {
     #[serde(rename = "$text")]
     pub text: Option<String>,
     #[serde(rename = "DetectPDTargets")]
-    pub detect_pdtargets: Option<String>,
+    pub detect_pdtargets: Option<bool>,
     #[serde(rename = "RejectUnvalidated")]
-    pub reject_unvalidated: Option<String>,
+    pub reject_unvalidated: Option<bool>,
     #[serde(rename = "Mode")]
     pub mode: Option<String>,
     #[serde(rename = "ApproachAngleControl")]
@@ -661,9 +693,9 @@ pub struct InstalledComponent {
     #[serde(rename = "Maneuvers")]
     pub maneuvers: Option<String>,
     #[serde(rename = "SelfDestructOnLost")]
-    pub self_destruct_on_lost: Option<String>,
+    pub self_destruct_on_lost: Option<bool>,
     #[serde(rename = "HotLaunch")]
-    pub hot_launch: Option<String>,
+    pub hot_launch: Option<bool>,
     #[serde(rename = "Role")]
     pub role: Option<String>,
     #[serde(rename = "ComponentKey")]
@@ -677,6 +709,7 @@ pub struct InstalledComponent {
     #[serde(rename = "SpreadOption")]
     pub spread_option: Option<u8>,
     #[serde(rename = "Range")]
+    #[serde_as(as = "Option<XmlFloat>")]
     pub range: Option<f64>,
     #[serde(rename = "Interval")]
     pub interval: Option<u8>,
@@ -701,15 +734,15 @@ pub struct DefensiveDoctrine {
     #[serde(rename = "$text")]
     pub text: Option<String>,
     #[serde(rename = "TargetSizeMask")]
-    pub target_size_mask: String,
+    pub target_size_mask: u8,
     #[serde(rename = "TargetType")]
     pub target_type: Option<String>,
     #[serde(rename = "TargetSizeOrdering")]
     pub target_size_ordering: String,
     #[serde(rename = "SalvoSize")]
-    pub salvo_size: String,
+    pub salvo_size: u32,
     #[serde(rename = "FarthestFirst")]
-    pub farthest_first: String,
+    pub farthest_first: bool,
     #[serde(rename = "Mode")]
     pub mode: Option<String>,
     #[serde(rename = "ConvSalvo")]
@@ -720,6 +753,7 @@ pub struct DefensiveDoctrine {
     pub craft_salvo: Option<u32>,
 }
 
+#[serde_as]
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
@@ -727,9 +761,72 @@ pub struct BalanceValues {
     #[serde(rename = "$text")]
     pub text: Option<String>,
     #[serde(rename = "A")]
-    pub a: String,
+    #[serde_as(as = "XmlFloat")]
+    pub a: f32,
     #[serde(rename = "B")]
-    pub b: String,
+    #[serde_as(as = "XmlFloat")]
+    pub b: f32,
     #[serde(rename = "C")]
-    pub c: String,
+    #[serde_as(as = "XmlFloat")]
+    pub c: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_numbers() {
+        let doctrine = |mask: &str, salvo: &str, farthest: &str| {
+            quick_xml::de::from_str::<DefensiveDoctrine>(&format!(
+                "<DefensiveDoctrine><TargetSizeMask>{mask}</\
+                 TargetSizeMask><TargetSizeOrdering>Descending</\
+                 TargetSizeOrdering><SalvoSize>{salvo}</\
+                 SalvoSize><FarthestFirst>{farthest}</FarthestFirst></\
+                 DefensiveDoctrine>"
+            ))
+        };
+        let parsed = doctrine("7", "2", "true").unwrap();
+        assert_eq!(
+            (
+                parsed.target_size_mask,
+                parsed.salvo_size,
+                parsed.farthest_first
+            ),
+            (7, 2, true)
+        );
+        let xml = quick_xml::se::to_string(&parsed).unwrap();
+        assert_eq!(
+            quick_xml::de::from_str::<DefensiveDoctrine>(&xml).unwrap(),
+            parsed
+        );
+
+        // Out of range, negative and non-numeric values are rejected
+        assert!(doctrine("256", "2", "true").is_err());
+        assert!(doctrine("7", "-1", "true").is_err());
+        assert!(doctrine("7", "two", "true").is_err());
+        assert!(doctrine("7", "2", "yes").is_err());
+    }
+
+    #[test]
+    fn dressing_indices() {
+        let dressing = |xml: &str| quick_xml::de::from_str::<Dressing>(xml);
+        assert_eq!(
+            dressing("<Dressing><int>1</int><int>0</int></Dressing>")
+                .unwrap()
+                .int,
+            Some(vec![1, 0])
+        );
+        assert_eq!(dressing("<Dressing />").unwrap().int, None);
+        assert!(dressing("<Dressing><int>256</int></Dressing>").is_err());
+        assert!(dressing("<Dressing><int>-1</int></Dressing>").is_err());
+
+        let parsed = Dressing {
+            text: None,
+            int: Some(vec![2, 3]),
+        };
+        let xml = quick_xml::se::to_string(&parsed).unwrap();
+        assert_eq!(xml, "<Dressing><int>2</int><int>3</int></Dressing>");
+        assert_eq!(dressing(&xml).unwrap(), parsed);
+    }
 }
@@ -0,0 +1,388 @@
+//! Discovers which XML elements and attributes a schema type understands.
+//!
+//! The fleet structs all use `deny_unknown_fields`, so a single element added
+//! by a game update makes the whole file unreadable. [`Schema::of`] runs a
+//! type's `Deserialize` implementation against a fake deserializer that
+//! records the field names requested at every level, producing a tree that can
+//! be used to find (and set aside) the parts of a document the structs don't
+//! know about.
+
+use std::collections::HashMap;
+
+use serde::{
+    de::{
+        self, value::Error, DeserializeSeed, EnumAccess, IntoDeserializer,
+        MapAccess, SeqAccess, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize,
+};
+
+use crate::xml_float::XML_FLOAT;
+
+/// Nested structs deeper than this are assumed to be recursive.
+const MAX_DEPTH: usize = 32;
+
+/// The fields of a schema struct, keyed by their serde names (`Name`, `@type`,
+/// `$text`, ...).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Schema {
+    /// Whether this is a struct. Primitive values have no fields but accept
+    /// any content.
+    pub is_struct: bool,
+    /// Whether this is a floating point value, which may be written in more
+    /// than one way, e.g. `1E-05` and `0.00001`.
+    pub is_float: bool,
+    pub fields: HashMap<&'static str, Schema>,
+}
+
+impl Schema {
+    pub fn of<'de, T: Deserialize<'de>>() -> Schema {
+        let mut schema = Schema::default();
+        // Any error means part of the type couldn't be probed, whatever was
+        // recorded up to that point is still accurate.
+        let _ = T::deserialize(Probe {
+            schema: &mut schema,
+            depth: 0,
+        });
+        schema
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Schema> {
+        self.fields.get(name)
+    }
+
+    /// Whether an element called `name` can appear directly inside this one.
+    pub fn has_element(&self, name: &str) -> bool {
+        !self.is_struct || self.fields.contains_key(name)
+    }
+
+    /// Whether an attribute called `name` (as written in the document, e.g.
+    /// `xsi:type`) is allowed on this element.
+    pub fn has_attribute(&self, name: &str) -> bool {
+        if !self.is_struct {
+            return true;
+        }
+        // quick-xml strips namespace prefixes from attribute names, except on
+        // namespace declarations
+        let local = if name.starts_with("xmlns") {
+            name
+        } else {
+            name.rsplit(':').next().unwrap_or(name)
+        };
+        self.fields
+            .keys()
+            .any(|field| field.strip_prefix('@') == Some(local))
+    }
+}
+
+struct Probe<'a> {
+    schema: &'a mut Schema,
+    depth: usize,
+}
+
+impl<'de> de::Deserializer<'de> for Probe<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        // Strings are the most forgiving value: they deserialize into strings,
+        // and "0" parses into any number for `FromStr` based fields.
+        visitor.visit_borrowed_str("0")
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_bool(false)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i8(0)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i16(0)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i32(0)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_i64(0)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_u8(0)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_u16(0)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_u32(0)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_u64(0)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.schema.is_float = true;
+        visitor.visit_f32(0.0)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.schema.is_float = true;
+        visitor.visit_f64(0.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if name == XML_FLOAT {
+            self.schema.is_float = true;
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(ProbeSeq {
+            schema: self.schema,
+            depth: self.depth,
+            remaining: 1,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(ProbeSeq {
+            schema: self.schema,
+            depth: self.depth,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if self.depth > MAX_DEPTH {
+            return Err(de::Error::custom("schema is recursive"));
+        }
+        self.schema.is_struct = true;
+        visitor.visit_map(ProbeMap {
+            schema: self.schema,
+            depth: self.depth + 1,
+            fields: fields.iter(),
+            current: None,
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let variant = variants
+            .first()
+            .ok_or_else(|| de::Error::custom("enum has no variants"))?;
+        visitor.visit_enum(ProbeEnum {
+            variant,
+            depth: self.depth,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit_struct tuple_struct map
+        identifier ignored_any
+    }
+}
+
+struct ProbeSeq<'a> {
+    schema: &'a mut Schema,
+    depth: usize,
+    remaining: usize,
+}
+
+impl<'de> SeqAccess<'de> for ProbeSeq<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(Probe {
+            schema: self.schema,
+            depth: self.depth,
+        })
+        .map(Some)
+    }
+}
+
+struct ProbeMap<'a> {
+    schema: &'a mut Schema,
+    depth: usize,
+    fields: std::slice::Iter<'static, &'static str>,
+    current: Option<&'static str>,
+}
+
+impl<'de> MapAccess<'de> for ProbeMap<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some(field) = self.fields.next() else {
+            return Ok(None);
+        };
+        self.current = Some(field);
+        seed.deserialize(field.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Error> {
+        let field = self
+            .current
+            .take()
+            .ok_or_else(|| de::Error::custom("value requested before key"))?;
+        seed.deserialize(Probe {
+            schema: self.schema.fields.entry(field).or_default(),
+            depth: self.depth,
+        })
+    }
+}
+
+struct ProbeEnum {
+    variant: &'static str,
+    depth: usize,
+}
+
+impl<'de> EnumAccess<'de> for ProbeEnum {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+/// Enums aren't elements, so the contents of a variant aren't recorded.
+impl<'de> VariantAccess<'de> for ProbeEnum {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Error> {
+        seed.deserialize(Probe {
+            schema: &mut Schema::default(),
+            depth: self.depth,
+        })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(
+            Probe {
+                schema: &mut Schema::default(),
+                depth: self.depth,
+            },
+            len,
+            visitor,
+        )
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_struct(
+            Probe {
+                schema: &mut Schema::default(),
+                depth: self.depth,
+            },
+            "",
+            fields,
+            visitor,
+        )
+    }
+}
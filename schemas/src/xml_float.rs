@@ -0,0 +1,181 @@
+//! Floats in the format written by the game.
+//!
+//! The game saves fleets with .NET's `XmlSerializer`, which writes infinity and
+//! NaN as `INF`, `-INF` and `NaN`. Rust formats them as `inf`, `-inf` and
+//! `NaN`, which the game can't read back. Use with
+//! `#[serde_as(as = "XmlFloat")]`.
+
+use std::{fmt, marker::PhantomData, str::FromStr};
+
+use serde::{
+    de::{self, Unexpected, Visitor},
+    Deserializer, Serializer,
+};
+use serde_with::{DeserializeAs, SerializeAs};
+
+pub struct XmlFloat;
+
+/// Name of the newtype floats are deserialized through, so that
+/// [`Schema`](crate::probe::Schema) can tell them apart from strings.
+pub(crate) const XML_FLOAT: &str = "$schemas::XmlFloat";
+
+macro_rules! impl_xml_float {
+    ($ty:ty, $serialize:ident) => {
+        impl SerializeAs<$ty> for XmlFloat {
+            fn serialize_as<S: Serializer>(
+                value: &$ty,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                if value.is_nan() {
+                    serializer.serialize_str("NaN")
+                } else if *value == <$ty>::INFINITY {
+                    serializer.serialize_str("INF")
+                } else if *value == <$ty>::NEG_INFINITY {
+                    serializer.serialize_str("-INF")
+                } else {
+                    serializer.$serialize(*value)
+                }
+            }
+        }
+
+        impl<'de> DeserializeAs<'de, $ty> for XmlFloat {
+            fn deserialize_as<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<$ty, D::Error> {
+                deserializer.deserialize_newtype_struct(
+                    XML_FLOAT,
+                    XmlFloatVisitor(PhantomData),
+                )
+            }
+        }
+
+        impl Float for $ty {
+            const INFINITY: Self = <$ty>::INFINITY;
+            const NEG_INFINITY: Self = <$ty>::NEG_INFINITY;
+            const NAN: Self = <$ty>::NAN;
+
+            fn from_f64(value: f64) -> Self {
+                value as $ty
+            }
+        }
+    };
+}
+
+impl_xml_float!(f32, serialize_f32);
+impl_xml_float!(f64, serialize_f64);
+
+trait Float: FromStr {
+    const INFINITY: Self;
+    const NEG_INFINITY: Self;
+    const NAN: Self;
+
+    fn from_f64(value: f64) -> Self;
+}
+
+struct XmlFloatVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Float> Visitor<'de> for XmlFloatVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a floating point number, INF, -INF or NaN")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        match value.trim() {
+            "INF" => Ok(T::INFINITY),
+            "-INF" => Ok(T::NEG_INFINITY),
+            "NaN" => Ok(T::NAN),
+            trimmed => trimmed
+                .parse()
+                .map_err(|_| E::invalid_value(Unexpected::Str(value), &self)),
+        }
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<T, E> {
+        Ok(T::from_f64(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<T, E> {
+        Ok(T::from_f64(value as f64))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<T, E> {
+        Ok(T::from_f64(value as f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_with::serde_as;
+
+    use super::*;
+
+    #[serde_as]
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Floats {
+        #[serde_as(as = "XmlFloat")]
+        single: f32,
+        #[serde_as(as = "XmlFloat")]
+        double: f64,
+    }
+
+    fn xml(single: &str, double: &str) -> String {
+        format!(
+            "<Floats><single>{single}</single><double>{double}</double></\
+             Floats>"
+        )
+    }
+
+    #[test]
+    fn round_trip() {
+        for (text, value) in [
+            ("INF", f64::INFINITY),
+            ("-INF", f64::NEG_INFINITY),
+            ("1.5", 1.5),
+            ("-0.25", -0.25),
+            ("100", 100.0),
+        ] {
+            let floats: Floats =
+                quick_xml::de::from_str(&xml(text, text)).unwrap();
+            assert_eq!(
+                floats,
+                Floats {
+                    single: value as f32,
+                    double: value,
+                }
+            );
+            assert_eq!(
+                quick_xml::se::to_string(&floats).unwrap(),
+                xml(text, text)
+            );
+        }
+
+        // NaN never equals itself, so is checked separately
+        let floats: Floats =
+            quick_xml::de::from_str(&xml("NaN", "NaN")).unwrap();
+        assert!(floats.single.is_nan() && floats.double.is_nan());
+        assert_eq!(
+            quick_xml::se::to_string(&floats).unwrap(),
+            xml("NaN", "NaN")
+        );
+    }
+
+    #[test]
+    fn invalid_floats() {
+        for text in ["1.5.0", "", "one", "0x10"] {
+            assert!(
+                quick_xml::de::from_str::<Floats>(&xml(text, "1")).is_err(),
+                "'{text}' parsed as a float"
+            );
+        }
+    }
+}
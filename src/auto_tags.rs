@@ -0,0 +1,296 @@
+//! Rules that tag fleets automatically, such as "if faction is ANS and points
+//! <= 3000, add tag `3k`". Stored in `auto_tags.toml` next to `tags.toml`:
+//!
+//! ```toml
+//! [[rule]]
+//! tag = "3k"
+//! color = "#ff8000"
+//! faction = "ans"
+//! max_points = 3000
+//!
+//! [[rule]]
+//! tag = "carrier"
+//! hull = "Carrier"
+//! ```
+//!
+//! A rule adds its tag to every fleet matching all of its conditions. Rules
+//! only ever add tags, so removing a tag from a fleet by hand lasts until
+//! another program changes the fleet or the rules are applied again.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use color_eyre::{Report, Result, eyre::eyre};
+use schemas::Fleet;
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+use tracing::{info, warn};
+
+use crate::{
+    fleet_data::FleetData,
+    system::{
+        config::TagStorage,
+        fleet_io::update_fleet,
+        load_fleets::refresh_fleets,
+        prefs::{init_prefs, load_prefs},
+    },
+    tags::{
+        Color, TAGS_REPO, Tag, get_tags_from_description, set_fleet_tags,
+        stored_tags, tag_storage, tagged_description,
+    },
+    util::search::faction_matches,
+};
+
+pub static AUTO_TAGS: OnceLock<Mutex<AutoTagRules>> = OnceLock::new();
+const AUTO_TAGS_FILE: &str = "auto_tags.toml";
+
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct AutoTagRule {
+    /// Tag to add to matching fleets
+    pub tag: String,
+    /// Colour of the tag, defaults to the colour last used for it or its
+    /// group's palette
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub color: Option<Color>,
+    /// Faction name or abbreviation, e.g. `ans` or `protectorate`
+    pub faction: Option<String>,
+    /// Part of the hull type of any ship in the fleet
+    pub hull: Option<String>,
+    pub min_points: Option<usize>,
+    pub max_points: Option<usize>,
+    pub min_ships: Option<usize>,
+    pub max_ships: Option<usize>,
+}
+
+impl AutoTagRule {
+    pub fn matches(&self, fleet: &Fleet) -> bool {
+        let ships = fleet
+            .ships
+            .as_ref()
+            .and_then(|ships| ships.ship.as_deref())
+            .unwrap_or_default();
+        let in_range =
+            |value: usize, min: Option<usize>, max: Option<usize>| {
+                min.is_none_or(|min| value >= min)
+                    && max.is_none_or(|max| value <= max)
+            };
+
+        self.faction.as_ref().is_none_or(|faction| {
+            faction_matches(&fleet.faction_key, &faction.to_lowercase())
+        }) && self.hull.as_ref().is_none_or(|hull| {
+            let hull = hull.to_lowercase();
+            ships
+                .iter()
+                .any(|ship| ship.hull_type.to_lowercase().contains(&hull))
+        }) && in_range(fleet.total_points, self.min_points, self.max_points)
+            && in_range(ships.len(), self.min_ships, self.max_ships)
+    }
+
+    /// The tag to add, or `None` if no colour is known for it.
+    fn tag(&self) -> Option<Tag> {
+        let color = self.color.clone().or_else(|| {
+            TAGS_REPO.get()?.lock().unwrap().suggest_color(&self.tag)
+        })?;
+        Some(Tag {
+            name: self.tag.clone(),
+            color,
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub struct AutoTagRules {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<AutoTagRule>,
+}
+
+impl AutoTagRules {
+    /// Add the tags of every rule matching `fleet` to `tags`, its current tags,
+    /// returning whether any were added.
+    pub fn add_tags(&self, fleet: &Fleet, tags: &mut Vec<Tag>) -> bool {
+        let mut changed = false;
+        for rule in self.rules.iter().filter(|rule| rule.matches(fleet)) {
+            if tags.iter().any(|tag| tag.name == rule.tag) {
+                continue;
+            }
+            let Some(tag) = rule.tag() else {
+                warn!(
+                    "Auto tag rule for '{}' has no colour, and the tag hasn't \
+                     been used before",
+                    rule.tag
+                );
+                continue;
+            };
+            tags.push(tag);
+            changed = true;
+        }
+        changed
+    }
+}
+
+pub fn init_auto_tags() {
+    init_prefs(&AUTO_TAGS, AUTO_TAGS_FILE, "auto tag rules");
+}
+
+/// Read the rules again, so edits to the file take effect.
+pub fn reload_auto_tags() -> Result<()> {
+    let rules = load_auto_tags()?;
+    *AUTO_TAGS
+        .get()
+        .ok_or(eyre!("Auto tag rules not yet loaded"))?
+        .lock()
+        .unwrap() = rules;
+    Ok(())
+}
+
+pub fn load_auto_tags() -> Result<AutoTagRules> {
+    load_prefs(AUTO_TAGS_FILE, "auto tag rules")
+}
+
+/// Tags of `fleet`, stored at `path`, once the loaded rules are applied. `None`
+/// if the rules add nothing or haven't been loaded.
+pub fn auto_tagged(path: &Path, fleet: &Fleet) -> Option<Vec<Tag>> {
+    let rules = AUTO_TAGS.get()?.lock().unwrap();
+    let mut tags =
+        stored_tags(path, fleet.description.as_deref().unwrap_or_default());
+    rules.add_tags(fleet, &mut tags).then_some(tags)
+}
+
+/// Apply the loaded rules to `fleet`, stored at `path`, returning whether any
+/// tags were added. Tags stored in the description are added to `fleet`, which
+/// must then be written.
+pub fn apply_auto_tags(path: &Path, fleet: &mut Fleet) -> Result<bool> {
+    let Some(tags) = auto_tagged(path, fleet) else {
+        return Ok(false);
+    };
+    match tag_storage() {
+        TagStorage::Description => {
+            let (_, desc) = get_tags_from_description(
+                fleet.description.as_deref().unwrap_or_default(),
+            );
+            fleet.description = Some(tagged_description(path, &tags, &desc)?);
+        }
+        TagStorage::Database => set_fleet_tags(path, &tags)?,
+    }
+    Ok(true)
+}
+
+/// Apply the loaded rules to each fleet in `paths`, writing the fleets that
+/// changed. Returns the fleets that were changed, and those that failed with
+/// the reason.
+pub fn auto_tag_fleets(
+    paths: &[PathBuf],
+) -> (Vec<PathBuf>, Vec<(PathBuf, Report)>) {
+    let mut changed = Vec::new();
+    let mut failed = Vec::new();
+    for path in paths {
+        match auto_tag_fleet(path) {
+            Ok(true) => changed.push(path.clone()),
+            Ok(false) => {}
+            Err(err) => {
+                warn!("Failed to auto tag '{}': {:?}", path.display(), err);
+                failed.push((path.clone(), err));
+            }
+        }
+    }
+    info!("Auto tagged {} of {} fleets", changed.len(), paths.len());
+    (changed, failed)
+}
+
+/// Apply the loaded rules to the `changed` fleets found by loading `fleets`,
+/// replacing those that were rewritten with their new data.
+pub fn auto_tag_loaded(fleets: &mut [FleetData], changed: &[PathBuf]) {
+    let (tagged, _) = auto_tag_fleets(changed);
+    let refreshed = match refresh_fleets(&tagged) {
+        Ok(refreshed) => refreshed,
+        Err(err) => {
+            warn!(?err, "Failed to reload auto tagged fleets");
+            return;
+        }
+    };
+    for fleet_data in refreshed {
+        if let Some(fleet) = fleets
+            .iter_mut()
+            .find(|fleet| fleet.path == fleet_data.path)
+        {
+            *fleet = fleet_data;
+        }
+    }
+}
+
+/// The fleet may be open in the editor, so tagging it mustn't let the editor
+/// overwrite changes made by another program.
+fn auto_tag_fleet(path: &Path) -> Result<bool> {
+    let mut tagged = false;
+    update_fleet(path, |fleet| {
+        let description = fleet.description.clone();
+        tagged = apply_auto_tags(path, fleet)?;
+        Ok(fleet.description != description)
+    })?;
+    Ok(tagged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fleet_io::read_fleet;
+
+    const GOLDEN_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    #[test]
+    fn rules() {
+        let rules: AutoTagRules = toml::from_str(
+            r##"
+            [[rule]]
+            tag = "3k"
+            color = "#ff8000"
+            faction = "ANS"
+            max_points = 3000
+
+            [[rule]]
+            tag = "screen"
+            color = "#0000ff"
+            hull = "corvette"
+
+            [[rule]]
+            tag = "liner"
+            color = "#00ffff"
+            hull = "container"
+            min_ships = 2
+
+            [[rule]]
+            tag = "meta"
+            color = "#ff0000"
+            "##,
+        )
+        .unwrap();
+        let fleet =
+            |name: &str| read_fleet(Path::new(GOLDEN_DIR).join(name)).unwrap();
+        let names = |tags: &[Tag]| {
+            tags.iter().map(|tag| tag.name.clone()).collect::<Vec<_>>()
+        };
+        let meta = Tag {
+            name: "meta".to_string(),
+            color: "00ff00".parse().unwrap(),
+        };
+
+        let ans = fleet("corvette-screen.fleet");
+        let mut ans_tags = vec![meta.clone()];
+        assert!(rules.add_tags(&ans, &mut ans_tags));
+        assert_eq!(names(&ans_tags), ["meta", "3k", "screen"]);
+        // Tags the fleet already has keep their colour
+        assert_eq!(ans_tags[0], meta);
+        // Applying the rules again changes nothing
+        assert!(!rules.add_tags(&ans, &mut ans_tags));
+
+        let osp = fleet("liner-escort.fleet");
+        let mut osp_tags = vec![meta];
+        assert!(rules.add_tags(&osp, &mut osp_tags));
+        assert_eq!(names(&osp_tags), ["meta", "liner"]);
+    }
+}
@@ -0,0 +1,463 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{Args, Subcommand};
+use color_eyre::{
+    Result,
+    eyre::{Context, bail, eyre},
+};
+use tracing::{info, warn};
+
+use crate::{
+    fleet_data::FleetData,
+    system::{
+        config::{APP_CONFIG, TagStorage, load_app_config},
+        fleet_io::{read_fleet, read_fleet_tolerant, write_fleet},
+        load_fleets::{is_fleet_file, load_fleets, refresh_fleets},
+    },
+    tags::{
+        Color, TAGS_REPO, Tag, TagChange, fleet_tags, set_fleet_tags,
+        stored_tags,
+    },
+};
+
+/// Headless commands, run instead of launching the app window.
+#[derive(Subcommand)]
+pub enum Command {
+    /// List all fleets in the saves directory
+    List,
+    /// List fleets matching a search query, using the same syntax as the
+    /// search bar
+    Search {
+        #[clap(required = true)]
+        query: Vec<String>,
+    },
+    /// Add or remove fleet tags
+    #[clap(subcommand)]
+    Tag(TagCommand),
+    /// Merge several fleets into a new fleet file
+    Merge {
+        /// Fleets to merge, by path or name
+        #[clap(required = true, num_args = 2..)]
+        fleets: Vec<String>,
+        /// Name of the merged fleet
+        #[clap(short, long)]
+        name: String,
+        /// Path of the new fleet file
+        #[clap(short, long)]
+        output: PathBuf,
+    },
+    /// Backup all fleets to a zip file or folder
+    Backup {
+        output: PathBuf,
+        /// Copy the fleets into a folder instead of a zip file
+        #[clap(long)]
+        folder: bool,
+    },
+    /// Check that fleets can be read by NebTools, reporting any that can't
+    Validate {
+        /// Fleet files to check, defaults to every fleet in the saves
+        /// directory
+        fleets: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TagCommand {
+    /// Add a tag to a fleet
+    Add(TagArgs),
+    /// Remove a tag from a fleet
+    Remove(TagArgs),
+    /// Rename a tag in every fleet. Fleets that already have a tag with the
+    /// new name are left with just that tag
+    Rename {
+        from: String,
+        to: String,
+        #[clap(flatten)]
+        preview: PreviewArgs,
+    },
+    /// Remove a tag from every fleet
+    Delete {
+        name: String,
+        #[clap(flatten)]
+        preview: PreviewArgs,
+    },
+    /// Apply the rules in auto_tags.toml to every fleet
+    Auto {
+        #[clap(flatten)]
+        preview: PreviewArgs,
+    },
+    /// Change the colour of a tag in every fleet
+    Recolor {
+        name: String,
+        /// New hex colour of the tag
+        color: String,
+        #[clap(flatten)]
+        preview: PreviewArgs,
+    },
+    /// Choose where tags are stored, moving every fleet's tags there
+    Storage { storage: TagStorage },
+    /// Write tags stored in the database into each fleet's description, so
+    /// they're visible in game
+    Export,
+}
+
+#[derive(Args)]
+pub struct PreviewArgs {
+    /// List the fleets that would be modified without changing them
+    #[clap(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct TagArgs {
+    /// Fleet to edit, by path or name
+    fleet: String,
+    /// Name of the tag
+    tag: String,
+    /// Hex colour of the tag, defaults to the colour last used for this tag or
+    /// its group's palette
+    #[clap(short, long)]
+    color: Option<String>,
+}
+
+pub fn run(command: Command, saves_dir: Option<PathBuf>) -> Result<()> {
+    load_app_config()?;
+    if let Some(saves_dir) = saves_dir {
+        APP_CONFIG.get().unwrap().lock().unwrap().saves_dir = saves_dir;
+    }
+    crate::tags::init_tags();
+    crate::tag_db::init_tag_db();
+    crate::auto_tags::init_auto_tags();
+
+    match command {
+        Command::List => {
+            for fleet in load_fleets(None)? {
+                print_fleet(&fleet);
+            }
+        }
+        Command::Search { query } => {
+            let filters =
+                crate::util::search::parse_search_text(&query.join(" "))
+                    .wrap_err("Invalid search")?;
+            let fleets = load_fleets(None)?;
+            for (idx, _) in filters.rank(&fleets) {
+                print_fleet(&fleets[idx]);
+            }
+        }
+        Command::Tag(command) => match command {
+            TagCommand::Add(args) => add_tag(args)?,
+            TagCommand::Remove(args) => remove_tag(args)?,
+            TagCommand::Rename { from, to, preview } => {
+                let fleets = load_fleets(None)?;
+                let color = tag_color(&fleets, &to)
+                    .or_else(|| tag_color(&fleets, &from))
+                    .ok_or(eyre!("No fleet has tag '{from}'"))?;
+                let to = Tag { name: to, color };
+                change_tag(&fleets, TagChange::Rename { from, to }, preview)?
+            }
+            TagCommand::Auto { preview } => auto_tag(preview)?,
+            TagCommand::Delete { name, preview } => change_tag(
+                &load_fleets(None)?,
+                TagChange::Delete(name),
+                preview,
+            )?,
+            TagCommand::Recolor {
+                name,
+                color,
+                preview,
+            } => {
+                let color = color.parse::<Color>().map_err(|err| {
+                    eyre!("Invalid tag colour '{color}': {err}")
+                })?;
+                change_tag(
+                    &load_fleets(None)?,
+                    TagChange::Recolor(name, color),
+                    preview,
+                )?
+            }
+            TagCommand::Storage { storage } => {
+                if storage == crate::tags::tag_storage() {
+                    info!("Tags are already stored in {:?}", storage);
+                    return Ok(());
+                }
+                let (changed, failed) = crate::tag_db::move_tags(storage)?;
+                println!("{} fleets modified", changed.len());
+                if !failed.is_empty() {
+                    bail!("{} fleets could not be modified", failed.len());
+                }
+            }
+            TagCommand::Export => {
+                if crate::tags::tag_storage() == TagStorage::Description {
+                    bail!("Tags are already stored in fleet descriptions");
+                }
+                let (changed, failed) =
+                    crate::tag_db::export_tags(&all_fleet_paths()?);
+                for path in &changed {
+                    println!("{}", path.display());
+                }
+                println!("{} fleets modified", changed.len());
+                if !failed.is_empty() {
+                    bail!("{} fleets could not be modified", failed.len());
+                }
+            }
+        },
+        Command::Merge {
+            fleets,
+            name,
+            output,
+        } => {
+            let all_fleets = load_fleets(None)?;
+            let mut merging = Vec::new();
+            for fleet in &fleets {
+                let path = find_fleet(&all_fleets, fleet)?;
+                merging.push(read_fleet(&path).wrap_err(format!(
+                    "Failed to read fleet '{}'",
+                    path.display()
+                ))?);
+            }
+            if output.exists() {
+                bail!("'{}' already exists", output.display());
+            }
+            info!("Merging {} fleets into {}", merging.len(), output.display());
+            let out = crate::util::merge::merge_fleets(name, merging)?;
+            write_fleet(&output, &out)?;
+        }
+        Command::Backup { output, folder } => {
+            if folder {
+                if !output.exists() {
+                    std::fs::create_dir_all(&output)
+                        .wrap_err("Failed to create backup folder")?;
+                }
+                crate::system::backup::backup_to_folder(&output)?;
+            } else {
+                crate::system::backup::backup_to_zip(&output)?;
+            }
+            info!("Backed up fleets to '{}'", output.display());
+        }
+        Command::Validate { fleets } => {
+            let fleets = if fleets.is_empty() {
+                all_fleet_paths()?
+            } else {
+                fleets
+            };
+            let mut invalid = 0;
+            for path in &fleets {
+                match read_fleet_tolerant(path) {
+                    Ok((_, unknown)) if !unknown.is_empty() => println!(
+                        "NEWER {}: unknown fields {}",
+                        path.display(),
+                        unknown.join(", ")
+                    ),
+                    Ok(_) => {}
+                    Err(err) => {
+                        invalid += 1;
+                        println!("INVALID {}: {:?}", path.display(), err);
+                    }
+                }
+            }
+            println!(
+                "{} of {} fleets valid",
+                fleets.len() - invalid,
+                fleets.len()
+            );
+            if invalid > 0 {
+                bail!("{invalid} fleets failed validation");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_fleet(fleet: &FleetData) {
+    println!("{}\t{}", fleet.path.display(), fleet.name);
+}
+
+/// Resolve a fleet given on the command line, either as a path to a fleet file
+/// (absolute, or relative to the Fleets directory) or as a fleet name.
+fn find_fleet(fleets: &[FleetData], fleet: &str) -> Result<PathBuf> {
+    let path = Path::new(fleet);
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+    let fleets_dir = APP_CONFIG
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .saves_dir
+        .join("Fleets");
+    if fleets_dir.join(path).is_file() {
+        return Ok(fleets_dir.join(path));
+    }
+
+    let mut matches = fleets.iter().filter(|f| f.name == fleet);
+    match (matches.next(), matches.next()) {
+        (Some(found), None) => Ok(found.path.clone()),
+        (Some(_), Some(_)) => Err(eyre!(
+            "Multiple fleets are named '{fleet}', use a path instead"
+        )),
+        (None, _) => Err(eyre!("No fleet found matching '{fleet}'")),
+    }
+}
+
+/// Every fleet file in the Fleets directory, skipping excluded directories.
+fn all_fleet_paths() -> Result<Vec<PathBuf>> {
+    let (fleets_dir, excluded_patterns) = {
+        let app_config = APP_CONFIG.get().unwrap().lock().unwrap();
+        (
+            app_config.saves_dir.join("Fleets"),
+            app_config
+                .excluded_dirs
+                .iter()
+                .filter_map(|x| glob::Pattern::from_str(x).ok())
+                .collect::<Vec<_>>(),
+        )
+    };
+    let pattern = glob::Pattern::escape(&fleets_dir.display().to_string());
+    let mut paths = Vec::new();
+    for entry in glob::glob(&format!("{pattern}/**/*.fleet"))
+        .wrap_err("Invalid fleets directory")?
+    {
+        match entry {
+            Ok(path) if is_fleet_file(&path, &excluded_patterns) => {
+                paths.push(path)
+            }
+            Ok(_) => {}
+            Err(err) => warn!(%err, "Failed to read fleet path"),
+        }
+    }
+    Ok(paths)
+}
+
+fn add_tag(args: TagArgs) -> Result<()> {
+    let path = find_fleet(&load_fleets(None)?, &args.fleet)?;
+    let color = match &args.color {
+        Some(hex) => hex
+            .parse::<Color>()
+            .map_err(|err| eyre!("Invalid tag colour '{hex}': {err}"))?,
+        None => TAGS_REPO
+            .get()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .suggest_color(&args.tag)
+            .ok_or(eyre!(
+                "Tag '{}' has not been used before, a colour must be given",
+                args.tag
+            ))?,
+    };
+
+    let fleet = read_fleet(&path)?;
+    let mut tags =
+        stored_tags(&path, fleet.description.as_deref().unwrap_or_default());
+    if tags.iter().any(|tag| tag.name == args.tag) {
+        info!("Fleet already has tag '{}'", args.tag);
+        return Ok(());
+    }
+    tags.push(Tag {
+        name: args.tag.clone(),
+        color: color.clone(),
+    });
+    // Reads the groups from the tag repository, so it can't be locked here
+    set_fleet_tags(&path, &tags)?;
+    TAGS_REPO
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .add_tag(args.tag, color);
+
+    Ok(())
+}
+
+/// Colour of the tag `name`, as last used or as found in a fleet.
+fn tag_color(fleets: &[FleetData], name: &str) -> Option<Color> {
+    if let Some(color) = TAGS_REPO.get().unwrap().lock().unwrap().tags.get(name)
+    {
+        return Some(color.clone());
+    }
+    fleets.iter().find_map(|fleet| {
+        fleet_tags(fleet)
+            .into_iter()
+            .find(|tag| tag.name == name)
+            .map(|tag| tag.color)
+    })
+}
+
+fn change_tag(
+    fleets: &[FleetData],
+    change: TagChange,
+    preview: PreviewArgs,
+) -> Result<()> {
+    let affected = crate::tags::fleets_affected_by(fleets, &change);
+    for fleet in &affected {
+        print_fleet(fleet);
+    }
+    if preview.dry_run {
+        println!("{} fleets would be modified", affected.len());
+        return Ok(());
+    }
+    let paths = affected
+        .iter()
+        .map(|fleet| fleet.path.clone())
+        .collect::<Vec<_>>();
+    let failed = crate::tags::apply_tag_change(&paths, &change);
+    println!("{} fleets modified", paths.len() - failed.len());
+    if !failed.is_empty() {
+        bail!("{} fleets could not be modified", failed.len());
+    }
+    Ok(())
+}
+
+fn auto_tag(preview: PreviewArgs) -> Result<()> {
+    let paths = all_fleet_paths()?;
+    if preview.dry_run {
+        let mut count = 0;
+        for path in &paths {
+            let fleet = match read_fleet(path) {
+                Ok(fleet) => fleet,
+                Err(err) => {
+                    warn!("Failed to read '{}': {:?}", path.display(), err);
+                    continue;
+                }
+            };
+            if crate::auto_tags::auto_tagged(path, &fleet).is_some() {
+                println!("{}\t{}", path.display(), fleet.name);
+                count += 1;
+            }
+        }
+        println!("{count} fleets would be modified");
+        return Ok(());
+    }
+    let (changed, failed) = crate::auto_tags::auto_tag_fleets(&paths);
+    // Keeps the rewritten fleets from being parsed again on the next load
+    refresh_fleets(&changed)?;
+    for path in &changed {
+        println!("{}", path.display());
+    }
+    println!("{} fleets modified", changed.len());
+    if !failed.is_empty() {
+        bail!("{} fleets could not be modified", failed.len());
+    }
+    Ok(())
+}
+
+fn remove_tag(args: TagArgs) -> Result<()> {
+    let path = find_fleet(&load_fleets(None)?, &args.fleet)?;
+
+    let fleet = read_fleet(&path)?;
+    let mut tags =
+        stored_tags(&path, fleet.description.as_deref().unwrap_or_default());
+    let len = tags.len();
+    tags.retain(|tag| tag.name != args.tag);
+    if tags.len() == len {
+        bail!("Fleet does not have tag '{}'", args.tag);
+    }
+    set_fleet_tags(&path, &tags)?;
+
+    Ok(())
+}
@@ -1,63 +1,104 @@
 use std::collections::HashMap;
 
+use color_eyre::{Result, eyre::bail};
 use lazy_static::lazy_static;
 
+use crate::fleet_edit::LINER_HULLS;
+
+/// Number of dressing slots on every liner segment
+pub const DRESSING_SLOTS: usize = 8;
+
+/// Dressings offered by each slot of a segment, in the order the game numbers
+/// them, starting with "None"
+type SegmentDressings = [&'static [&'static str]; DRESSING_SLOTS];
+
+/// Dressings offered by every slot of the stock liner segments. They're
+/// lettered by variant, like segments and bridges are, and cover every index
+/// the game writes.
+const SLOT_DRESSINGS: &[&str] = &["None", "A", "B", "C"];
+
 lazy_static! {
-    /// Map of LN dressings, (segment key, dressing slot) -> dressing idx -> dressing title
-    pub static ref LN_DRESSINGS: HashMap<(&'static str, usize), Vec<&'static str>> = HashMap::from_iter([
-        // Bow A
-        (("38e7a28f-1b06-4b73-98ee-f03d1d8a81fe", 0), vec!["None"]),
-        (("38e7a28f-1b06-4b73-98ee-f03d1d8a81fe", 1), vec!["None"]),
-        (("38e7a28f-1b06-4b73-98ee-f03d1d8a81fe", 2), vec!["None"]),
-        (("38e7a28f-1b06-4b73-98ee-f03d1d8a81fe", 3), vec!["None"]),
-        (("38e7a28f-1b06-4b73-98ee-f03d1d8a81fe", 4), vec!["None"]),
-        (("38e7a28f-1b06-4b73-98ee-f03d1d8a81fe", 5), vec!["None"]),
-        (("38e7a28f-1b06-4b73-98ee-f03d1d8a81fe", 6), vec!["None"]),
-        (("38e7a28f-1b06-4b73-98ee-f03d1d8a81fe", 7), vec!["None"]),
-        // Bow B
-        (("29eb9c63-6c47-40f2-8f46-4ed4da8d3386", 0), vec!["None"]),
-        (("29eb9c63-6c47-40f2-8f46-4ed4da8d3386", 1), vec!["None"]),
-        (("29eb9c63-6c47-40f2-8f46-4ed4da8d3386", 2), vec!["None"]),
-        (("29eb9c63-6c47-40f2-8f46-4ed4da8d3386", 3), vec!["None"]),
-        (("29eb9c63-6c47-40f2-8f46-4ed4da8d3386", 4), vec!["None"]),
-        (("29eb9c63-6c47-40f2-8f46-4ed4da8d3386", 5), vec!["None"]),
-        (("29eb9c63-6c47-40f2-8f46-4ed4da8d3386", 6), vec!["None"]),
-        (("29eb9c63-6c47-40f2-8f46-4ed4da8d3386", 7), vec!["None"]),
-        // Bow C
-        (("c534a876-3f8a-4315-a194-5dda0f84c2b3", 0), vec!["None"]),
-        (("c534a876-3f8a-4315-a194-5dda0f84c2b3", 1), vec!["None"]),
-        (("c534a876-3f8a-4315-a194-5dda0f84c2b3", 2), vec!["None"]),
-        (("c534a876-3f8a-4315-a194-5dda0f84c2b3", 3), vec!["None"]),
-        (("c534a876-3f8a-4315-a194-5dda0f84c2b3", 4), vec!["None"]),
-        (("c534a876-3f8a-4315-a194-5dda0f84c2b3", 5), vec!["None"]),
-        (("c534a876-3f8a-4315-a194-5dda0f84c2b3", 6), vec!["None"]),
-        (("c534a876-3f8a-4315-a194-5dda0f84c2b3", 7), vec!["None"]),
-        // Core A
-        (("d4c9a66d-81e6-49ee-9b33-82d7a1522bbf", 0), vec!["None"]),
-        (("d4c9a66d-81e6-49ee-9b33-82d7a1522bbf", 1), vec!["None"]),
-        (("d4c9a66d-81e6-49ee-9b33-82d7a1522bbf", 2), vec!["None"]),
-        (("d4c9a66d-81e6-49ee-9b33-82d7a1522bbf", 3), vec!["None"]),
-        (("d4c9a66d-81e6-49ee-9b33-82d7a1522bbf", 4), vec!["None"]),
-        (("d4c9a66d-81e6-49ee-9b33-82d7a1522bbf", 5), vec!["None"]),
-        (("d4c9a66d-81e6-49ee-9b33-82d7a1522bbf", 6), vec!["None"]),
-        (("d4c9a66d-81e6-49ee-9b33-82d7a1522bbf", 7), vec!["None"]),
-        // Core B
-        (("e2c11e02-b770-495e-a3c2-3dc998eac5a6", 0), vec!["None"]),
-        (("e2c11e02-b770-495e-a3c2-3dc998eac5a6", 1), vec!["None"]),
-        (("e2c11e02-b770-495e-a3c2-3dc998eac5a6", 2), vec!["None"]),
-        (("e2c11e02-b770-495e-a3c2-3dc998eac5a6", 3), vec!["None"]),
-        (("e2c11e02-b770-495e-a3c2-3dc998eac5a6", 4), vec!["None"]),
-        (("e2c11e02-b770-495e-a3c2-3dc998eac5a6", 5), vec!["None"]),
-        (("e2c11e02-b770-495e-a3c2-3dc998eac5a6", 6), vec!["None"]),
-        (("e2c11e02-b770-495e-a3c2-3dc998eac5a6", 7), vec!["None"]),
-        // Core C
-        (("429f178e-e369-4f51-8054-2e01dd0abea1", 0), vec!["None"]),
-        (("429f178e-e369-4f51-8054-2e01dd0abea1", 1), vec!["None"]),
-        (("429f178e-e369-4f51-8054-2e01dd0abea1", 2), vec!["None"]),
-        (("429f178e-e369-4f51-8054-2e01dd0abea1", 3), vec!["None"]),
-        (("429f178e-e369-4f51-8054-2e01dd0abea1", 4), vec!["None"]),
-        (("429f178e-e369-4f51-8054-2e01dd0abea1", 5), vec!["None"]),
-        (("429f178e-e369-4f51-8054-2e01dd0abea1", 6), vec!["None"]),
-        (("429f178e-e369-4f51-8054-2e01dd0abea1", 7), vec!["None"]),
-    ]);
+    /// Dressings of each liner segment, by segment key. Every bow, core and
+    /// stern of the hulls in [`LINER_HULLS`] is listed.
+    pub static ref LN_DRESSINGS: HashMap<&'static str, SegmentDressings> =
+        LINER_HULLS
+            .iter()
+            .flat_map(|hull| (0..3).flat_map(|segment| hull.segments(segment)))
+            .map(|&key| (key, [SLOT_DRESSINGS; DRESSING_SLOTS]))
+            .collect();
+}
+
+/// Dressings that can go in a slot of a segment, empty if the slot doesn't
+/// exist or the segment isn't a liner segment.
+pub fn dressing_options(
+    segment_key: &str,
+    slot: usize,
+) -> &'static [&'static str] {
+    LN_DRESSINGS
+        .get(segment_key)
+        .and_then(|slots| slots.get(slot))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Make sure the game can load a segment with these dressings.
+pub fn validate_dressings(segment_key: &str, dressings: &[u8]) -> Result<()> {
+    match LN_DRESSINGS.get(segment_key) {
+        Some(slots) => validate_segment(segment_key, slots, dressings),
+        None if dressings.iter().all(|&dressing| dressing == 0) => Ok(()),
+        None => bail!("Segment '{segment_key}' has no dressings"),
+    }
+}
+
+fn validate_segment(
+    segment_key: &str,
+    slots: &SegmentDressings,
+    dressings: &[u8],
+) -> Result<()> {
+    for (slot, &dressing) in dressings.iter().enumerate() {
+        let options = slots.get(slot).copied().unwrap_or_default();
+        if dressing != 0 && dressing as usize >= options.len() {
+            bail!(
+                "Dressing {dressing} isn't available in slot {} of segment \
+                 '{segment_key}'",
+                slot + 1
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_dressings() {
+        let mut slots: SegmentDressings = [&["None"]; DRESSING_SLOTS];
+        slots[0] = &["None", "Antenna", "Floodlights"];
+
+        validate_segment("bow", &slots, &[2, 0, 0]).unwrap();
+        // Every slot can be left empty
+        validate_segment("bow", &slots, &[0; DRESSING_SLOTS]).unwrap();
+        let err = validate_segment("bow", &slots, &[0, 1]).unwrap_err();
+        assert!(err.to_string().contains("slot 2"));
+        assert!(validate_segment("bow", &slots, &[3]).is_err());
+    }
+
+    #[test]
+    fn every_liner_segment_is_catalogued() {
+        for hull in LINER_HULLS.iter() {
+            for segment in 0..3 {
+                for key in hull.segments(segment) {
+                    assert!(!dressing_options(key, 0).is_empty(), "{key}");
+                    validate_dressings(key, &[3, 0, 2]).unwrap();
+                    assert!(validate_dressings(key, &[4]).is_err());
+                    assert!(validate_dressings(key, &[0, 9]).is_err());
+                }
+            }
+        }
+        // Segments that aren't liner segments can't have dressings
+        validate_dressings("not-a-segment", &[0, 0]).unwrap();
+        assert!(validate_dressings("not-a-segment", &[1]).is_err());
+    }
 }
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::SystemTime};
 
 use serde::{Deserialize, Serialize};
 
@@ -8,4 +8,20 @@ pub struct FleetData {
     pub short_path: PathBuf,
     pub name: String,
     pub description: String,
+    /// Fields in the fleet file that NebTools doesn't understand, usually
+    /// because the fleet was saved by a newer version of the game.
+    pub unknown_fields: Vec<String>,
+    pub total_points: usize,
+    pub faction_key: String,
+    pub ship_count: usize,
+    pub ship_names: Vec<String>,
+    /// Each distinct hull in the fleet, sorted.
+    pub hull_types: Vec<String>,
+    /// Designation and nickname of each missile template, e.g.
+    /// `SGM-2 Tempest`.
+    pub missile_names: Vec<String>,
+    /// Steam workshop IDs of the mods the fleet uses.
+    pub mod_dependencies: Vec<String>,
+    /// When the fleet file was last modified, if known.
+    pub modified: Option<SystemTime>,
 }
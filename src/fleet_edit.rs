@@ -1,7 +1,10 @@
+use color_eyre::{Result, eyre::bail};
 use lazy_static::lazy_static;
-use schemas::Ship;
+use schemas::{Fleet, HullConfig, SegmentConfiguration, Ship};
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use tracing::{debug, warn};
+
+use crate::dressings::{DRESSING_SLOTS, validate_dressings};
 
 lazy_static! {
     pub static ref BRIDGE_MODELS: Vec<&'static str> = Vec::from_iter([
@@ -40,9 +43,130 @@ lazy_static! {
         "2dbd82fe-d365-4367-aef5-9bb2d3528528",
         "aff1eba2-048e-4477-956b-574f4d468f1d",
     ]);
+    /// Every modular liner hull the editor understands. Supporting a new liner
+    /// only needs its segment and bridge tables added here.
+    pub static ref LINER_HULLS: Vec<LinerHull> = Vec::from_iter([
+        LinerHull {
+            hull_type: "Stock/Bulk Hauler",
+            bows: &BULK_BOWS,
+            cores: &BULK_CORES,
+            sterns: &BULK_STERNS,
+            bridges: &BRIDGE_MODELS,
+            // Bow C and stern B
+            bridgeless: &[
+                "c534a876-3f8a-4315-a194-5dda0f84c2b3",
+                "2f2b451c-4776-405c-9914-cad4764f1072",
+            ],
+        },
+        LinerHull {
+            hull_type: "Stock/Container Hauler",
+            bows: &CONTAINER_BOWS,
+            cores: &CONTAINER_CORES,
+            sterns: &CONTAINER_STERNS,
+            bridges: &BRIDGE_MODELS,
+            // Bow A
+            bridgeless: &["2d7c228c-cbd6-425e-9590-a2f8ae8d5915"],
+        },
+    ]);
+}
+
+/// Segment and bridge keys of one family of modular liner hulls.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LinerHull {
+    /// `HullType` of ships using this hull
+    pub hull_type: &'static str,
+    pub bows: &'static [&'static str],
+    pub cores: &'static [&'static str],
+    pub sterns: &'static [&'static str],
+    pub bridges: &'static [&'static str],
+    /// Segments the game crashes on when a bridge is mounted to them, as
+    /// listed under "Liner editing" in the README
+    pub bridgeless: &'static [&'static str],
+}
+
+impl LinerHull {
+    /// The liner hull a ship is built on, if it is a liner.
+    pub fn of(ship: &Ship) -> Option<&'static LinerHull> {
+        LINER_HULLS
+            .iter()
+            .find(|hull| hull.hull_type == ship.hull_type)
+    }
+
+    /// Segment keys for the bow (0), core (1) or stern (2).
+    pub fn segments(&self, segment: usize) -> &'static [&'static str] {
+        match segment {
+            0 => self.bows,
+            1 => self.cores,
+            2 => self.sterns,
+            _ => &[],
+        }
+    }
+
+    /// Key of the bow (0), core (1) or stern (2) chosen in `params`.
+    pub fn segment_key(
+        &self,
+        params: &EditableHullParams,
+        segment: usize,
+    ) -> Option<&'static str> {
+        let segment_type = match segment {
+            0 => params.bow_type,
+            1 => params.core_type,
+            2 => params.stern_type,
+            _ => return None,
+        };
+        self.segments(segment).get(segment_type).copied()
+    }
+
+    /// Whether the bridge can be mounted on a segment of `params`.
+    pub fn can_mount_bridge(
+        &self,
+        params: &EditableHullParams,
+        segment: usize,
+    ) -> bool {
+        self.segment_key(params, segment)
+            .is_some_and(|key| !self.bridgeless.contains(&key))
+    }
+
+    /// Make sure the game can load a hull of this family built from `params`.
+    pub fn validate(&self, params: &EditableHullParams) -> Result<()> {
+        for (segment, name) in SEGMENT_NAMES.iter().enumerate() {
+            let Some(key) = self.segment_key(params, segment) else {
+                bail!(
+                    "The {} doesn't have a {name} {}",
+                    self.hull_type,
+                    params.segment_type(segment)
+                );
+            };
+            validate_dressings(key, params.dressings(segment))?;
+        }
+        if params.superstructure_type >= self.bridges.len() {
+            bail!(
+                "The {} doesn't have bridge {}",
+                self.hull_type,
+                params.superstructure_type
+            );
+        }
+        if !self.can_mount_bridge(params, params.superstructure_loc) {
+            bail!("The bridge can't be mounted on that segment");
+        }
+        Ok(())
+    }
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+/// Names of the bow (0), core (1) and stern (2) segments
+pub const SEGMENT_NAMES: [&str; 3] = ["bow", "core", "stern"];
+
+/// Letter the game uses for a segment or bridge variant. Variants past `Z`,
+/// which only come from malformed configs, are shown as their number.
+pub fn variant_label(idx: usize) -> String {
+    u32::try_from(idx)
+        .ok()
+        .filter(|&idx| idx < 26)
+        .and_then(|idx| char::from_u32('A' as u32 + idx))
+        .map_or_else(|| idx.to_string(), String::from)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct EditableHullParams {
     pub bow_type: usize,
     pub core_type: usize,
@@ -51,13 +175,140 @@ pub struct EditableHullParams {
     pub superstructure_loc: usize,
     pub superstructure_type: usize,
 
-    pub bow_dressings: [u8; 8],
-    pub core_dressings: [u8; 8],
+    pub bow_dressings: [u8; DRESSING_SLOTS],
+    pub core_dressings: [u8; DRESSING_SLOTS],
+    /// `None` leaves the stern's dressings as they are, such as for configs
+    /// copied before stern dressings were added
+    pub stern_dressings: Option<[u8; DRESSING_SLOTS]>,
+
+    /// `None` leaves the hull's paint as it is, such as for configs copied
+    /// before paint was added
+    pub paint: Option<HullPaint>,
+}
+
+/// Colour and texture of a liner's hull.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct HullPaint {
+    /// Red, green, blue and alpha
+    pub tint: [u8; 4],
+    pub texture_variation: [f32; 3],
+}
+
+impl Default for HullPaint {
+    fn default() -> Self {
+        HullPaint {
+            tint: [255; 4],
+            texture_variation: [0.0; 3],
+        }
+    }
+}
+
+impl HullPaint {
+    pub fn of(hull_config: &HullConfig) -> Self {
+        let tint = &hull_config.hull_tint;
+        let texture = &hull_config.texture_variation;
+        HullPaint {
+            tint: [tint.r, tint.g, tint.b, tint.a].map(tint_to_u8),
+            texture_variation: [texture.x, texture.y, texture.z],
+        }
+    }
+
+    /// Paint a hull, leaving tint channels that round to the same value
+    /// untouched so the game's own values are kept.
+    pub fn apply(&self, hull_config: &mut HullConfig) {
+        let tint = &mut hull_config.hull_tint;
+        for (channel, value) in
+            [&mut tint.r, &mut tint.g, &mut tint.b, &mut tint.a]
+                .into_iter()
+                .zip(self.tint)
+        {
+            if tint_to_u8(*channel) != value {
+                *channel = value as f32 / 255.0;
+            }
+        }
+        let texture = &mut hull_config.texture_variation;
+        [texture.x, texture.y, texture.z] = self.texture_variation;
+    }
+}
+
+fn tint_to_u8(channel: f32) -> u8 {
+    (channel.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Paint every liner in a fleet, returning how many were painted.
+pub fn paint_liners(fleet: &mut Fleet, paint: &HullPaint) -> usize {
+    let ships = fleet
+        .ships
+        .iter_mut()
+        .flat_map(|ships| ships.ship.iter_mut().flatten());
+    let mut painted = 0;
+    for ship in ships {
+        if LinerHull::of(ship).is_none() {
+            continue;
+        }
+        if let Some(hull_config) = ship.hull_config.as_mut() {
+            paint.apply(hull_config);
+            painted += 1;
+        }
+    }
+    painted
+}
+
+impl EditableHullParams {
+    /// The segments and bridge, e.g. `Bow B, core A, stern C, bridge D on
+    /// the core`.
+    pub fn summary(&self) -> String {
+        format!(
+            "Bow {}, core {}, stern {}, bridge {} on the {}",
+            variant_label(self.bow_type),
+            variant_label(self.core_type),
+            variant_label(self.stern_type),
+            variant_label(self.superstructure_type),
+            SEGMENT_NAMES
+                .get(self.superstructure_loc)
+                .copied()
+                .unwrap_or("unknown segment"),
+        )
+    }
+
+    /// Variant of the bow (0), core (1) or stern (2).
+    pub fn segment_type(&self, segment: usize) -> usize {
+        match segment {
+            0 => self.bow_type,
+            1 => self.core_type,
+            _ => self.stern_type,
+        }
+    }
+
+    /// Dressings of the bow (0), core (1) or stern (2). Stern dressings that
+    /// are left as they are count as empty.
+    pub fn dressings(&self, segment: usize) -> &[u8; DRESSING_SLOTS] {
+        match segment {
+            0 => &self.bow_dressings,
+            1 => &self.core_dressings,
+            _ => self
+                .stern_dressings
+                .as_ref()
+                .unwrap_or(&[0; DRESSING_SLOTS]),
+        }
+    }
+
+    pub fn dressings_mut(
+        &mut self,
+        segment: usize,
+    ) -> &mut [u8; DRESSING_SLOTS] {
+        match segment {
+            0 => &mut self.bow_dressings,
+            1 => &mut self.core_dressings,
+            _ => self.stern_dressings.get_or_insert_default(),
+        }
+    }
 }
 
 pub fn get_ln_editable_hull_params(ship: &Ship) -> Option<EditableHullParams> {
     debug!(ship = %ship.name, "Loading liner hull configuration");
 
+    let hull = LinerHull::of(ship)?;
     let mut out = EditableHullParams::default();
 
     if let Some(hull_config) = &ship.hull_config {
@@ -66,67 +317,30 @@ pub fn get_ln_editable_hull_params(ship: &Ship) -> Option<EditableHullParams> {
 
         let segment = segments.next()?;
         out.bow_type =
-            BULK_BOWS.iter().position(|x| *x == segment.key.as_str())?;
-        out.bow_dressings = segment
-            .dressing
-            .int
-            .as_ref()
-            .map(|ints| {
-                let mut vec = ints
-                    .iter()
-                    .map(|s| s.parse::<u8>().unwrap())
-                    .collect::<Vec<u8>>();
-
-                // Make sure vec is exactly 5 items long
-                if vec.len() < 8 {
-                    for _ in 0..(8 - vec.len()) {
-                        vec.push(0);
-                    }
-                }
-
-                vec
-            })
-            .unwrap_or(vec![0, 0, 0, 0, 0, 0, 0, 0])
-            .try_into()
-            .ok()?;
+            hull.bows.iter().position(|x| *x == segment.key.as_str())?;
+        out.bow_dressings = read_dressings(segment)?;
 
         let segment = segments.next()?;
         out.core_type =
-            BULK_CORES.iter().position(|x| *x == segment.key.as_str())?;
-        out.core_dressings = segment
-            .dressing
-            .int
-            .as_ref()
-            .map(|ints| {
-                let mut vec = ints
-                    .iter()
-                    .map(|s| s.parse::<u8>().unwrap())
-                    .collect::<Vec<u8>>();
-
-                // Make sure vec is exactly 8 items long
-                if vec.len() < 8 {
-                    for _ in 0..(8 - vec.len()) {
-                        vec.push(0);
-                    }
-                }
-
-                vec
-            })
-            .unwrap_or(vec![0, 0, 0, 0, 0, 0, 0, 0])
-            .try_into()
-            .ok()?;
+            hull.cores.iter().position(|x| *x == segment.key.as_str())?;
+        out.core_dressings = read_dressings(segment)?;
 
         let segment = segments.next()?;
-        out.stern_type = BULK_STERNS
+        out.stern_type = hull
+            .sterns
             .iter()
             .position(|x| *x == segment.key.as_str())?;
+        out.stern_dressings = Some(read_dressings(segment)?);
 
         let superstructure_config =
             &hull_config.secondary_structure.secondary_structure_config;
-        out.superstructure_type = BRIDGE_MODELS
+        out.superstructure_type = hull
+            .bridges
             .iter()
             .position(|k| *k == superstructure_config.key.as_str())?;
-        out.superstructure_loc = superstructure_config.segment.parse().ok()?;
+        out.superstructure_loc = superstructure_config.segment as usize;
+
+        out.paint = Some(HullPaint::of(hull_config));
     }
 
     debug!("Got liner config: {:?}", out);
@@ -134,20 +348,47 @@ pub fn get_ln_editable_hull_params(ship: &Ship) -> Option<EditableHullParams> {
     Some(out)
 }
 
+/// Dressings of a segment, padded with zeros to fill every slot.
+fn read_dressings(
+    segment: &SegmentConfiguration,
+) -> Option<[u8; DRESSING_SLOTS]> {
+    let mut dressings = [0; DRESSING_SLOTS];
+    let ints = segment.dressing.int.as_deref().unwrap_or_default();
+    if ints.len() > DRESSING_SLOTS {
+        return None;
+    }
+    dressings[..ints.len()].copy_from_slice(ints);
+    Some(dressings)
+}
+
 pub fn set_ln_hull_config(
     ship: &mut Ship,
     hull_params: EditableHullParams,
 ) -> Option<()> {
     debug!(config = ?hull_params, "Saving ship hull configuration");
 
+    let mut hull_params = hull_params;
+    if hull_params.stern_dressings.is_none() {
+        // The stern keeps its dressings, unless it's replaced by another
+        let current = get_ln_editable_hull_params(ship)?;
+        if current.stern_type == hull_params.stern_type {
+            hull_params.stern_dressings = current.stern_dressings;
+        }
+    }
+
+    let hull = LinerHull::of(ship)?;
+    if let Err(err) = hull.validate(&hull_params) {
+        warn!(ship = %ship.name, %err, "Invalid hull configuration");
+        return None;
+    }
+
     let EditableHullParams {
         bow_type,
         core_type,
         stern_type,
-        bow_dressings,
-        core_dressings,
         superstructure_loc,
         superstructure_type,
+        ..
     } = hull_params;
 
     set_bridge_type_and_anchor(ship, superstructure_type, superstructure_loc);
@@ -157,70 +398,50 @@ pub fn set_ln_hull_config(
     set_ln_stern_type(ship, stern_type);
 
     let hull_config = ship.hull_config.as_mut()?;
-    let primary_hull_config = &mut hull_config.primary_structure;
-
-    let mut segment_configs =
-        primary_hull_config.segment_configuration.iter_mut();
-    if let Some(bow_config) = segment_configs.next() {
-        let mut dressings = bow_dressings
-            .iter()
-            .rev()
-            .skip_while(|x| **x == 0)
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
-        dressings.reverse();
-        bow_config.dressing.int = Some(dressings)
-    }
-    if let Some(core_config) = segment_configs.next() {
-        let mut dressings = core_dressings
-            .iter()
-            .rev()
-            .skip_while(|x| **x == 0)
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
-        dressings.reverse();
-        core_config.dressing.int = Some(dressings)
+    if let Some(paint) = &hull_params.paint {
+        paint.apply(hull_config);
+    }
+    for (segment, segment_config) in hull_config
+        .primary_structure
+        .segment_configuration
+        .iter_mut()
+        .take(3)
+        .enumerate()
+    {
+        // Trailing empty slots are left out, like the game does
+        let dressings = hull_params.dressings(segment);
+        let len = dressings.iter().rposition(|x| *x != 0).map_or(0, |i| i + 1);
+        segment_config.dressing.int =
+            (len > 0).then(|| dressings[..len].to_vec());
     }
 
     Some(())
 }
 
 pub fn set_ln_bow_type(ship: &mut Ship, segment_type: usize) -> Option<()> {
-    let hex = BULK_BOWS[segment_type];
-
-    let hull_config = ship.hull_config.as_mut()?;
-    let bow_config = hull_config
-        .primary_structure
-        .segment_configuration
-        .get_mut(0)?;
-
-    bow_config.key = hex.to_string();
-
-    Some(())
+    set_ln_segment_type(ship, 0, segment_type)
 }
 pub fn set_ln_core_type(ship: &mut Ship, segment_type: usize) -> Option<()> {
-    let hex = BULK_CORES[segment_type];
-
-    let hull_config = ship.hull_config.as_mut()?;
-    let bow_config = hull_config
-        .primary_structure
-        .segment_configuration
-        .get_mut(1)?;
-
-    bow_config.key = hex.to_string();
-
-    Some(())
+    set_ln_segment_type(ship, 1, segment_type)
 }
 pub fn set_ln_stern_type(ship: &mut Ship, segment_type: usize) -> Option<()> {
-    let hex = BULK_STERNS[segment_type];
+    set_ln_segment_type(ship, 2, segment_type)
+}
+
+fn set_ln_segment_type(
+    ship: &mut Ship,
+    segment: usize,
+    segment_type: usize,
+) -> Option<()> {
+    let hex = LinerHull::of(ship)?.segments(segment).get(segment_type)?;
 
     let hull_config = ship.hull_config.as_mut()?;
-    let bow_config = hull_config
+    let segment_config = hull_config
         .primary_structure
         .segment_configuration
-        .get_mut(2)?;
+        .get_mut(segment)?;
 
-    bow_config.key = hex.to_string();
+    segment_config.key = hex.to_string();
 
     Some(())
 }
@@ -230,14 +451,122 @@ pub fn set_bridge_type_and_anchor(
     superstructure_type: usize,
     superstructure_segment: usize,
 ) -> Option<()> {
+    let bridge = LinerHull::of(ship)?.bridges.get(superstructure_type)?;
     let bridge_config = &mut ship
         .hull_config
         .as_mut()?
         .secondary_structure
         .secondary_structure_config;
 
-    bridge_config.key = BRIDGE_MODELS[superstructure_type].to_string();
-    bridge_config.segment = superstructure_segment.to_string();
+    bridge_config.key = bridge.to_string();
+    bridge_config.segment = superstructure_segment as u32;
 
     Some(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::system::fleet_io::read_fleet;
+
+    const GOLDEN_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    #[test]
+    fn variant_labels() {
+        assert_eq!(variant_label(0), "A");
+        assert_eq!(variant_label(25), "Z");
+        assert_eq!(variant_label(26), "26");
+        assert_eq!(variant_label(usize::MAX), usize::MAX.to_string());
+    }
+
+    #[test]
+    fn liner_hull_families() {
+        let fleet =
+            read_fleet(Path::new(GOLDEN_DIR).join("liner-escort.fleet"))
+                .unwrap();
+        let ships = fleet.ships.unwrap().ship.unwrap();
+        let mut bulk = ships[0].clone();
+        let mut container = ships[1].clone();
+
+        assert_eq!(LinerHull::of(&bulk).unwrap().bows, &BULK_BOWS[..]);
+        assert_eq!(
+            LinerHull::of(&container).unwrap().bows,
+            &CONTAINER_BOWS[..]
+        );
+
+        let bulk_params = get_ln_editable_hull_params(&bulk).unwrap();
+        assert_eq!(bulk_params.bow_type, 1);
+        assert_eq!(bulk_params.bow_dressings, [1, 0, 2, 0, 0, 0, 0, 0]);
+        assert_eq!(bulk_params.stern_type, 2);
+
+        let mut params = get_ln_editable_hull_params(&container).unwrap();
+        assert_eq!((params.bow_type, params.core_type), (1, 0));
+        assert_eq!(params.superstructure_loc, 2);
+
+        params.bow_type = 2;
+        params.stern_type = 1;
+        params.superstructure_type = 3;
+        params.dressings_mut(2)[1] = 2;
+        set_ln_hull_config(&mut container, params.clone()).unwrap();
+        assert_eq!(get_ln_editable_hull_params(&container), Some(params));
+        let segments = &container
+            .hull_config
+            .as_ref()
+            .unwrap()
+            .primary_structure
+            .segment_configuration;
+        assert_eq!(segments[0].key, CONTAINER_BOWS[2]);
+        assert_eq!(segments[2].key, CONTAINER_STERNS[1]);
+        assert_eq!(segments[1].dressing.int, None);
+        assert_eq!(segments[2].dressing.int, Some(vec![0, 2]));
+
+        // Configurations that don't fit the hull are rejected untouched
+        let invalid = EditableHullParams {
+            bow_type: 3,
+            ..bulk_params.clone()
+        };
+        assert!(set_ln_hull_config(&mut bulk, invalid).is_none());
+        let bridge_on_bow_c = EditableHullParams {
+            bow_type: 2,
+            superstructure_loc: 0,
+            ..bulk_params.clone()
+        };
+        assert!(set_ln_hull_config(&mut bulk, bridge_on_bow_c).is_none());
+        let mut unknown_dressing = bulk_params.clone();
+        unknown_dressing.dressings_mut(2)[0] = 9;
+        assert!(set_ln_hull_config(&mut bulk, unknown_dressing).is_none());
+        assert_eq!(get_ln_editable_hull_params(&bulk), Some(bulk_params));
+
+        let mut frigate = bulk.clone();
+        frigate.hull_type = "Stock/Raines Frigate".to_string();
+        assert!(LinerHull::of(&frigate).is_none());
+        assert!(get_ln_editable_hull_params(&frigate).is_none());
+    }
+
+    #[test]
+    fn hull_paint() {
+        let mut fleet =
+            read_fleet(Path::new(GOLDEN_DIR).join("liner-escort.fleet"))
+                .unwrap();
+        let ships = fleet.ships.as_ref().unwrap().ship.as_ref().unwrap();
+        let paint = get_ln_editable_hull_params(&ships[0])
+            .unwrap()
+            .paint
+            .unwrap();
+        assert_eq!(paint.tint, [100, 110, 120, 255]);
+        assert_eq!(paint.texture_variation, [0.25, 0.5, 0.0]);
+
+        // Unchanged channels keep the game's value
+        let mut hull_config = ships[0].hull_config.clone().unwrap();
+        paint.apply(&mut hull_config);
+        assert_eq!(&hull_config, ships[0].hull_config.as_ref().unwrap());
+
+        assert_eq!(paint_liners(&mut fleet, &paint), 2);
+        let ships = fleet.ships.as_ref().unwrap().ship.as_ref().unwrap();
+        let container = ships[1].hull_config.as_ref().unwrap();
+        assert_eq!(HullPaint::of(container), paint);
+    }
+}
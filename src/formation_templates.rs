@@ -0,0 +1,204 @@
+//! Named formations that can be applied to any formation. Stored in
+//! `formation_templates.toml` next to `config.toml`.
+
+use std::sync::{Mutex, OnceLock};
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    system::prefs::{init_prefs, load_prefs, save_prefs},
+    ui::formations::{FormationTemplate, Point3Serde},
+};
+
+pub static FORMATION_TEMPLATES: OnceLock<Mutex<FormationTemplates>> =
+    OnceLock::new();
+const FORMATION_TEMPLATES_FILE: &str = "formation_templates.toml";
+
+/// Size of each class of hull. Hulls are matched on the end of their
+/// `HullType`, so `Stock/Sprinter Corvette` is a corvette, trying heavy and
+/// command cruisers before other cruisers.
+const HULL_SIZES: &[(&str, u8)] = &[
+    ("Corvette", 1),
+    ("Clipper", 1),
+    ("Tugboat", 1),
+    ("Frigate", 2),
+    ("Feeder", 2),
+    ("Destroyer", 3),
+    ("Heavy Cruiser", 5),
+    ("Command Cruiser", 5),
+    ("Cruiser", 4),
+    ("Hauler", 5),
+    ("Battleship", 6),
+];
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SavedFormation {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub template: FormationTemplate,
+    /// `HullType` of the ship in each slot when the formation was saved, if
+    /// known. Used to put ships back in slots that suit them.
+    #[serde(default)]
+    pub hulls: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub struct FormationTemplates {
+    #[serde(default, rename = "formation")]
+    pub formations: Vec<SavedFormation>,
+}
+
+impl FormationTemplates {
+    /// Save `formation`, replacing any formation with the same name.
+    pub fn add(&mut self, formation: SavedFormation) {
+        match self
+            .formations
+            .iter_mut()
+            .find(|f| f.name == formation.name)
+        {
+            Some(existing) => *existing = formation,
+            None => self.formations.push(formation),
+        }
+        self.save();
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.formations.retain(|formation| formation.name != name);
+        self.save();
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = save_formation_templates(self) {
+            error!("{}", err.wrap_err("Failed to save formation templates"));
+        }
+    }
+}
+
+/// Rough size of a hull, from 1 for corvettes to 6 for battleships. `None` if
+/// the hull's class isn't known.
+pub fn hull_size(hull_type: &str) -> Option<u8> {
+    HULL_SIZES
+        .iter()
+        .find(|(class, _)| hull_type.ends_with(class))
+        .map(|(_, size)| *size)
+}
+
+/// Position for each escort, given their `HullType`s, or `None` for those
+/// left without a slot. Escorts take slots saved with the same hull first,
+/// then slots saved with a hull of the same size, then any free slot, keeping
+/// to the saved order at each step.
+pub fn place_escorts(
+    formation: &SavedFormation,
+    escort_hulls: &[String],
+) -> Vec<Option<Point3Serde>> {
+    let slots = &formation.template.escorts;
+    let slot_hull = |slot: usize| formation.hulls.get(slot).map(String::as_str);
+    // Same hull, then same size, then any slot
+    let fits = |pass: usize, slot: usize, hull: &str| match pass {
+        0 => slot_hull(slot) == Some(hull),
+        1 => slot_hull(slot)
+            .and_then(hull_size)
+            .is_some_and(|size| hull_size(hull) == Some(size)),
+        _ => true,
+    };
+
+    let mut placed = vec![None; escort_hulls.len()];
+    let mut free = vec![true; slots.len()];
+    for pass in 0..3 {
+        for (escort, hull) in escort_hulls.iter().enumerate() {
+            if placed[escort].is_some() {
+                continue;
+            }
+            if let Some(slot) = (0..slots.len())
+                .find(|&slot| free[slot] && fits(pass, slot, hull))
+            {
+                free[slot] = false;
+                placed[escort] = Some(slots[slot]);
+            }
+        }
+    }
+    placed
+}
+
+pub fn init_formation_templates() {
+    init_prefs(
+        &FORMATION_TEMPLATES,
+        FORMATION_TEMPLATES_FILE,
+        "formation templates",
+    );
+}
+
+pub fn load_formation_templates() -> Result<FormationTemplates> {
+    load_prefs(FORMATION_TEMPLATES_FILE, "formation templates")
+}
+
+pub fn save_formation_templates(templates: &FormationTemplates) -> Result<()> {
+    save_prefs(FORMATION_TEMPLATES_FILE, "formation templates", templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64) -> Point3Serde {
+        Point3Serde { x, y: 0.0, z: 0.0 }
+    }
+
+    #[test]
+    fn placing_escorts() {
+        let screen = SavedFormation {
+            name: "Screen".to_string(),
+            description: String::new(),
+            template: FormationTemplate {
+                escorts: vec![point(1.0), point(2.0), point(3.0)],
+            },
+            hulls: vec![
+                "Stock/Raines Frigate".to_string(),
+                "Stock/Sprinter Corvette".to_string(),
+                "Stock/Keystone Destroyer".to_string(),
+            ],
+        };
+
+        // Stored formations survive a round trip
+        let templates = FormationTemplates {
+            formations: vec![screen.clone()],
+        };
+        let toml = toml::to_string(&templates).unwrap();
+        assert_eq!(
+            toml::from_str::<FormationTemplates>(&toml).unwrap(),
+            templates
+        );
+
+        let hulls = |hulls: &[&str]| {
+            hulls
+                .iter()
+                .map(|hull| hull.to_string())
+                .collect::<Vec<_>>()
+        };
+        // Same hull first, then same size, then anything left
+        let escorts = hulls(&[
+            "Mod/Unknown Hull",
+            "Stock/Sprinter Corvette",
+            "Stock/Shuttle Clipper",
+            "Stock/Raines Frigate",
+        ]);
+        assert_eq!(
+            place_escorts(&screen, &escorts),
+            [Some(point(3.0)), Some(point(2.0)), None, Some(point(1.0))]
+        );
+
+        // Without saved hulls, escorts fill the slots in order
+        let plain = SavedFormation {
+            hulls: Vec::new(),
+            ..screen
+        };
+        let escorts = hulls(&["Stock/Raines Frigate", "Stock/Raines Frigate"]);
+        assert_eq!(
+            place_escorts(&plain, &escorts),
+            [Some(point(1.0)), Some(point(2.0))]
+        );
+    }
+}
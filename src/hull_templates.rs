@@ -0,0 +1,203 @@
+//! Named liner hull configs that can be applied to any liner with the same
+//! hull. Stored in `hull_templates.toml` next to `config.toml`.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+    fleet_edit::{EditableHullParams, LinerHull},
+    system::{
+        fleet_io::read_fleet,
+        prefs::{init_prefs, load_prefs, save_prefs},
+    },
+    util::hull_batch::{
+        HullBatch, HullBatchResult, HullPart, apply_hull_batch,
+    },
+};
+
+pub static HULL_TEMPLATES: OnceLock<Mutex<HullTemplates>> = OnceLock::new();
+const HULL_TEMPLATES_FILE: &str = "hull_templates.toml";
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct HullTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// `HullType` of the liners the template fits
+    pub hull_type: String,
+    pub params: EditableHullParams,
+}
+
+impl HullTemplate {
+    /// One line description of the segments and bridge, shown alongside the
+    /// description.
+    pub fn summary(&self) -> String {
+        self.params.summary()
+    }
+
+    /// A batch edit copying the whole template to the ships with the given
+    /// keys in each fleet.
+    pub fn batch(&self, fleets: &[(PathBuf, Vec<String>)]) -> HullBatch {
+        HullBatch {
+            hull_type: self.hull_type.clone(),
+            params: self.params.clone(),
+            parts: HullPart::ALL.to_vec(),
+            ships: Some(fleets.iter().cloned().collect()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub struct HullTemplates {
+    #[serde(default, rename = "template")]
+    pub templates: Vec<HullTemplate>,
+}
+
+impl HullTemplates {
+    /// Save `template`, replacing any template with the same name.
+    pub fn add(&mut self, template: HullTemplate) {
+        match self.templates.iter_mut().find(|t| t.name == template.name) {
+            Some(existing) => *existing = template,
+            None => self.templates.push(template),
+        }
+        self.save();
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.templates.retain(|template| template.name != name);
+        self.save();
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = save_hull_templates(self) {
+            error!("{}", err.wrap_err("Failed to save hull templates"));
+        }
+    }
+}
+
+/// A liner in a fleet file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Liner {
+    /// The ship's `Key`
+    pub key: String,
+    pub name: String,
+    pub hull_type: String,
+}
+
+/// Every liner in the fleet at `path`.
+pub fn fleet_liners(path: &Path) -> Result<Vec<Liner>> {
+    let fleet = read_fleet(path)?;
+    Ok(fleet
+        .ships
+        .iter()
+        .flat_map(|ships| ships.ship.iter().flatten())
+        .filter(|ship| LinerHull::of(ship).is_some())
+        .map(|ship| Liner {
+            key: ship.key.clone(),
+            name: ship.name.clone(),
+            hull_type: ship.hull_type.clone(),
+        })
+        .collect())
+}
+
+/// Apply `template` to the ships with the given keys in each fleet, backing
+/// up each fleet before it is rewritten. A fleet is only written if the
+/// template fits every chosen ship in it.
+pub fn apply_hull_template(
+    template: &HullTemplate,
+    fleets: &[(PathBuf, Vec<String>)],
+) -> HullBatchResult {
+    let paths = fleets
+        .iter()
+        .filter(|(_, keys)| !keys.is_empty())
+        .map(|(path, _)| path.clone())
+        .collect::<Vec<_>>();
+    let result = apply_hull_batch(&template.batch(fleets), &paths);
+    info!(
+        "Applied hull template '{}' to {} fleets",
+        template.name,
+        result.0.len()
+    );
+    result
+}
+
+pub fn init_hull_templates() {
+    init_prefs(&HULL_TEMPLATES, HULL_TEMPLATES_FILE, "hull templates");
+}
+
+pub fn load_hull_templates() -> Result<HullTemplates> {
+    load_prefs(HULL_TEMPLATES_FILE, "hull templates")
+}
+
+pub fn save_hull_templates(templates: &HullTemplates) -> Result<()> {
+    save_prefs(HULL_TEMPLATES_FILE, "hull templates", templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::hull_batch::preview_hull_batch;
+
+    const GOLDEN_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    #[test]
+    fn apply_templates() {
+        let path = std::env::temp_dir().join(format!(
+            "nebtools-{}-hull-templates.fleet",
+            std::process::id()
+        ));
+        std::fs::copy(Path::new(GOLDEN_DIR).join("liner-escort.fleet"), &path)
+            .unwrap();
+        let liners = fleet_liners(&path).unwrap();
+        assert_eq!(liners.len(), 2);
+
+        let fleet = read_fleet(&path).unwrap();
+        let ships = fleet.ships.as_ref().unwrap().ship.as_ref().unwrap();
+        let mut params =
+            crate::fleet_edit::get_ln_editable_hull_params(&ships[1]).unwrap();
+        params.core_type = 2;
+        let template = HullTemplate {
+            name: "Wide core".to_string(),
+            description: String::new(),
+            hull_type: liners[1].hull_type.clone(),
+            params: params.clone(),
+        };
+
+        // Stored templates survive a round trip
+        let templates = HullTemplates {
+            templates: vec![template.clone()],
+        };
+        let toml = toml::to_string(&templates).unwrap();
+        assert_eq!(toml::from_str::<HullTemplates>(&toml).unwrap(), templates);
+
+        // The bulk hauler can't take a container hauler's template
+        let paths = std::slice::from_ref(&path);
+        let all = vec![(
+            path.clone(),
+            liners.iter().map(|liner| liner.key.clone()).collect(),
+        )];
+        assert!(
+            preview_hull_batch(&template.batch(&all), paths)[0]
+                .1
+                .is_err()
+        );
+
+        // Only the chosen ships change, and previewing writes nothing
+        let containers = vec![(path.clone(), vec![liners[1].key.clone()])];
+        let preview = preview_hull_batch(&template.batch(&containers), paths);
+        let changes = preview[0].1.as_ref().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].name, liners[1].name);
+        assert_eq!(changes[0].after, params);
+        assert_eq!(read_fleet(&path).unwrap(), fleet);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
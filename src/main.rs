@@ -18,6 +18,8 @@ use tracing_subscriber::{
 
 use crate::ui::{fleet_list::FleetList, menubar::Menubars};
 
+// Headless commands
+mod cli;
 // System interactions
 mod system;
 // Constants
@@ -26,7 +28,12 @@ mod fleet_edit;
 // Helper functions
 mod util;
 // Structures
+mod auto_tags;
 mod fleet_data;
+mod formation_templates;
+mod hull_templates;
+mod saved_searches;
+mod tag_db;
 mod tags;
 // UI
 mod ui;
@@ -42,6 +49,12 @@ struct Cli {
     #[clap(short, long)]
     #[clap(default_value = "info")]
     logging_filter: String,
+    /// Override the configured Nebulous saves directory (headless commands
+    /// only)
+    #[clap(long, global = true)]
+    saves_dir: Option<PathBuf>,
+    #[clap(subcommand)]
+    command: Option<cli::Command>,
 }
 
 lazy_static! {
@@ -65,6 +78,7 @@ fn main() -> Result<()> {
             .open(p)
     });
     let console_layer = tracing_subscriber::fmt::Layer::new()
+        .with_writer(std::io::stderr)
         .with_target(true)
         .with_span_events(tracing_subscriber::fmt::format::FmtSpan::NONE)
         .with_filter(EnvFilter::new(cli.logging_filter));
@@ -87,6 +101,10 @@ fn main() -> Result<()> {
     tracing::subscriber::set_global_default(subscriber)
         .wrap_err("Failed to initialise logger")?;
 
+    if let Some(command) = cli.command {
+        return cli::run(command, cli.saves_dir);
+    }
+
     std::thread::spawn(|| {
         if let Err(err) = update() {
             warn!("Self update failed: {:?}", err);
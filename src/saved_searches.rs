@@ -0,0 +1,194 @@
+//! Named searches, shown as folders in the fleet list. Stored in
+//! `saved_searches.toml` next to `config.toml`, and can be exported to share
+//! them with other players.
+
+use std::{
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+use color_eyre::{Result, eyre::bail};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+use crate::{
+    system::prefs::{
+        init_prefs, load_prefs, read_toml, save_prefs, write_toml,
+    },
+    util::search::parse_search_text,
+};
+
+pub static SAVED_SEARCHES: OnceLock<Mutex<SavedSearches>> = OnceLock::new();
+const SAVED_SEARCHES_FILE: &str = "saved_searches.toml";
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub struct SavedSearches {
+    #[serde(default, rename = "search")]
+    pub searches: Vec<SavedSearch>,
+}
+
+impl SavedSearches {
+    /// Save `query` as `name`, replacing any saved search with the same name.
+    pub fn add(&mut self, name: String, query: String) {
+        self.insert(SavedSearch { name, query });
+        self.save();
+    }
+
+    /// Rename the saved search `from` to `to`, failing if another saved search
+    /// is already called `to`.
+    pub fn rename(&mut self, from: &str, to: String) -> Result<()> {
+        self.rename_search(from, to)?;
+        self.save();
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.delete(name);
+        self.save();
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = save_saved_searches(self) {
+            error!("{}", err.wrap_err("Failed to save saved searches"));
+        }
+    }
+
+    /// Write every saved search to `path`, in the same format they are stored
+    /// in.
+    pub fn export(&self, path: &Path) -> Result<()> {
+        debug!("Exporting saved searches to '{}'", path.display());
+        write_toml(path, "saved searches", self)
+    }
+
+    /// Add the saved searches in `path`, replacing any with the same names.
+    /// Returns how many were imported.
+    pub fn import(&mut self, path: &Path) -> Result<usize> {
+        debug!("Importing saved searches from '{}'", path.display());
+        let imported = read_saved_searches(path)?;
+        for search in &imported.searches {
+            if let Err(err) = parse_search_text(&search.query) {
+                bail!("Invalid search '{}': {}", search.name, err);
+            }
+        }
+        let count = imported.searches.len();
+        for search in imported.searches {
+            self.insert(search);
+        }
+        self.save();
+        Ok(count)
+    }
+
+    fn insert(&mut self, search: SavedSearch) {
+        match self.searches.iter_mut().find(|s| s.name == search.name) {
+            Some(existing) => existing.query = search.query,
+            None => self.searches.push(search),
+        }
+    }
+
+    fn rename_search(&mut self, from: &str, to: String) -> Result<()> {
+        if from == to {
+            return Ok(());
+        }
+        if self.searches.iter().any(|search| search.name == to) {
+            bail!("A saved search is already called '{to}'");
+        }
+        let Some(search) =
+            self.searches.iter_mut().find(|search| search.name == from)
+        else {
+            bail!("No saved search called '{from}'");
+        };
+        search.name = to;
+        Ok(())
+    }
+
+    fn delete(&mut self, name: &str) {
+        self.searches.retain(|search| search.name != name);
+    }
+}
+
+pub fn init_saved_searches() {
+    init_prefs(&SAVED_SEARCHES, SAVED_SEARCHES_FILE, "saved searches");
+}
+
+pub fn load_saved_searches() -> Result<SavedSearches> {
+    load_prefs(SAVED_SEARCHES_FILE, "saved searches")
+}
+
+pub fn save_saved_searches(saved_searches: &SavedSearches) -> Result<()> {
+    save_prefs(SAVED_SEARCHES_FILE, "saved searches", saved_searches)
+}
+
+fn read_saved_searches(path: &Path) -> Result<SavedSearches> {
+    read_toml(path, "saved searches")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(searches: &SavedSearches) -> Vec<(&str, &str)> {
+        searches
+            .searches
+            .iter()
+            .map(|search| (search.name.as_str(), search.query.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn editing_searches() {
+        let search = |name: &str, query: &str| SavedSearch {
+            name: name.to_string(),
+            query: query.to_string(),
+        };
+        let mut searches = SavedSearches::default();
+        searches.insert(search("OSP", "tag:tournament faction:OSP"));
+        searches.insert(search("Active", "not tag:archived"));
+        // A search with the same name replaces the old one in place
+        searches.insert(search("OSP", "faction:OSP"));
+        assert_eq!(
+            names(&searches),
+            [("OSP", "faction:OSP"), ("Active", "not tag:archived")]
+        );
+
+        searches
+            .rename_search("OSP", "Protectorate".to_string())
+            .unwrap();
+        assert!(
+            searches
+                .rename_search("Active", "Protectorate".to_string())
+                .is_err()
+        );
+        assert!(
+            searches
+                .rename_search("Missing", "New".to_string())
+                .is_err()
+        );
+        assert_eq!(
+            names(&searches),
+            [
+                ("Protectorate", "faction:OSP"),
+                ("Active", "not tag:archived")
+            ]
+        );
+
+        searches.delete("Protectorate");
+        searches.delete("Missing");
+        assert_eq!(names(&searches), [("Active", "not tag:archived")]);
+
+        // Exported searches read back the same
+        let path = std::env::temp_dir().join(format!(
+            "nebtools-{}-saved-searches.toml",
+            std::process::id()
+        ));
+        searches.export(&path).unwrap();
+        let read = read_saved_searches(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), searches);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::{
+    Result,
+    eyre::{Context, eyre},
+};
+use tracing::{debug, trace};
+use zip::write::FileOptions;
+
+use crate::system::config::APP_CONFIG;
+
+pub fn backup_to_zip(out_path: &Path) -> Result<()> {
+    let fleets_root = APP_CONFIG
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .saves_dir
+        .join("Fleets");
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(out_path)?;
+    let mut zip_writer = zip::ZipWriter::new_stream(&mut file);
+
+    let mut dirs_queue = Vec::new();
+
+    dirs_queue.push(fleets_root.clone());
+
+    while !dirs_queue.is_empty() {
+        let dir = dirs_queue.remove(0);
+        let read_dir = dir.read_dir()?;
+        for child in read_dir {
+            let Ok(entry) = child else { continue };
+            let path = entry.path();
+
+            let sub_path = path.strip_prefix(&fleets_root).unwrap();
+
+            if path.is_dir() {
+                zip_writer
+                    .add_directory_from_path(sub_path, FileOptions::DEFAULT)?;
+                dirs_queue.push(path);
+            } else if path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.to_str().unwrap() == "fleet")
+            {
+                zip_writer
+                    .start_file_from_path(sub_path, FileOptions::DEFAULT)?;
+                let mut src_file = File::open(&path)?;
+                std::io::copy(&mut src_file, &mut zip_writer)?;
+            }
+        }
+    }
+
+    zip_writer.finish()?;
+
+    Ok(())
+}
+
+pub fn backup_to_folder(out_path: &Path) -> Result<()> {
+    let fleets_root = APP_CONFIG
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .saves_dir
+        .join("Fleets");
+
+    let mut dirs_queue = Vec::new();
+
+    dirs_queue.push(fleets_root.clone());
+
+    while !dirs_queue.is_empty() {
+        let dir = dirs_queue.remove(0);
+        let read_dir = dir.read_dir()?;
+        for child in read_dir {
+            let Ok(entry) = child else { continue };
+            let path = entry.path();
+
+            let sub_path = path.strip_prefix(&fleets_root).unwrap();
+
+            let new_path = out_path.join(sub_path);
+            if path.is_dir() {
+                dirs_queue.push(path);
+                if !new_path.exists() {
+                    trace!("Creating directory: '{}'", new_path.display());
+                    std::fs::create_dir(new_path)?;
+                }
+            } else if path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.to_str().unwrap() == "fleet")
+            {
+                if new_path.exists() {
+                    trace!("Removing old file: '{}'", new_path.display());
+                    std::fs::remove_file(&new_path)?;
+                }
+                trace!(
+                    "Copying file: '{}' -> '{}'",
+                    path.display(),
+                    new_path.display()
+                );
+                std::fs::copy(&path, &new_path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A new, empty folder to copy fleets into before a batch edit rewrites them,
+/// named after the time the edit started.
+pub fn batch_backup_dir() -> Result<PathBuf> {
+    let backups = directories::ProjectDirs::from("", "", "NebTools")
+        .ok_or(eyre!("OS not recognised?"))
+        .wrap_err("Failed to retrieve data dir")?
+        .data_dir()
+        .join("Backups");
+    create_backup_dir(&backups)
+}
+
+/// Create a folder in `backups` named after the current time in milliseconds,
+/// adding a counter if another batch already claimed that name.
+fn create_backup_dir(backups: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(backups)
+        .wrap_err("Failed to create backups folder")?;
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let mut dir = backups.join(millis.to_string());
+    let mut count = 0;
+    loop {
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                count += 1;
+                dir = backups.join(format!("{millis}-{count}"));
+            }
+            Err(err) => {
+                return Err(err).wrap_err("Failed to create backup folder");
+            }
+        }
+    }
+}
+
+/// Copy the fleet at `path` into `backup_dir`, keeping its folders under the
+/// fleets directory. Returns where the copy was written.
+pub fn backup_fleet(path: &Path, backup_dir: &Path) -> Result<PathBuf> {
+    let fleets_root = APP_CONFIG
+        .get()
+        .map(|config| config.lock().unwrap().saves_dir.join("Fleets"));
+    let sub_path = fleets_root
+        .as_deref()
+        .and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(Path::new(path.file_name().unwrap_or_default()));
+    let backup_path = backup_dir.join(sub_path);
+    debug!(
+        "Backing up '{}' to '{}'",
+        path.display(),
+        backup_path.display()
+    );
+    if let Some(parent) = backup_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(path, &backup_path).wrap_err("Failed to back up fleet")?;
+    Ok(backup_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_dirs_are_unique() {
+        let backups = std::env::temp_dir()
+            .join(format!("nebtools-{}-backup-dirs", std::process::id()));
+        let _ = std::fs::remove_dir_all(&backups);
+        let first = create_backup_dir(&backups).unwrap();
+        let second = create_backup_dir(&backups).unwrap();
+        assert_ne!(first, second);
+        assert!(first.is_dir() && second.is_dir());
+        std::fs::remove_dir_all(backups).unwrap();
+    }
+}
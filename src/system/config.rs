@@ -26,6 +26,34 @@ pub struct AppConfig {
     pub sound_effects: bool,
     #[serde(default = "default_true")]
     pub use_fleet_cache: bool,
+    /// Skip fields added by newer game versions instead of treating the fleet
+    /// as invalid
+    #[serde(default = "default_true")]
+    pub tolerant_parsing: bool,
+    #[serde(default)]
+    pub tag_storage: TagStorage,
+}
+
+/// Where fleet tags are kept.
+#[derive(
+    Deserialize,
+    Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TagStorage {
+    /// In a `Tags:` line at the start of each fleet's description, so they're
+    /// visible in game
+    #[default]
+    Description,
+    /// In NebTools' own database, leaving fleet files untouched
+    Database,
 }
 
 pub fn default_true() -> bool {
@@ -1,46 +1,111 @@
 use std::{
+    collections::HashMap,
     fs::File,
-    io::{BufReader, BufWriter},
-    path::Path,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
 };
 
-use color_eyre::{Result, eyre::Context};
-use schemas::{Fleet, MissileTemplate};
+use color_eyre::{
+    Result,
+    eyre::{Context, bail},
+};
+use lazy_static::lazy_static;
+use schemas::{Fleet, MissileTemplate, probe::Schema};
+use serde::de::DeserializeOwned;
 use tracing::{info, instrument, trace, warn};
 
-use crate::fleet_data::FleetData;
+use crate::{
+    fleet_data::FleetData,
+    system::{
+        config::APP_CONFIG,
+        load_fleets::hash_bytes,
+        xml_patch::{patch_xml, pretty_xml, strip_unknown},
+    },
+};
+
+lazy_static! {
+    static ref FLEET_SCHEMA: Schema = Schema::of::<Fleet>();
+    static ref MISSILE_SCHEMA: Schema = Schema::of::<MissileTemplate>();
+    /// Hash of each file's contents when NebTools last read or wrote it.
+    static ref LAST_SEEN: Mutex<HashMap<PathBuf, u64>> =
+        Mutex::new(HashMap::new());
+    /// Hash of what NebTools last wrote to each file.
+    static ref LAST_WRITTEN: Mutex<HashMap<PathBuf, u64>> =
+        Mutex::new(HashMap::new());
+}
 
 pub fn read_fleet(path: impl AsRef<Path>) -> Result<Fleet> {
+    read_fleet_tolerant(path).map(|(fleet, _)| fleet)
+}
+
+/// Read a fleet, also returning the paths of any elements or attributes that
+/// were skipped because NebTools doesn't understand them. These are kept when
+/// the fleet is written back.
+pub fn read_fleet_tolerant(
+    path: impl AsRef<Path>,
+) -> Result<(Fleet, Vec<String>)> {
     let path = path.as_ref();
 
     trace!("Opening fleet '{}'", path.display());
-    let file =
-        BufReader::new(File::open(path).wrap_err("Failed to open fleet file")?);
+    let xml =
+        std::fs::read_to_string(path).wrap_err("Failed to open fleet file")?;
 
-    trace!("Parsing fleet '{}'", path.display());
-    let fleet = match quick_xml::de::from_reader(file) {
-        Ok(fleet) => fleet,
-        Err(err) => {
-            warn!("{}", err);
-            Err(err).wrap_err("Failed to parse fleet file")?
-        }
-    };
+    let fleet = parse_fleet_tolerant(path, &xml)?;
+    remember_contents(path, &xml);
     Ok(fleet)
 }
 
-#[allow(dead_code)]
+/// Like [`read_fleet_tolerant`], for a fleet file that has already been read.
+/// `path` is only used for logging.
+pub fn parse_fleet_tolerant(
+    path: &Path,
+    xml: &str,
+) -> Result<(Fleet, Vec<String>)> {
+    trace!("Parsing fleet '{}'", path.display());
+    read_xml(path, xml, &FLEET_SCHEMA).wrap_err("Failed to parse fleet file")
+}
+
 pub fn write_fleet(path: impl AsRef<Path>, fleet: &Fleet) -> Result<()> {
     let path = path.as_ref();
 
-    let _ = std::fs::remove_file(path);
-    trace!("Creating fleet file '{}'", path.display());
-    let file = BufWriter::new(
-        File::create_new(path).wrap_err("Failed to create fleet file")?,
-    );
     trace!("Serializing fleet '{}'", path.display());
-    quick_xml::se::to_utf8_io_writer(file, fleet)
+    let xml = quick_xml::se::to_string(fleet)
         .wrap_err("Failed to serialize fleet file")?;
-    Ok(())
+    write_xml(path, &xml, &FLEET_SCHEMA).wrap_err("Failed to write fleet file")
+}
+
+/// Read the fleet at `path` and write it back if `edit` returns true, failing
+/// if the file changes in between.
+///
+/// Unlike [`read_fleet`], this doesn't count as having seen the file: if it
+/// was changed by another program since NebTools last read it, edits based on
+/// that read still can't be saved.
+pub fn update_fleet(
+    path: impl AsRef<Path>,
+    edit: impl FnOnce(&mut Fleet) -> Result<bool>,
+) -> Result<bool> {
+    let path = path.as_ref();
+
+    trace!("Opening fleet '{}'", path.display());
+    let original =
+        std::fs::read_to_string(path).wrap_err("Failed to open fleet file")?;
+    let (mut fleet, _) = parse_fleet_tolerant(path, &original)?;
+    if !edit(&mut fleet)? {
+        return Ok(false);
+    }
+
+    trace!("Serializing fleet '{}'", path.display());
+    let xml = quick_xml::se::to_string(&fleet)
+        .wrap_err("Failed to serialize fleet file")?;
+    let read = hash_bytes(original.as_bytes());
+    let out = write_xml_over(path, &xml, &FLEET_SCHEMA, Some(read))
+        .wrap_err("Failed to write fleet file")?;
+    let mut last_seen = LAST_SEEN.lock().unwrap();
+    if last_seen.get(path).is_none_or(|&hash| hash == read) {
+        last_seen.insert(path.to_path_buf(), hash_bytes(out.as_bytes()));
+    }
+    Ok(true)
 }
 
 #[allow(dead_code)]
@@ -48,18 +113,13 @@ pub fn read_missile(path: impl AsRef<Path>) -> Result<MissileTemplate> {
     let path = path.as_ref();
 
     trace!("Opening missile '{}'", path.display());
-    let file = BufReader::new(
-        File::open(path).wrap_err("Failed to open missile file")?,
-    );
+    let xml = std::fs::read_to_string(path)
+        .wrap_err("Failed to open missile file")?;
 
     trace!("Parsing missile '{}'", path.display());
-    let missile = match quick_xml::de::from_reader(file) {
-        Ok(missile) => missile,
-        Err(err) => {
-            warn!("{}", err);
-            Err(err).wrap_err("Failed to parse missile file")?
-        }
-    };
+    let (missile, _) = read_xml(path, &xml, &MISSILE_SCHEMA)
+        .wrap_err("Failed to parse missile file")?;
+    remember_contents(path, &xml);
     Ok(missile)
 }
 
@@ -70,17 +130,154 @@ pub fn write_missile(
 ) -> Result<()> {
     let path = path.as_ref();
 
-    let _ = std::fs::remove_file(path);
-    trace!("Creating missile '{}'", path.display());
-    let file = BufWriter::new(
-        File::create_new(path).wrap_err("Failed to create missile file")?,
-    );
     trace!("Serializing missile '{}'", path.display());
-    quick_xml::se::to_utf8_io_writer(file, missile)
+    let xml = quick_xml::se::to_string(missile)
         .wrap_err("Failed to serialize missile file")?;
+    write_xml(path, &xml, &MISSILE_SCHEMA)
+        .wrap_err("Failed to write missile file")
+}
+
+/// Deserialize `xml`. If it contains fields that aren't in the schema and
+/// tolerant parsing is enabled, they are skipped instead of failing.
+fn read_xml<T: DeserializeOwned>(
+    path: &Path,
+    xml: &str,
+    schema: &Schema,
+) -> Result<(T, Vec<String>)> {
+    let err = match quick_xml::de::from_str(xml) {
+        Ok(value) => return Ok((value, Vec::new())),
+        Err(err) => err,
+    };
+    let tolerant = APP_CONFIG
+        .get()
+        .is_none_or(|config| config.lock().unwrap().tolerant_parsing);
+    if !tolerant {
+        warn!("{}", err);
+        return Err(err.into());
+    }
+
+    let (stripped, unknown) = strip_unknown(xml, schema)?;
+    if unknown.is_empty() {
+        warn!("{}", err);
+        return Err(err.into());
+    }
+    let value = match quick_xml::de::from_str(&stripped) {
+        Ok(value) => value,
+        Err(err) => {
+            warn!("{}", err);
+            return Err(err.into());
+        }
+    };
+    warn!(
+        "'{}' contains fields NebTools doesn't understand: {}",
+        path.display(),
+        unknown.join(", ")
+    );
+    Ok((value, unknown))
+}
+
+fn remember_contents(path: &Path, contents: &str) {
+    LAST_SEEN
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), hash_bytes(contents.as_bytes()));
+}
+
+/// Whether the file at `path` is different from when NebTools last read or
+/// wrote it, i.e. something else has changed it since. Files NebTools hasn't
+/// opened are never considered changed.
+pub fn changed_on_disk(path: &Path) -> bool {
+    let Some(last_seen) = LAST_SEEN.lock().unwrap().get(path).copied() else {
+        return false;
+    };
+    match std::fs::read(path) {
+        Ok(contents) => hash_bytes(&contents) != last_seen,
+        Err(_) => true,
+    }
+}
+
+/// Whether the file at `path` is as NebTools last wrote it, i.e. the last
+/// change to it was NebTools' own.
+pub fn written_by_nebtools(path: &Path) -> bool {
+    let Some(written) = LAST_WRITTEN.lock().unwrap().get(path).copied() else {
+        return false;
+    };
+    std::fs::read(path).is_ok_and(|contents| hash_bytes(&contents) == written)
+}
+
+/// Write a serialized document to `path`. If the file already exists, only the
+/// elements that changed are rewritten so the rest of the file stays
+/// byte-for-byte identical.
+///
+/// Fails instead of overwriting the file if another program has changed it
+/// since NebTools read it, as `xml` would be based on the old contents.
+fn write_xml(path: &Path, xml: &str, schema: &Schema) -> Result<()> {
+    let last_seen = LAST_SEEN.lock().unwrap().get(path).copied();
+    let out = write_xml_over(path, xml, schema, last_seen)?;
+    remember_contents(path, &out);
     Ok(())
 }
 
+/// Like [`write_xml`], but fails if the file's hash isn't `expected` rather
+/// than the hash from when NebTools last read it. Returns what was written.
+fn write_xml_over(
+    path: &Path,
+    xml: &str,
+    schema: &Schema,
+    expected: Option<u64>,
+) -> Result<String> {
+    let out = match std::fs::read_to_string(path) {
+        Ok(original) => {
+            if expected
+                .is_some_and(|hash| hash != hash_bytes(original.as_bytes()))
+            {
+                bail!(
+                    "'{}' was changed by another program since it was opened",
+                    path.display()
+                );
+            }
+            match patch_xml(&original, xml, schema) {
+                Ok(patched) => patched,
+                Err(err) => {
+                    // Rewriting from `xml` would drop the fields NebTools
+                    // doesn't understand
+                    let unknown = strip_unknown(&original, schema)
+                        .map(|(_, unknown)| unknown)
+                        .unwrap_or_default();
+                    if !unknown.is_empty() {
+                        return Err(err.wrap_err(format!(
+                            "Could not patch '{}', and rewriting it would \
+                             lose {}",
+                            path.display(),
+                            unknown.join(", ")
+                        )));
+                    }
+                    warn!(
+                        "Could not patch '{}', rewriting it: {:?}",
+                        path.display(),
+                        err
+                    );
+                    pretty_xml(xml)?
+                }
+            }
+        }
+        Err(_) => pretty_xml(xml)?,
+    };
+
+    let _ = std::fs::remove_file(path);
+    trace!("Creating file '{}'", path.display());
+    let mut file = BufWriter::new(
+        File::create_new(path).wrap_err("Failed to create file")?,
+    );
+    file.write_all(out.as_bytes())?;
+    file.flush()?;
+    LAST_WRITTEN
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), hash_bytes(out.as_bytes()));
+    Ok(out)
+}
+
 #[instrument(skip(fleet_data, fleet))]
 #[allow(unused)]
 pub fn save_fleet_data(fleet_data: &FleetData, fleet: &Fleet) -> Result<()> {
@@ -92,3 +289,217 @@ pub fn save_fleet_data(fleet_data: &FleetData, fleet: &Fleet) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::{Path, PathBuf},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use crate::system::fleet_io::{
+        FLEET_SCHEMA, changed_on_disk, read_fleet, read_fleet_tolerant,
+        update_fleet, write_fleet, write_xml, written_by_nebtools,
+    };
+
+    const GOLDEN_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    /// Copy a golden fleet to a scratch file so it can be written to.
+    fn scratch_copy(golden: &Path) -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "nebtools-{}-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed),
+            golden.file_name().unwrap().to_string_lossy()
+        ));
+        std::fs::copy(golden, &path).unwrap();
+        path
+    }
+
+    fn golden_fleets() -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(GOLDEN_DIR)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "fleet"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty(), "No golden fleets found");
+        paths
+    }
+
+    #[test]
+    fn unmodified_round_trip() {
+        for golden in golden_fleets() {
+            let path = scratch_copy(&golden);
+            let fleet = read_fleet(&path).unwrap();
+            write_fleet(&path, &fleet).unwrap();
+
+            let expected = std::fs::read(&golden).unwrap();
+            let actual = std::fs::read(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert!(
+                expected == actual,
+                "'{}' changed after round trip:\n{}",
+                golden.display(),
+                String::from_utf8_lossy(&actual)
+            );
+        }
+    }
+
+    #[test]
+    fn edit_only_touches_changed_element() {
+        for golden in golden_fleets() {
+            let path = scratch_copy(&golden);
+            let mut fleet = read_fleet(&path).unwrap();
+            let old_name = format!("<Name>{}</Name>", fleet.name);
+            fleet.name = "Renamed Fleet".to_string();
+            write_fleet(&path, &fleet).unwrap();
+
+            let expected = std::fs::read_to_string(&golden).unwrap().replacen(
+                &old_name,
+                "<Name>Renamed Fleet</Name>",
+                1,
+            );
+            let actual = std::fs::read_to_string(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(expected, actual, "'{}'", golden.display());
+        }
+    }
+
+    #[test]
+    fn numeric_looking_names() {
+        let golden = Path::new(GOLDEN_DIR).join("liner-escort.fleet");
+        let path = scratch_copy(&golden);
+        for name in ["7", "007", "7.0", "7"] {
+            let mut fleet = read_fleet(&path).unwrap();
+            fleet.name = name.to_string();
+            let ships = fleet.ships.as_mut().unwrap().ship.as_mut().unwrap();
+            ships[0].name = name.to_string();
+            write_fleet(&path, &fleet).unwrap();
+
+            let fleet = read_fleet(&path).unwrap();
+            let ships = fleet.ships.as_ref().unwrap().ship.as_ref().unwrap();
+            assert_eq!(
+                (fleet.name.as_str(), ships[0].name.as_str()),
+                (name, name)
+            );
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unknown_fields_are_kept() {
+        let golden = Path::new(GOLDEN_DIR).join("future-fields.fleet");
+        let path = scratch_copy(&golden);
+        let (mut fleet, unknown) = read_fleet_tolerant(&path).unwrap();
+        assert_eq!(
+            unknown,
+            [
+                "Fleet/CampaignKey",
+                "Fleet/Ships/Ship/Doctrine",
+                "Fleet/Ships/Ship/WeaponGroups/WepGroup/@Priority",
+            ]
+        );
+
+        fleet.ships.as_mut().unwrap().ship.as_mut().unwrap()[0].name =
+            "Sprinter 2".to_string();
+        write_fleet(&path, &fleet).unwrap();
+        let actual = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&golden)
+                .unwrap()
+                .replace("Sprinter 1", "Sprinter 2"),
+            actual
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_not_rewritten() {
+        let golden = Path::new(GOLDEN_DIR).join("future-fields.fleet");
+        let path = scratch_copy(&golden);
+        read_fleet_tolerant(&path).unwrap();
+
+        // A document with another root element can't be patched in
+        let err =
+            write_xml(&path, "<MissileTemplate/>", &FLEET_SCHEMA).unwrap_err();
+        assert!(format!("{err:#}").contains("Fleet/CampaignKey"));
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            std::fs::read(&golden).unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn external_changes_are_not_overwritten() {
+        let golden = Path::new(GOLDEN_DIR).join("corvette-screen.fleet");
+        let path = scratch_copy(&golden);
+        let mut fleet = read_fleet(&path).unwrap();
+        assert!(!changed_on_disk(&path));
+
+        let external = std::fs::read_to_string(&path).unwrap().replacen(
+            "<Name>",
+            "<Name>Edited ",
+            1,
+        );
+        std::fs::write(&path, &external).unwrap();
+        assert!(changed_on_disk(&path));
+
+        fleet.name = "Stale Edit".to_string();
+        assert!(write_fleet(&path, &fleet).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), external);
+
+        // Once reloaded, edits can be saved again
+        let mut fleet = read_fleet(&path).unwrap();
+        fleet.name = "Fresh Edit".to_string();
+        write_fleet(&path, &fleet).unwrap();
+        assert!(!changed_on_disk(&path));
+        assert!(written_by_nebtools(&path));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn updates_keep_stale_edits_out() {
+        let golden = Path::new(GOLDEN_DIR).join("corvette-screen.fleet");
+        let path = scratch_copy(&golden);
+        // Opened in the editor, then changed by another program
+        let mut fleet = read_fleet(&path).unwrap();
+        let external = std::fs::read_to_string(&path).unwrap().replacen(
+            "<Name>",
+            "<Name>Edited ",
+            1,
+        );
+        std::fs::write(&path, &external).unwrap();
+        assert!(!written_by_nebtools(&path));
+
+        // Auto tagging the change keeps it, and the editor's edits still can't
+        // overwrite it
+        assert!(
+            update_fleet(&path, |fleet| {
+                fleet.description = Some("[auto]".to_string());
+                Ok(true)
+            })
+            .unwrap()
+        );
+        assert!(written_by_nebtools(&path));
+        assert!(changed_on_disk(&path));
+        fleet.name = "Stale Edit".to_string();
+        assert!(write_fleet(&path, &fleet).is_err());
+        let updated = read_fleet(&path).unwrap();
+        assert!(updated.name.starts_with("Edited "));
+        assert_eq!(updated.description.as_deref(), Some("[auto]"));
+
+        // Updating a fleet that hasn't changed since it was read counts as
+        // having seen the update
+        update_fleet(&path, |fleet| {
+            fleet.description = Some("[auto] [more]".to_string());
+            Ok(true)
+        })
+        .unwrap();
+        assert!(!changed_on_disk(&path));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -4,6 +4,8 @@ use std::{
     hash::Hasher,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::atomic::{self, AtomicUsize},
+    time::SystemTime,
 };
 
 use color_eyre::{
@@ -12,15 +14,75 @@ use color_eyre::{
 };
 use glob::Pattern;
 use metrohash::MetroHash;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, info, trace, warn};
 
 use crate::{
     fleet_data::FleetData,
-    system::{config::APP_CONFIG, fleet_io::read_fleet},
+    system::{config::APP_CONFIG, fleet_io::parse_fleet_tolerant},
 };
 
+pub const FLEET_CACHE_FILE: &str = "fleets_data.bin";
+
+/// Written at the start of the fleet cache. Bump whenever [`FleetData`] or
+/// [`CacheEntry`] changes, so caches in the old format are rebuilt instead of
+/// being misread.
+const FLEET_CACHE_VERSION: u32 = 5;
+
+/// Cached fleets, by path.
+type FleetCache = HashMap<PathBuf, CacheEntry>;
+
+/// Every fleet found, and the paths of those that are new or changed since
+/// they were cached.
+pub type LoadedFleets = (Vec<FleetData>, Vec<PathBuf>);
+
+#[derive(Clone, Deserialize, Serialize)]
+struct CacheEntry {
+    size: u64,
+    hash: u64,
+    fleet_data: FleetData,
+}
+
+/// A fleet file found while walking the Fleets directory.
+struct FleetFile {
+    path: PathBuf,
+    short_path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// How far through loading fleets [`load_fleets_with_progress`] is. Can be
+/// read from another thread while loading.
+#[derive(Default)]
+pub struct LoadProgress {
+    loaded: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl LoadProgress {
+    /// The number of fleets loaded so far, and the total number of fleets.
+    pub fn get(&self) -> (usize, usize) {
+        (
+            self.loaded.load(atomic::Ordering::Relaxed),
+            self.total.load(atomic::Ordering::Relaxed),
+        )
+    }
+}
+
+/// Load every fleet in the Fleets directory. Never writes to fleet files.
 pub fn load_fleets(use_cache: Option<bool>) -> Result<Vec<FleetData>> {
-    let Some(Ok(app_config)) = APP_CONFIG.get().map(|m| m.lock()) else {
+    load_fleets_with_progress(use_cache, &LoadProgress::default())
+        .map(|(fleets, _)| fleets)
+}
+
+pub fn load_fleets_with_progress(
+    use_cache: Option<bool>,
+    progress: &LoadProgress,
+) -> Result<LoadedFleets> {
+    // Cloned so the config isn't locked while reading fleets
+    let Some(Ok(app_config)) =
+        APP_CONFIG.get().map(|m| m.lock().map(|c| c.clone()))
+    else {
         bail!("App configuration not yet loaded");
     };
 
@@ -37,49 +99,111 @@ pub fn load_fleets(use_cache: Option<bool>) -> Result<Vec<FleetData>> {
         .filter_map(|x| Pattern::from_str(x).ok())
         .collect::<Vec<_>>();
 
-    let cache_path = app_config.cache_dir.join("fleets_data.bin");
-    let get_fleet_cache = || {
-        let bytes = std::fs::read(&cache_path).ok()?;
-        let fleet_cache: HashMap<u64, FleetData> =
-            postcard::from_bytes(&bytes).ok()?;
-        info!("Loading fleets from cache");
-        Some(fleet_cache)
-    };
-    let mut fleet_cache = if use_cache {
-        get_fleet_cache().unwrap_or_default()
+    let fleet_cache = if use_cache {
+        read_fleet_cache(&app_config.cache_dir).unwrap_or_default()
     } else {
         HashMap::new()
     };
 
     debug!("Loading fleets from {}", path.display());
-    let mut output = vec![];
-    load_fleets_rec(
+    let mut files = vec![];
+    find_fleets_rec(
         path.as_ref(),
         &excluded_patterns,
         path.as_ref(),
-        &mut output,
-        &mut fleet_cache,
+        &mut files,
     )?;
+    progress.loaded.store(0, atomic::Ordering::Relaxed);
+    progress.total.store(files.len(), atomic::Ordering::Relaxed);
+
+    let entries = scan_fleets(&files, &fleet_cache, progress);
+
+    let mut output = Vec::with_capacity(files.len());
+    let mut changed = Vec::new();
+    let mut new_cache = FleetCache::with_capacity(files.len());
+    for (file, entry) in files.into_iter().zip(entries) {
+        if let Some((entry, parsed)) = entry {
+            output.push(entry.fleet_data.clone());
+            if parsed {
+                changed.push(file.path.clone());
+            }
+            new_cache.insert(file.path, entry);
+        }
+    }
 
     debug!("Loaded {} fleets", output.len());
+    // Lets stored tags follow fleets that were moved
+    crate::tag_db::identify_fleets(
+        new_cache
+            .iter()
+            .map(|(path, entry)| (path.as_path(), entry.hash)),
+    );
+
+    write_fleet_cache(&app_config.cache_dir, &new_cache);
+
+    Ok((output, changed))
+}
+
+/// Read `paths` again after NebTools has rewritten them, updating the fleet
+/// cache so they aren't parsed again on the next load.
+pub fn refresh_fleets(paths: &[PathBuf]) -> Result<Vec<FleetData>> {
+    let Some(Ok((root_path, cache_dir))) = APP_CONFIG.get().map(|m| {
+        m.lock()
+            .map(|c| (c.saves_dir.join("Fleets"), c.cache_dir.clone()))
+    }) else {
+        bail!("App configuration not yet loaded");
+    };
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut fleet_cache = read_fleet_cache(&cache_dir).unwrap_or_default();
+    let mut refreshed = Vec::with_capacity(paths.len());
+    for path in paths {
+        match read_cache_entry(&root_path, path) {
+            Ok(entry) => {
+                refreshed.push(entry.fleet_data.clone());
+                fleet_cache.insert(path.clone(), entry);
+            }
+            Err(err) => {
+                warn!("Failed to reload '{}': {:?}", path.display(), err)
+            }
+        }
+    }
+    write_fleet_cache(&cache_dir, &fleet_cache);
+    Ok(refreshed)
+}
+
+fn read_fleet_cache(cache_dir: &Path) -> Option<FleetCache> {
+    let bytes = std::fs::read(cache_dir.join(FLEET_CACHE_FILE)).ok()?;
+    let (version, bytes) = postcard::take_from_bytes::<u32>(&bytes).ok()?;
+    if version != FLEET_CACHE_VERSION {
+        info!(version, "Rebuilding fleet cache from an older version");
+        return None;
+    }
+    let fleet_cache: FleetCache = postcard::from_bytes(bytes).ok()?;
+    info!("Loading fleets from cache");
+    Some(fleet_cache)
+}
 
+fn write_fleet_cache(cache_dir: &Path, fleet_cache: &FleetCache) {
     info!("Saving fleet cache");
-    let bytes: Vec<u8> = postcard::to_stdvec(&fleet_cache).unwrap();
-    if !app_config.cache_dir.exists() {
-        let _ = std::fs::create_dir_all(&app_config.cache_dir);
+    let bytes: Vec<u8> =
+        postcard::to_stdvec(&(FLEET_CACHE_VERSION, fleet_cache)).unwrap();
+    if !cache_dir.exists() {
+        let _ = std::fs::create_dir_all(cache_dir);
     }
-    if let Err(err) = std::fs::write(&cache_path, &bytes) {
+    if let Err(err) = std::fs::write(cache_dir.join(FLEET_CACHE_FILE), &bytes) {
         warn!(%err, "Failed to save fleets cache");
     }
-
-    Ok(output)
 }
-fn load_fleets_rec(
+
+/// Collect every fleet file under `path`, without reading them.
+fn find_fleets_rec(
     root_path: &Path,
     excluded_patterns: &Vec<Pattern>,
     path: &Path,
-    output: &mut Vec<FleetData>,
-    fleet_cache: &mut HashMap<u64, FleetData>,
+    output: &mut Vec<FleetFile>,
 ) -> Result<()> {
     let mut children = path
         .read_dir()
@@ -95,7 +219,7 @@ fn load_fleets_rec(
             Ordering::Equal
         }
     });
-    'child_loop: for child in children {
+    for child in children {
         let file_type = child
             .file_type()
             .wrap_err(format!(
@@ -104,75 +228,224 @@ fn load_fleets_rec(
             ))
             .wrap_err("Failed to determine file type")?;
         if file_type.is_dir() {
-            load_fleets_rec(
+            find_fleets_rec(
                 root_path,
                 excluded_patterns,
                 &child.path(),
                 output,
-                fleet_cache,
             )?;
         } else if file_type.is_file() {
             let path = child.path();
-            for pattern in excluded_patterns {
-                if pattern.matches_path(path.as_path()) {
-                    continue 'child_loop;
-                }
-            }
-            if path.extension().map(|s| s.to_str())
-                != Some(Some("fleet".into()))
-            {
+            if !is_fleet_file(&path, excluded_patterns) {
                 continue;
             }
-            let Ok(hash) = hash_file(&path) else {
-                warn!("Failed to hash fleet");
-                continue 'child_loop;
-            };
-            let fleet_data = if let Some(fleet_data) = fleet_cache.get(&hash) {
-                trace!(path = %path.display(), "Cache hit for fleet");
-                fleet_data.clone()
-            } else {
-                let fleet = match read_fleet(&path) {
-                    Ok(fleet) => fleet,
-                    Err(err) => {
-                        warn!(
-                            "Skipping invalid fleet '{}': {}",
-                            path.display(),
-                            err
-                        );
-                        continue 'child_loop;
-                    }
-                };
-                let short_path = path
-                    .strip_prefix(root_path)
-                    .wrap_err(format!(
-                        "Failed to strip prefix from '{}'",
-                        path.display()
-                    ))?
-                    .parent()
-                    .map(Path::to_path_buf)
-                    .unwrap_or(PathBuf::new());
-                let fleet_data = FleetData {
-                    path,
-                    short_path: short_path.into(),
-                    name: fleet.name.into(),
-                    description: fleet.description.unwrap_or_default(),
-                };
-
-                fleet_cache.insert(hash, fleet_data.clone());
-
-                fleet_data
+            let Ok(metadata) = child.metadata() else {
+                warn!("Failed to read metadata of '{}'", path.display());
+                continue;
             };
 
-            output.push(fleet_data);
+            output.push(FleetFile {
+                short_path: short_path(root_path, &path)?,
+                path,
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            });
         }
     }
     Ok(())
 }
 
-pub fn hash_file(path: impl AsRef<Path>) -> Result<u64> {
+/// Whether `path` names a fleet file that isn't in an excluded directory.
+pub fn is_fleet_file(path: &Path, excluded_patterns: &[Pattern]) -> bool {
+    path.extension().is_some_and(|ext| ext == "fleet")
+        && !excluded_patterns
+            .iter()
+            .any(|pattern| pattern.matches_path(path))
+}
+
+/// The directory of a fleet relative to the Fleets directory at `root_path`.
+fn short_path(root_path: &Path, path: &Path) -> Result<PathBuf> {
+    Ok(path
+        .strip_prefix(root_path)
+        .wrap_err(format!("Failed to strip prefix from '{}'", path.display()))?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default())
+}
+
+/// Load a single fleet from the Fleets directory at `root_path`, bypassing the
+/// cache.
+pub fn load_fleet_data(root_path: &Path, path: &Path) -> Result<FleetData> {
+    read_cache_entry(root_path, path).map(|entry| entry.fleet_data)
+}
+
+fn read_cache_entry(root_path: &Path, path: &Path) -> Result<CacheEntry> {
+    let xml = std::fs::read_to_string(path)
+        .wrap_err(format!("Failed to read fleet '{}'", path.display()))?;
+    let metadata = std::fs::metadata(path)?;
+    let file = FleetFile {
+        path: path.to_path_buf(),
+        short_path: short_path(root_path, path)?,
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+    };
+    let hash = hash_bytes(xml.as_bytes());
+    crate::tag_db::identify_fleets([(path, hash)]);
+    Ok(CacheEntry {
+        size: file.size,
+        hash,
+        fleet_data: fleet_data_from_xml(&file, &xml)?,
+    })
+}
+
+/// Load every fleet in `files` across a pool of threads, returning the cache
+/// entry for each and whether it had to be parsed, or `None` if the fleet
+/// couldn't be read.
+fn scan_fleets(
+    files: &[FleetFile],
+    fleet_cache: &FleetCache,
+    progress: &LoadProgress,
+) -> Vec<Option<(CacheEntry, bool)>> {
+    // Lets fleets that were moved or copied be found without parsing them
+    let by_hash: HashMap<u64, &CacheEntry> = fleet_cache
+        .values()
+        .map(|entry| (entry.hash, entry))
+        .collect();
+
+    let threads = std::thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .min(files.len().max(1));
+    let next_file = AtomicUsize::new(0);
+
+    let mut entries: Vec<Option<(CacheEntry, bool)>> = vec![None; files.len()];
+    std::thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut loaded = Vec::new();
+                    loop {
+                        let idx =
+                            next_file.fetch_add(1, atomic::Ordering::Relaxed);
+                        let Some(file) = files.get(idx) else {
+                            break;
+                        };
+                        loaded.push((
+                            idx,
+                            scan_fleet(file, fleet_cache, &by_hash),
+                        ));
+                        progress.loaded.fetch_add(1, atomic::Ordering::Relaxed);
+                    }
+                    loaded
+                })
+            })
+            .collect::<Vec<_>>();
+        for worker in workers {
+            for (idx, entry) in
+                worker.join().expect("Fleet loading thread panicked")
+            {
+                entries[idx] = entry;
+            }
+        }
+    });
+    entries
+}
+
+fn scan_fleet(
+    file: &FleetFile,
+    fleet_cache: &FleetCache,
+    by_hash: &HashMap<u64, &CacheEntry>,
+) -> Option<(CacheEntry, bool)> {
+    let path = &file.path;
+    let unchanged = |entry: &&CacheEntry| {
+        file.modified.is_some()
+            && entry.fleet_data.modified == file.modified
+            && entry.size == file.size
+    };
+    if let Some(entry) = fleet_cache.get(path).filter(unchanged) {
+        trace!(path = %path.display(), "Cache hit for fleet");
+        return Some((entry.clone(), false));
+    }
+
+    let Ok(bytes) = std::fs::read(path) else {
+        warn!("Failed to read fleet '{}'", path.display());
+        return None;
+    };
+    let hash = hash_bytes(&bytes);
+    let (fleet_data, parsed) = if let Some(entry) = by_hash.get(&hash) {
+        trace!(path = %path.display(), "Cache hit for fleet contents");
+        let fleet_data = FleetData {
+            path: path.clone(),
+            short_path: file.short_path.clone(),
+            modified: file.modified,
+            ..entry.fleet_data.clone()
+        };
+        (fleet_data, false)
+    } else {
+        let Ok(xml) = String::from_utf8(bytes) else {
+            warn!("Skipping invalid fleet '{}': not UTF-8", path.display());
+            return None;
+        };
+        match fleet_data_from_xml(file, &xml) {
+            Ok(fleet_data) => (fleet_data, true),
+            Err(err) => {
+                warn!("Skipping invalid fleet '{}': {}", path.display(), err);
+                return None;
+            }
+        }
+    };
+
+    let entry = CacheEntry {
+        size: file.size,
+        hash,
+        fleet_data,
+    };
+    Some((entry, parsed))
+}
+
+fn fleet_data_from_xml(file: &FleetFile, xml: &str) -> Result<FleetData> {
+    let (fleet, unknown_fields) = parse_fleet_tolerant(&file.path, xml)?;
+
+    let ships = fleet.ships.and_then(|ships| ships.ship).unwrap_or_default();
+    let mut hull_types = ships
+        .iter()
+        .map(|ship| ship.hull_type.clone())
+        .collect::<Vec<_>>();
+    hull_types.sort();
+    hull_types.dedup();
+    let missile_names = fleet
+        .missile_types
+        .and_then(|missiles| missiles.missile_template)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|missile| {
+            format!("{} {}", missile.designation, missile.nickname)
+                .trim()
+                .to_string()
+        })
+        .collect();
+
+    Ok(FleetData {
+        path: file.path.clone(),
+        short_path: file.short_path.clone(),
+        name: fleet.name,
+        description: fleet.description.unwrap_or_default(),
+        unknown_fields,
+        total_points: fleet.total_points,
+        faction_key: fleet.faction_key,
+        ship_count: ships.len(),
+        ship_names: ships.iter().map(|ship| ship.name.clone()).collect(),
+        hull_types,
+        missile_names,
+        mod_dependencies: fleet
+            .mod_dependencies
+            .and_then(|mods| mods.unsigned_long)
+            .unwrap_or_default(),
+        modified: file.modified,
+    })
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
     let mut hasher = MetroHash::new();
-    let bytes = std::fs::read(&path)?;
-    hasher.write(path.as_ref().as_os_str().as_encoded_bytes());
-    hasher.write(&bytes);
-    Ok(hasher.finish())
+    hasher.write(bytes);
+    hasher.finish()
 }
@@ -1,4 +1,8 @@
 pub mod audio;
+pub mod backup;
 pub mod config;
 pub mod fleet_io;
 pub mod load_fleets;
+pub mod prefs;
+pub mod watcher;
+pub mod xml_patch;
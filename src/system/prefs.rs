@@ -0,0 +1,157 @@
+//! TOML files kept in the NebTools config directory, next to `config.toml`,
+//! such as saved searches and hull templates. `what` names a file's contents
+//! in logs and errors, e.g. "hull templates".
+
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::{
+    Result,
+    eyre::{Context, bail, eyre},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use tracing::{debug, error, warn};
+
+/// Files that couldn't be loaded or set aside, so saving would overwrite
+/// whatever is in them.
+static UNSAVEABLE: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Path of the file `file_name` in the config directory.
+pub fn prefs_path(file_name: &str) -> Result<PathBuf> {
+    Ok(directories::ProjectDirs::from("", "", "NebTools")
+        .ok_or(eyre!("OS not recognised?"))
+        .wrap_err("Failed to retrieve config dir")?
+        .preference_dir()
+        .join(file_name))
+}
+
+/// Load `file_name` from the config directory into `global`. If it can't be
+/// read, it is moved aside so it isn't saved over, and `global` starts empty.
+pub fn init_prefs<T: DeserializeOwned + Default>(
+    global: &OnceLock<Mutex<T>>,
+    file_name: &str,
+    what: &str,
+) {
+    let value = load_prefs(file_name, what).unwrap_or_else(|err| {
+        warn!(?err, "Failed to load {what}");
+        match prefs_path(file_name).and_then(|path| set_aside(&path)) {
+            Ok(backup) => {
+                warn!("Moved unreadable {what} to '{}'", backup.display())
+            }
+            Err(err) => {
+                error!(?err, "Failed to move unreadable {what} aside");
+                UNSAVEABLE.lock().unwrap().push(file_name.to_string());
+            }
+        }
+        T::default()
+    });
+    if global.set(Mutex::new(value)).is_err() {
+        panic!("{what} loaded more than once");
+    }
+}
+
+/// Read `file_name` from the config directory, or the default if it doesn't
+/// exist yet.
+pub fn load_prefs<T: DeserializeOwned + Default>(
+    file_name: &str,
+    what: &str,
+) -> Result<T> {
+    let path = prefs_path(file_name)?;
+    debug!("Loading {what} from '{}'", path.display());
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    read_toml(&path, what)
+}
+
+/// Write `value` to `file_name` in the config directory.
+pub fn save_prefs<T: Serialize>(
+    file_name: &str,
+    what: &str,
+    value: &T,
+) -> Result<()> {
+    if UNSAVEABLE
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|file| file == file_name)
+    {
+        bail!("The {what} file couldn't be loaded, so it won't be saved over");
+    }
+    let path = prefs_path(file_name)?;
+    debug!("Writing {what} to '{}'", path.display());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    write_toml(&path, what, value)
+}
+
+pub fn read_toml<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T> {
+    let toml = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Failed to read {what} file"))?;
+    toml::from_str(&toml)
+        .wrap_err_with(|| format!("Failed to parse {what} file"))
+}
+
+/// Write `value` to `path`. The file is replaced in one step, so an
+/// interrupted write can't leave it truncated.
+pub fn write_toml<T: Serialize>(
+    path: &Path,
+    what: &str,
+    value: &T,
+) -> Result<()> {
+    let toml = toml::to_string(value)
+        .wrap_err_with(|| format!("Failed to serialize {what}"))?;
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    let mut file = File::create(&tmp_path)
+        .wrap_err_with(|| format!("Failed to create {what} file"))?;
+    file.write_all(toml.as_bytes())
+        .and_then(|_| file.sync_all())
+        .wrap_err_with(|| format!("Failed to write {what} file"))?;
+    std::fs::rename(&tmp_path, path)
+        .wrap_err_with(|| format!("Failed to replace {what} file"))
+}
+
+/// Move the file at `path` to a new name next to it, returning the new path.
+fn set_aside(path: &Path) -> Result<PathBuf> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".{secs}.bak"));
+    let backup = PathBuf::from(backup);
+    std::fs::rename(path, &backup).wrap_err("Failed to rename file")?;
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreadable_files_are_kept() {
+        let dir = std::env::temp_dir()
+            .join(format!("nebtools-prefs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("prefs.toml");
+        std::fs::write(&path, "fleet = [").unwrap();
+        assert!(read_toml::<toml::Table>(&path, "prefs").is_err());
+
+        let backup = set_aside(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "fleet = [");
+        let value: toml::Table = toml::from_str("fleet = 1").unwrap();
+        write_toml(&path, "prefs", &value).unwrap();
+        assert_eq!(read_toml::<toml::Table>(&path, "prefs").unwrap(), value);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+//! Keeps the fleet list in sync with the Fleets directory while NebTools is
+//! open, e.g. when the game saves a fleet.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::mpsc,
+    time::Duration,
+};
+
+use color_eyre::{
+    Result,
+    eyre::{Context, eyre},
+};
+use futures::channel::mpsc::UnboundedSender;
+use glob::Pattern;
+use notify::{
+    EventKind, RecommendedWatcher, RecursiveMode, Watcher, event::ModifyKind,
+};
+use tracing::{debug, trace, warn};
+
+use crate::{
+    fleet_data::FleetData,
+    system::{
+        config::APP_CONFIG,
+        load_fleets::{is_fleet_file, load_fleet_data},
+    },
+};
+
+/// How long the Fleets directory has to be quiet before changes are handled.
+/// Saving a fleet takes several file system events, and the file may not be
+/// complete until the last one.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone)]
+pub enum FleetChange {
+    /// A fleet was added or changed.
+    Updated(Box<FleetData>),
+    /// A fleet, or a directory of fleets, was removed.
+    Removed(PathBuf),
+}
+
+/// Watches the Fleets directory until dropped.
+pub struct FleetWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+/// Start watching the Fleets directory, sending every fleet that is added,
+/// changed or removed to `changes`.
+pub fn watch_fleets(
+    changes: UnboundedSender<FleetChange>,
+) -> Result<FleetWatcher> {
+    let (fleets_dir, excluded_patterns) = {
+        let app_config = APP_CONFIG
+            .get()
+            .ok_or_else(|| eyre!("App configuration not yet loaded"))?
+            .lock()
+            .unwrap();
+        (
+            app_config.saves_dir.join("Fleets"),
+            app_config
+                .excluded_dirs
+                .iter()
+                .filter_map(|x| Pattern::from_str(x).ok())
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    let (tx, rx) = mpsc::channel::<PathBuf>();
+    let mut watcher = notify::recommended_watcher(
+        move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+            Ok(event) => {
+                // Directories also get events when their contents change,
+                // which are already covered by the events for the files
+                let added = matches!(
+                    event.kind,
+                    EventKind::Create(_)
+                        | EventKind::Modify(ModifyKind::Name(_))
+                );
+                for path in event.paths {
+                    if added || !path.is_dir() {
+                        let _ = tx.send(path);
+                    }
+                }
+            }
+            Err(err) => warn!(%err, "Error watching fleets"),
+        },
+    )
+    .wrap_err("Failed to create fleet watcher")?;
+    watcher
+        .watch(&fleets_dir, RecursiveMode::Recursive)
+        .wrap_err(format!("Failed to watch '{}'", fleets_dir.display()))?;
+    debug!("Watching {} for changes", fleets_dir.display());
+
+    // Stops once the watcher, and with it `tx`, is dropped
+    std::thread::spawn(move || {
+        while let Ok(path) = rx.recv() {
+            let mut paths = BTreeSet::from([path]);
+            while let Ok(path) = rx.recv_timeout(DEBOUNCE) {
+                paths.insert(path);
+            }
+            let mut batch = Vec::new();
+            for path in paths {
+                fleet_changes(
+                    &fleets_dir,
+                    &excluded_patterns,
+                    path,
+                    &mut batch,
+                );
+            }
+            for change in batch {
+                if changes.unbounded_send(change).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    Ok(FleetWatcher { _watcher: watcher })
+}
+
+fn fleet_changes(
+    fleets_dir: &Path,
+    excluded_patterns: &[Pattern],
+    path: PathBuf,
+    output: &mut Vec<FleetChange>,
+) {
+    if !path.exists() {
+        trace!(path = %path.display(), "Fleet removed");
+        output.push(FleetChange::Removed(path));
+    } else if path.is_dir() {
+        // A directory moved into the Fleets directory only gets an event for
+        // the directory itself
+        let Ok(children) = path.read_dir() else {
+            return;
+        };
+        for child in children.filter_map(|c| c.ok()) {
+            fleet_changes(fleets_dir, excluded_patterns, child.path(), output);
+        }
+    } else if is_fleet_file(&path, excluded_patterns) {
+        match load_fleet_data(fleets_dir, &path) {
+            Ok(fleet_data) => {
+                trace!(path = %path.display(), "Fleet updated");
+                output.push(FleetChange::Updated(Box::new(fleet_data)));
+            }
+            Err(err) => {
+                warn!("Skipping invalid fleet '{}': {:?}", path.display(), err);
+            }
+        }
+    }
+}
@@ -0,0 +1,645 @@
+//! Lossless rewriting of XML files.
+//!
+//! Serializing a fleet with quick-xml throws away the formatting, attribute
+//! order and XML declaration of the file the game wrote. Instead, the edited
+//! document is serialized and compared element by element against the file
+//! on disk, and only the elements that actually changed are replaced. Every
+//! other byte of the original file is copied through untouched.
+//!
+//! Elements the schema doesn't know about (see [`strip_unknown`]) never make
+//! it into the edited document, so they are copied through as well.
+
+use std::{collections::BTreeSet, fmt::Write, ops::Range};
+
+use color_eyre::{
+    Result,
+    eyre::{Context, bail, eyre},
+};
+use quick_xml::{Reader, events::Event};
+use schemas::probe::Schema;
+
+/// Newline and indentation used for elements that don't exist in the original
+/// file. Matches what the game writes.
+const DEFAULT_NEWLINE: &str = "\r\n";
+const DEFAULT_INDENT: &str = "  ";
+const XML_DECLARATION: &str = r#"<?xml version="1.0"?>"#;
+
+/// Patch `original` so that it has the same content as `updated`, keeping the
+/// original bytes of every element that is unchanged. Elements of `original`
+/// that aren't part of `schema` are kept as they are.
+pub fn patch_xml(
+    original: &str,
+    updated: &str,
+    schema: &Schema,
+) -> Result<String> {
+    let old = Document::parse(original).wrap_err("Failed to parse original")?;
+    let new = Document::parse(updated).wrap_err("Failed to parse update")?;
+    if old.root.local_name() != new.root.local_name() {
+        bail!(
+            "Root elements differ: '{}' != '{}'",
+            old.root.name,
+            new.root.name
+        );
+    }
+
+    let style = Style::detect(&old);
+    let mut out = String::with_capacity(original.len());
+    out.push_str(&original[old.prolog.clone()]);
+    Patcher {
+        old_src: original,
+        new_src: updated,
+        style: &style,
+    }
+    .patch(&mut out, &old.root, &new.root, &style.newline, Some(schema));
+    out.push_str(&original[old.epilog.clone()]);
+    Ok(out)
+}
+
+/// Remove every element and attribute of `xml` that `schema` doesn't know
+/// about, so it can be deserialized. Also returns the paths of what was
+/// removed, e.g. `Fleet/Ships/Ship/NewElement` or `Fleet/Ships/Ship/@newAttr`.
+pub fn strip_unknown(
+    xml: &str,
+    schema: &Schema,
+) -> Result<(String, Vec<String>)> {
+    let doc = Document::parse(xml)?;
+    let mut edits = Vec::new();
+    let mut unknown = BTreeSet::new();
+    collect_unknown(
+        &doc.root,
+        schema,
+        doc.root.local_name(),
+        &mut edits,
+        &mut unknown,
+    );
+    if edits.is_empty() {
+        return Ok((xml.to_string(), Vec::new()));
+    }
+
+    let mut out = String::with_capacity(xml.len());
+    let mut cursor = 0;
+    for (range, replacement) in edits {
+        out.push_str(&xml[cursor..range.start]);
+        out.push_str(&replacement);
+        cursor = range.end;
+    }
+    out.push_str(&xml[cursor..]);
+    Ok((out, unknown.into_iter().collect()))
+}
+
+/// Find the unknown parts of `element`, in document order. Each edit is a
+/// range of the source to replace.
+fn collect_unknown(
+    element: &Element,
+    schema: &Schema,
+    path: &str,
+    edits: &mut Vec<(Range<usize>, String)>,
+    unknown: &mut BTreeSet<String>,
+) {
+    if !schema.is_struct {
+        return;
+    }
+
+    let (known, unknown_attrs): (Vec<_>, Vec<_>) = element
+        .attributes
+        .iter()
+        .partition(|(name, _)| schema.has_attribute(name));
+    if !unknown_attrs.is_empty() {
+        let mut tag = format!("<{}", element.name);
+        for (name, value) in known {
+            let _ = write!(
+                tag,
+                " {name}=\"{}\"",
+                quick_xml::escape::escape(value.as_str())
+            );
+        }
+        tag.push_str(if element.end.is_some() { ">" } else { "/>" });
+        edits.push((element.start.clone(), tag));
+        for (name, _) in unknown_attrs {
+            unknown.insert(format!("{path}/@{name}"));
+        }
+    }
+
+    for child in &element.children {
+        let child_path = format!("{path}/{}", child.local_name());
+        match schema.field(child.local_name()) {
+            Some(child_schema) => collect_unknown(
+                child,
+                child_schema,
+                &child_path,
+                edits,
+                unknown,
+            ),
+            None => {
+                edits.push((child.span(), String::new()));
+                unknown.insert(child_path);
+            }
+        }
+    }
+}
+
+/// Indent a freshly serialized document the way the game would write it.
+pub fn pretty_xml(xml: &str) -> Result<String> {
+    let doc = Document::parse(xml)?;
+    let style = Style {
+        newline: DEFAULT_NEWLINE.to_string(),
+        indent: DEFAULT_INDENT.to_string(),
+    };
+    let mut out = String::with_capacity(xml.len() * 2);
+    out.push_str(XML_DECLARATION);
+    out.push_str(&style.newline);
+    write_pretty(&mut out, xml, &doc.root, &style.newline, &style);
+    Ok(out)
+}
+
+struct Document {
+    /// Everything before the root element, e.g. the XML declaration.
+    prolog: Range<usize>,
+    root: Element,
+    /// Everything after the root element.
+    epilog: Range<usize>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    /// Attributes by qualified name, with unescaped values.
+    attributes: Vec<(String, String)>,
+    /// The start tag, or the whole tag for empty elements.
+    start: Range<usize>,
+    /// The end tag, `None` for empty elements.
+    end: Option<Range<usize>>,
+    children: Vec<Element>,
+    /// Raw (escaped) text content, excluding child elements.
+    raw_text: String,
+}
+
+impl Element {
+    fn local_name(&self) -> &str {
+        local_name(&self.name)
+    }
+
+    fn span(&self) -> Range<usize> {
+        self.start.start
+            ..self.end.as_ref().map_or(self.start.end, |end| end.end)
+    }
+
+    fn inner(&self) -> Option<Range<usize>> {
+        self.end.as_ref().map(|end| self.start.end..end.start)
+    }
+
+    fn text(&self) -> String {
+        let trimmed = self.raw_text.trim();
+        quick_xml::escape::unescape(trimmed)
+            .map(|s| s.into_owned())
+            .unwrap_or_else(|_| trimmed.to_string())
+    }
+
+    /// Key used to pair up children of two versions of the same element:
+    /// the child's name and how many siblings of that name precede it.
+    fn child_keys(&self) -> Vec<(&str, usize)> {
+        let mut keys: Vec<(&str, usize)> = Vec::new();
+        for child in &self.children {
+            let name = child.local_name();
+            let occurrence = keys.iter().filter(|(n, _)| *n == name).count();
+            keys.push((name, occurrence));
+        }
+        keys
+    }
+
+    /// For every child of `self`, the index of the matching child of `other`.
+    fn match_children(&self, other: &Element) -> Vec<Option<usize>> {
+        let other_keys = other.child_keys();
+        self.child_keys()
+            .into_iter()
+            .map(|key| other_keys.iter().position(|k| *k == key))
+            .collect()
+    }
+
+    /// Whether this child of an element described by `schema` is left out of
+    /// deserialized documents, rather than having been removed.
+    ///
+    /// `xsi:nil` elements deserialize to `None`, which quick-xml doesn't write
+    /// back, and unknown elements are never deserialized in the first place.
+    fn is_preserved(&self, schema: Option<&Schema>) -> bool {
+        let is_nil = self
+            .attributes
+            .iter()
+            .any(|(name, value)| local_name(name) == "nil" && value == "true");
+        is_nil || schema.is_some_and(|s| !s.has_element(self.local_name()))
+    }
+
+    /// Attributes that aren't in `schema`, and so never make it into the
+    /// deserialized document.
+    fn unknown_attributes<'a>(
+        &'a self,
+        schema: Option<&'a Schema>,
+    ) -> impl Iterator<Item = &'a (String, String)> {
+        self.attributes.iter().filter(move |(name, _)| {
+            schema.is_some_and(|s| !s.has_attribute(name))
+        })
+    }
+
+    /// Compare attributes by local name, quick-xml doesn't always write the
+    /// namespace prefix the game uses.
+    fn same_attributes(
+        &self,
+        other: &Element,
+        schema: Option<&Schema>,
+    ) -> bool {
+        let known: Vec<_> = self
+            .attributes
+            .iter()
+            .filter(|(name, _)| schema.is_none_or(|s| s.has_attribute(name)))
+            .collect();
+        known.len() == other.attributes.len()
+            && known.iter().all(|(name, value)| {
+                other.attributes.iter().any(|(other_name, other_value)| {
+                    local_name(name) == local_name(other_name)
+                        && value == other_value
+                })
+            })
+    }
+
+    fn same_as(&self, other: &Element, schema: Option<&Schema>) -> bool {
+        if self.local_name() != other.local_name()
+            || !self.same_attributes(other, schema)
+            || !same_text(
+                &self.text(),
+                &other.text(),
+                schema.is_some_and(|s| s.is_float),
+            )
+        {
+            return false;
+        }
+        let all_matched = |a: &Element, matches: &[Option<usize>]| {
+            matches.iter().zip(&a.children).all(|(matched, child)| {
+                matched.is_some() || child.is_preserved(schema)
+            })
+        };
+        let to_other = self.match_children(other);
+        all_matched(self, &to_other)
+            && all_matched(other, &other.match_children(self))
+            && to_other.iter().zip(&self.children).all(|(matched, child)| {
+                matched.is_none_or(|idx| {
+                    child.same_as(
+                        &other.children[idx],
+                        schema.and_then(|s| s.field(child.local_name())),
+                    )
+                })
+            })
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Whether two texts are the same value. Only `float` texts can be written in
+/// more than one way, everything else must match exactly.
+fn same_text(a: &str, b: &str, float: bool) -> bool {
+    if a == b {
+        return true;
+    }
+    if !float {
+        return false;
+    }
+    // Numbers may be formatted differently, e.g. `1E-05` and `0.00001`
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) if a == b => return true,
+        (Ok(_), Ok(_)) => {}
+        _ => return false,
+    }
+    // The game writes single precision floats with more digits than needed,
+    // e.g. `0.392156869` rather than `0.39215687`
+    let shortest = |s: &str| s.parse::<f32>().map(|f| f.to_string());
+    shortest(a).is_ok_and(|a| a == b) || shortest(b).is_ok_and(|b| a == b)
+}
+
+impl Document {
+    fn parse(src: &str) -> Result<Document> {
+        let mut reader = Reader::from_str(src);
+        let mut stack: Vec<Element> = Vec::new();
+        let mut root = None;
+        let mut prolog_end = 0;
+
+        loop {
+            let pos = reader.buffer_position() as usize;
+            let event = reader.read_event().wrap_err(format!(
+                "Invalid XML at position {}",
+                reader.error_position()
+            ))?;
+            let end = reader.buffer_position() as usize;
+            let element = match event {
+                Event::Start(start) => {
+                    if stack.is_empty() {
+                        prolog_end = pos;
+                    }
+                    stack.push(Element::from_start(&start, pos..end)?);
+                    continue;
+                }
+                Event::Empty(start) => {
+                    if stack.is_empty() {
+                        prolog_end = pos;
+                    }
+                    Element::from_start(&start, pos..end)?
+                }
+                Event::End(_) => {
+                    let mut element =
+                        stack.pop().ok_or(eyre!("Unexpected end tag"))?;
+                    element.end = Some(pos..end);
+                    element
+                }
+                Event::Text(text) => {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.raw_text.push_str(&text.decode()?);
+                    }
+                    continue;
+                }
+                Event::GeneralRef(reference) => {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.raw_text.push('&');
+                        parent.raw_text.push_str(&reference.decode()?);
+                        parent.raw_text.push(';');
+                    }
+                    continue;
+                }
+                Event::CData(cdata) => {
+                    if let Some(parent) = stack.last_mut() {
+                        parent.raw_text.push_str(&quick_xml::escape::escape(
+                            cdata.decode()?,
+                        ));
+                    }
+                    continue;
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None if root.is_none() => root = Some(element),
+                None => bail!("Multiple root elements"),
+            }
+        }
+
+        let root = root.ok_or(eyre!("No root element"))?;
+        let epilog = root.span().end..src.len();
+        Ok(Document {
+            prolog: 0..prolog_end,
+            root,
+            epilog,
+        })
+    }
+}
+
+impl Element {
+    fn from_start(
+        start: &quick_xml::events::BytesStart,
+        span: Range<usize>,
+    ) -> Result<Element> {
+        let mut attributes = Vec::new();
+        for attr in start.attributes() {
+            let attr = attr?;
+            attributes.push((
+                String::from_utf8_lossy(attr.key.as_ref()).into_owned(),
+                attr.unescape_value()?.into_owned(),
+            ));
+        }
+        Ok(Element {
+            name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+            attributes,
+            start: span,
+            end: None,
+            children: Vec::new(),
+            raw_text: String::new(),
+        })
+    }
+}
+
+struct Style {
+    newline: String,
+    indent: String,
+}
+
+impl Style {
+    fn detect(doc: &Document) -> Style {
+        let newline = if doc.root.raw_text.contains("\r\n") {
+            "\r\n"
+        } else if doc.root.raw_text.contains('\n') {
+            "\n"
+        } else {
+            DEFAULT_NEWLINE
+        };
+        let indent = doc
+            .root
+            .raw_text
+            .split(newline)
+            .nth(1)
+            .filter(|indent| {
+                !indent.is_empty()
+                    && indent.chars().all(|c| c == ' ' || c == '\t')
+            })
+            .unwrap_or(DEFAULT_INDENT);
+        Style {
+            newline: newline.to_string(),
+            indent: indent.to_string(),
+        }
+    }
+}
+
+struct Patcher<'a> {
+    old_src: &'a str,
+    new_src: &'a str,
+    style: &'a Style,
+}
+
+impl Patcher<'_> {
+    /// Write `new` to `out`, reusing as much of `old` as possible. `line` is
+    /// the newline and indentation that precedes the element, and `schema`
+    /// describes it if known.
+    fn patch(
+        &self,
+        out: &mut String,
+        old: &Element,
+        new: &Element,
+        line: &str,
+        schema: Option<&Schema>,
+    ) {
+        if old.same_as(new, schema) {
+            out.push_str(&self.old_src[old.span()]);
+            return;
+        }
+
+        let same_attributes = old.same_attributes(new, schema);
+        let keeps_children =
+            old.children.iter().any(|child| child.is_preserved(schema));
+        if new.children.is_empty() && !keeps_children {
+            match (old.inner(), new.inner()) {
+                // Only the text changed, keep the tags as they were written
+                (Some(_), Some(new_inner)) if same_attributes => {
+                    out.push_str(&self.old_src[old.start.clone()]);
+                    out.push_str(&self.new_src[new_inner]);
+                    out.push_str(&self.old_src[old.end.clone().unwrap()]);
+                }
+                (_, new_inner) => {
+                    self.write_start_tag(out, old, new, schema, false);
+                    if let Some(new_inner) = new_inner {
+                        out.push_str(&self.new_src[new_inner]);
+                        out.push_str(&self.new_src[new.end.clone().unwrap()]);
+                    }
+                }
+            }
+            return;
+        }
+        if old.children.is_empty() {
+            self.write_start_tag(out, old, new, schema, true);
+            write_pretty_children(out, self.new_src, new, line, self.style);
+            return;
+        }
+
+        let old_src = self.old_src;
+        if same_attributes {
+            out.push_str(&old_src[old.start.clone()]);
+        } else {
+            self.write_start_tag(out, old, new, schema, true);
+        }
+
+        let child_line = old
+            .children
+            .first()
+            .map(|child| &old_src[old.start.end..child.start.start])
+            .filter(|gap| gap.trim().is_empty() && gap.contains('\n'))
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{line}{}", self.style.indent));
+
+        // Index of the old child each new child replaces
+        let new_to_old = new.match_children(old);
+        // New children without an old counterpart are inserted after the old
+        // child matching their closest preceding sibling.
+        let mut inserts: Vec<Vec<usize>> =
+            vec![Vec::new(); old.children.len() + 1];
+        let mut anchor = 0;
+        for (new_idx, matched) in new_to_old.iter().enumerate() {
+            match matched {
+                Some(old_idx) => anchor = old_idx + 1,
+                None => inserts[anchor].push(new_idx),
+            }
+        }
+
+        let write_inserts = |out: &mut String, anchor: usize| {
+            for &new_idx in &inserts[anchor] {
+                out.push_str(&child_line);
+                write_pretty(
+                    out,
+                    self.new_src,
+                    &new.children[new_idx],
+                    &child_line,
+                    self.style,
+                );
+            }
+        };
+
+        write_inserts(out, 0);
+        let mut cursor = old.start.end;
+        for (old_idx, old_child) in old.children.iter().enumerate() {
+            let gap = &old_src[cursor..old_child.start.start];
+            cursor = old_child.span().end;
+            match new_to_old.iter().position(|m| *m == Some(old_idx)) {
+                Some(new_idx) => {
+                    out.push_str(gap);
+                    let line = if gap.trim().is_empty() && gap.contains('\n') {
+                        gap
+                    } else {
+                        &child_line
+                    };
+                    self.patch(
+                        out,
+                        old_child,
+                        &new.children[new_idx],
+                        line,
+                        schema.and_then(|s| s.field(old_child.local_name())),
+                    );
+                }
+                None if old_child.is_preserved(schema) => {
+                    out.push_str(gap);
+                    out.push_str(&old_src[old_child.span()]);
+                }
+                // Removed from the document, drop it along with its indentation
+                None => {}
+            }
+            write_inserts(out, old_idx + 1);
+        }
+        out.push_str(&old_src[cursor..old.end.clone().unwrap().end]);
+    }
+
+    /// Write the start tag of `new`, keeping any unknown attributes of `old`.
+    /// `open` forces a start tag rather than an empty element tag.
+    fn write_start_tag(
+        &self,
+        out: &mut String,
+        old: &Element,
+        new: &Element,
+        schema: Option<&Schema>,
+        open: bool,
+    ) {
+        let tag = &self.new_src[new.start.clone()];
+        out.push_str(
+            tag.trim_end_matches('>').trim_end_matches('/').trim_end(),
+        );
+        for (name, value) in old.unknown_attributes(schema) {
+            let _ = write!(
+                out,
+                " {name}=\"{}\"",
+                quick_xml::escape::escape(value.as_str())
+            );
+        }
+        // The game writes `<Empty />`, quick-xml writes `<Empty/>`
+        out.push_str(if open || new.end.is_some() {
+            ">"
+        } else {
+            " />"
+        });
+    }
+}
+
+/// Write an element of a compact document with one child element per line.
+fn write_pretty(
+    out: &mut String,
+    src: &str,
+    element: &Element,
+    line: &str,
+    style: &Style,
+) {
+    if element.children.is_empty() {
+        match element.end {
+            Some(_) => out.push_str(&src[element.span()]),
+            // The game writes `<Empty />`, quick-xml writes `<Empty/>`
+            None => {
+                let tag = &src[element.start.clone()];
+                out.push_str(tag.trim_end_matches("/>").trim_end());
+                out.push_str(" />");
+            }
+        }
+        return;
+    }
+
+    out.push_str(&src[element.start.clone()]);
+    write_pretty_children(out, src, element, line, style);
+}
+
+/// Write the children and end tag of an element, one child per line.
+fn write_pretty_children(
+    out: &mut String,
+    src: &str,
+    element: &Element,
+    line: &str,
+    style: &Style,
+) {
+    let child_line = format!("{line}{}", style.indent);
+    for child in &element.children {
+        out.push_str(&child_line);
+        write_pretty(out, src, child, &child_line, style);
+    }
+    out.push_str(line);
+    out.push_str(&src[element.end.clone().unwrap()]);
+}
@@ -0,0 +1,351 @@
+//! Tags stored outside fleet files, in `fleet_tags.toml` next to `tags.toml`.
+//! Used instead of the `Tags:` line in fleet descriptions when
+//! [`TagStorage::Database`] is selected.
+//!
+//! Fleets are identified by their path, falling back to the hash of their
+//! contents so tags follow fleets that are moved or renamed. A fleet that is
+//! both moved and changed while NebTools isn't running loses its tags.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use color_eyre::{
+    Report, Result,
+    eyre::{Context, eyre},
+};
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    system::{
+        config::{APP_CONFIG, TagStorage, save_app_config},
+        fleet_io::{read_fleet, write_fleet},
+        load_fleets::{hash_bytes, load_fleets},
+        prefs::{init_prefs, load_prefs, save_prefs},
+    },
+    tags::{
+        Tag, format_description, get_tags_from_description, merge_tags,
+        split_hidden,
+    },
+};
+
+pub static TAG_DB: OnceLock<Mutex<TagDb>> = OnceLock::new();
+const TAG_DB_FILE: &str = "fleet_tags.toml";
+
+/// Fleets that were rewritten, and those that failed with the reason.
+pub type MovedTags = (Vec<PathBuf>, Vec<(PathBuf, Report)>);
+
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TaggedFleet {
+    pub path: PathBuf,
+    /// Hash of the fleet file when it was last seen
+    #[serde_as(as = "DisplayFromStr")]
+    pub hash: u64,
+    pub tags: Vec<Tag>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+pub struct TagDb {
+    #[serde(default, rename = "fleet")]
+    pub fleets: Vec<TaggedFleet>,
+}
+
+impl TagDb {
+    pub fn get(&self, path: &Path) -> &[Tag] {
+        self.fleets
+            .iter()
+            .find(|fleet| fleet.path == path)
+            .map_or(&[], |fleet| &fleet.tags)
+    }
+
+    /// Set the tags of the fleet at `path`, whose contents hash to `hash`.
+    pub fn set(&mut self, path: &Path, hash: u64, tags: Vec<Tag>) {
+        let idx = self.fleets.iter().position(|fleet| fleet.path == path);
+        match idx {
+            Some(idx) if tags.is_empty() => {
+                self.fleets.remove(idx);
+            }
+            Some(idx) => {
+                self.fleets[idx].hash = hash;
+                self.fleets[idx].tags = tags;
+            }
+            None if tags.is_empty() => {}
+            None => self.fleets.push(TaggedFleet {
+                path: path.to_path_buf(),
+                hash,
+                tags,
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.fleets.retain(|fleet| fleet.path != path);
+    }
+
+    /// Record that the fleet at `path` now hashes to `hash`. A fleet that isn't
+    /// known by its path takes the tags of a fleet with the same contents
+    /// whose file is gone, as it was moved there. Returns whether anything
+    /// changed.
+    pub fn identify(&mut self, path: &Path, hash: u64) -> bool {
+        if let Some(fleet) = self.fleets.iter_mut().find(|f| f.path == path) {
+            let changed = fleet.hash != hash;
+            fleet.hash = hash;
+            return changed;
+        }
+        // Identical fleets are common, so only a missing one can have moved
+        let moved = self
+            .fleets
+            .iter_mut()
+            .find(|f| f.hash == hash && !f.path.exists());
+        let Some(fleet) = moved else {
+            return false;
+        };
+        debug!("Moving tags of '{}'", fleet.path.display());
+        fleet.path = path.to_path_buf();
+        true
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = save_tag_db(self) {
+            error!("{}", err.wrap_err("Failed to save fleet tags"));
+        }
+    }
+}
+
+pub fn init_tag_db() {
+    init_prefs(&TAG_DB, TAG_DB_FILE, "fleet tags");
+}
+
+pub fn load_tag_db() -> Result<TagDb> {
+    load_prefs(TAG_DB_FILE, "fleet tags")
+}
+
+pub fn save_tag_db(tag_db: &TagDb) -> Result<()> {
+    save_prefs(TAG_DB_FILE, "fleet tags", tag_db)
+}
+
+/// Record the hashes of fleets that were added or changed, so their tags can
+/// still be found if they're moved. Does nothing if the database isn't loaded.
+pub fn identify_fleets<'a>(fleets: impl IntoIterator<Item = (&'a Path, u64)>) {
+    let Some(tag_db) = TAG_DB.get() else {
+        return;
+    };
+    let mut tag_db = tag_db.lock().unwrap();
+    let mut changed = false;
+    for (path, hash) in fleets {
+        changed |= tag_db.identify(path, hash);
+    }
+    if changed {
+        tag_db.save();
+    }
+}
+
+/// Hash of the fleet file at `path`, as used to identify it.
+pub fn hash_fleet_file(path: &Path) -> Result<u64> {
+    let bytes = std::fs::read(path)
+        .wrap_err(format!("Failed to read fleet '{}'", path.display()))?;
+    Ok(hash_bytes(&bytes))
+}
+
+/// Move the tags of every fleet to `to`, and store them there from now on.
+/// Tags already stored there are kept, so moving is lossless even if some
+/// fleets fail. Returns the fleets that were rewritten, and those that failed
+/// with the reason.
+pub fn move_tags(to: TagStorage) -> Result<MovedTags> {
+    info!("Moving tags to {:?}", to);
+    let tag_db = TAG_DB.get().ok_or(eyre!("Fleet tags not yet loaded"))?;
+    let mut changed = Vec::new();
+    let mut failed = Vec::new();
+    match to {
+        TagStorage::Database => {
+            let paths = load_fleets(None)?
+                .into_iter()
+                .map(|fleet| fleet.path)
+                .collect::<Vec<_>>();
+            // Saved before the descriptions are changed, so the tags are
+            // always stored somewhere
+            let mut moving = Vec::new();
+            for path in &paths {
+                let result = read_fleet(path).and_then(|fleet| {
+                    let (tags, _) = get_tags_from_description(
+                        fleet.description.as_deref().unwrap_or_default(),
+                    );
+                    Ok((fleet, tags, hash_fleet_file(path)?))
+                });
+                match result {
+                    Ok((_, tags, _)) if tags.is_empty() => {}
+                    Ok((fleet, tags, hash)) => {
+                        let mut tag_db = tag_db.lock().unwrap();
+                        let stored = tag_db.get(path).to_vec();
+                        tag_db.set(path, hash, merge_tags(&tags, stored));
+                        moving.push((path, fleet));
+                    }
+                    Err(err) => failed.push((path.clone(), err)),
+                }
+            }
+            save_tag_db(&tag_db.lock().unwrap())?;
+
+            for (path, mut fleet) in moving {
+                let (_, desc) = get_tags_from_description(
+                    fleet.description.as_deref().unwrap_or_default(),
+                );
+                fleet.description = Some(desc);
+                match write_fleet(path, &fleet)
+                    .and_then(|_| hash_fleet_file(path))
+                {
+                    Ok(hash) => {
+                        tag_db.lock().unwrap().identify(path, hash);
+                        changed.push(path.clone());
+                    }
+                    Err(err) => failed.push((path.clone(), err)),
+                }
+            }
+            tag_db.lock().unwrap().save();
+        }
+        TagStorage::Description => {
+            let paths = tag_db
+                .lock()
+                .unwrap()
+                .fleets
+                .iter()
+                .map(|fleet| fleet.path.clone())
+                .collect::<Vec<_>>();
+            for path in &paths {
+                let stored = tag_db.lock().unwrap().get(path).to_vec();
+                let result = read_fleet(path).and_then(|mut fleet| {
+                    let (tags, desc) = get_tags_from_description(
+                        fleet.description.as_deref().unwrap_or_default(),
+                    );
+                    // Tags hidden in game stay in the database
+                    let (shown, hidden) =
+                        split_hidden(&merge_tags(&stored, tags));
+                    fleet.description = Some(format_description(&shown, &desc));
+                    write_fleet(path, &fleet)?;
+                    Ok((hash_fleet_file(path)?, hidden))
+                });
+                // Only forgotten once they're in the description
+                match result {
+                    Ok((hash, hidden)) => {
+                        tag_db.lock().unwrap().set(path, hash, hidden);
+                        changed.push(path.clone());
+                    }
+                    Err(err) => failed.push((path.clone(), err)),
+                }
+            }
+            save_tag_db(&tag_db.lock().unwrap())?;
+        }
+    }
+    for (path, err) in &failed {
+        warn!("Failed to move tags of '{}': {:?}", path.display(), err);
+    }
+
+    APP_CONFIG
+        .get()
+        .ok_or(eyre!("App configuration not yet loaded"))?
+        .lock()
+        .unwrap()
+        .tag_storage = to;
+    save_app_config()?;
+    Ok((changed, failed))
+}
+
+/// Write the stored tags of the fleets in `paths` into their `Tags:` line, so
+/// they're visible in game.
+pub fn export_tags(paths: &[PathBuf]) -> MovedTags {
+    let mut changed = Vec::new();
+    let mut failed = Vec::new();
+    let Some(tag_db) = TAG_DB.get() else {
+        return (changed, failed);
+    };
+    for path in paths {
+        let stored = tag_db.lock().unwrap().get(path).to_vec();
+        let result = read_fleet(path).and_then(|mut fleet| {
+            let (tags, desc) = get_tags_from_description(
+                fleet.description.as_deref().unwrap_or_default(),
+            );
+            // Tags in groups hidden in game are left out
+            let (shown, _) = split_hidden(&stored);
+            if tags == shown {
+                return Ok(false);
+            }
+            fleet.description = Some(format_description(&shown, &desc));
+            write_fleet(path, &fleet)?;
+            // Keep following the fleet now its contents have changed
+            tag_db
+                .lock()
+                .unwrap()
+                .identify(path, hash_fleet_file(path)?);
+            Ok(true)
+        });
+        match result {
+            Ok(true) => changed.push(path.clone()),
+            Ok(false) => {}
+            Err(err) => {
+                warn!(
+                    "Failed to export tags of '{}': {:?}",
+                    path.display(),
+                    err
+                );
+                failed.push((path.clone(), err));
+            }
+        }
+    }
+    tag_db.lock().unwrap().save();
+    info!(
+        "Exported tags to {} of {} fleets",
+        changed.len(),
+        paths.len()
+    );
+    (changed, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str) -> Tag {
+        Tag {
+            name: name.to_string(),
+            color: "ff0000".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn identify_moved_fleets() {
+        let dir = std::env::temp_dir()
+            .join(format!("nebtools-tag-db-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let original = dir.join("original.fleet");
+        let copied = dir.join("copied.fleet");
+        let moved = dir.join("moved.fleet");
+        std::fs::write(&original, "fleet").unwrap();
+
+        let mut tag_db = TagDb::default();
+        tag_db.set(&original, 1, vec![tag("brawler")]);
+        assert_eq!(tag_db.get(&original), [tag("brawler")]);
+
+        // Changed in place
+        assert!(tag_db.identify(&original, 2));
+        assert!(!tag_db.identify(&original, 2));
+        // An identical fleet while the original still exists
+        assert!(!tag_db.identify(&copied, 2));
+        assert_eq!(tag_db.get(&copied), []);
+        // Moved once the original is gone
+        std::fs::remove_file(&original).unwrap();
+        assert!(tag_db.identify(&moved, 2));
+        assert_eq!(tag_db.get(&moved), [tag("brawler")]);
+        assert_eq!(tag_db.get(&original), []);
+        // Unknown contents have no tags
+        assert!(!tag_db.identify(&original, 3));
+        assert_eq!(tag_db.fleets.len(), 1);
+
+        // Setting no tags forgets the fleet
+        tag_db.set(&moved, 2, Vec::new());
+        assert!(tag_db.fleets.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,23 +1,32 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Display,
     fs::OpenOptions,
     io::Write,
     ops::Deref,
+    path::{Path, PathBuf},
     str::FromStr,
     sync::{Mutex, OnceLock},
 };
 
 use chumsky::prelude::*;
 use color_eyre::{
-    Result,
+    Report, Result,
     eyre::{Context, eyre},
 };
 use palette::{encoding::Srgb, rgb::Rgb};
 use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
-use text::whitespace;
-use tracing::{debug, error, trace, warn};
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{
+    fleet_data::FleetData,
+    system::{
+        config::{APP_CONFIG, TagStorage, default_true},
+        fleet_io::{read_fleet, write_fleet},
+    },
+    tag_db::{TAG_DB, hash_fleet_file, save_tag_db},
+};
 
 pub static TAGS_REPO: OnceLock<Mutex<TagsRepository>> = OnceLock::new();
 
@@ -94,6 +103,7 @@ pub fn save_tags(tags_repo: &TagsRepository) -> Result<()> {
     let mut tags_file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(&tags_path)
         .wrap_err("Failed to open tags file")?;
     let toml =
@@ -113,11 +123,32 @@ pub struct Tag {
     pub color: Color,
 }
 
+/// Tags sharing a colour palette and whether they're shown in game. A group
+/// also contains the descendants of its tags, so `tournament` includes
+/// `tournament/2026/week3`.
+#[serde_as]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TagGroup {
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Colours given to new tags in the group, in order
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    pub palette: Vec<Color>,
+    /// Whether the group's tags are written into the `Tags:` line of fleet
+    /// descriptions, or only kept in NebTools
+    #[serde(default = "default_true")]
+    pub show_in_game: bool,
+}
+
 #[serde_as]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TagsRepository {
     #[serde_as(as = "HashMap<_, DisplayFromStr>")]
     pub tags: HashMap<String, Color>,
+    #[serde(default, rename = "group")]
+    pub groups: Vec<TagGroup>,
 }
 
 impl TagsRepository {
@@ -125,8 +156,132 @@ impl TagsRepository {
         self.tags.insert(name, color);
         self.save();
     }
-    pub fn get_tag(&self, name: &String) -> Option<&Color> {
-        self.tags.get(name)
+
+    /// Colour to suggest for the tag `name`: the colour it was last used with,
+    /// else the next colour in its group's palette, else the colour of its
+    /// closest ancestor.
+    pub fn suggest_color(&self, name: &str) -> Option<Color> {
+        if let Some(color) = self.tags.get(name) {
+            return Some(color.clone());
+        }
+        if let Some(group) = self.group_of(name)
+            && !group.palette.is_empty()
+        {
+            let used = self
+                .tags
+                .keys()
+                .filter(|tag| {
+                    self.group_of(tag).is_some_and(|g| g.name == group.name)
+                })
+                .count();
+            return Some(group.palette[used % group.palette.len()].clone());
+        }
+        tag_ancestors(name)
+            .find_map(|ancestor| self.tags.get(ancestor).cloned())
+    }
+
+    /// The group containing the tag `name`, directly or through an ancestor.
+    pub fn group_of(&self, name: &str) -> Option<&TagGroup> {
+        self.groups
+            .iter()
+            .find(|group| group.tags.iter().any(|tag| tag_is_under(name, tag)))
+    }
+
+    pub fn shown_in_game(&self, name: &str) -> bool {
+        self.group_of(name).is_none_or(|group| group.show_in_game)
+    }
+
+    /// Changes recolouring the tags of the group named `group` among `tags`
+    /// with its palette, in order.
+    pub fn palette_changes(&self, group: &str, tags: &[Tag]) -> Vec<TagChange> {
+        let Some(palette) = self
+            .groups
+            .iter()
+            .find(|g| g.name == group)
+            .map(|g| &g.palette)
+            .filter(|palette| !palette.is_empty())
+        else {
+            return Vec::new();
+        };
+        tags.iter()
+            .filter(|tag| {
+                self.group_of(&tag.name).is_some_and(|g| g.name == group)
+            })
+            .zip(palette.iter().cycle())
+            .filter(|(tag, color)| tag.color != **color)
+            .map(|(tag, color)| {
+                TagChange::Recolor(tag.name.clone(), color.clone())
+            })
+            .collect()
+    }
+
+    /// Add `group`, replacing any group with the same name.
+    pub fn set_group(&mut self, group: TagGroup) {
+        match self.groups.iter_mut().find(|g| g.name == group.name) {
+            Some(existing) => *existing = group,
+            None => self.groups.push(group),
+        }
+        self.save();
+    }
+
+    pub fn remove_group(&mut self, name: &str) {
+        self.groups.retain(|group| group.name != name);
+        self.save();
+    }
+
+    /// Move the tag `name` into the group named `group`, or out of every group
+    /// if `None`.
+    pub fn move_to_group(&mut self, name: &str, group: Option<&str>) {
+        for existing in &mut self.groups {
+            existing.tags.retain(|tag| tag != name);
+        }
+        if let Some(group) =
+            self.groups.iter_mut().find(|g| Some(&*g.name) == group)
+        {
+            group.tags.push(name.to_string());
+        }
+        self.save();
+    }
+
+    /// Record `change`, so the tag is suggested with its new name or colour.
+    pub fn apply_change(&mut self, change: &TagChange) {
+        match change {
+            TagChange::Rename { from, to } => self.rename_stored(from, to),
+            TagChange::Delete(name) => {
+                self.tags.remove(name);
+                for group in &mut self.groups {
+                    group.tags.retain(|tag| tag != name);
+                }
+            }
+            TagChange::Recolor(name, color) => {
+                self.tags.insert(name.clone(), color.clone());
+            }
+        }
+        self.save();
+    }
+
+    /// Rename the tag `from` and its descendants, without saving.
+    fn rename_stored(&mut self, from: &str, to: &Tag) {
+        let descendants = self
+            .tags
+            .keys()
+            .filter(|tag| *tag != from && tag_is_under(tag, from))
+            .cloned()
+            .collect::<Vec<_>>();
+        for tag in descendants {
+            if let Some(color) = self.tags.remove(&tag) {
+                self.tags.insert(renamed_tag(&tag, from, &to.name), color);
+            }
+        }
+        self.tags.remove(from);
+        self.tags.insert(to.name.clone(), to.color.clone());
+        for group in &mut self.groups {
+            for tag in
+                group.tags.iter_mut().filter(|tag| tag_is_under(tag, from))
+            {
+                *tag = renamed_tag(tag, from, &to.name);
+            }
+        }
     }
 
     pub fn save(&self) {
@@ -139,50 +294,787 @@ impl Default for TagsRepository {
     fn default() -> Self {
         Self {
             tags: Default::default(),
+            groups: Default::default(),
         }
     }
 }
 
-pub fn get_tags_from_description(desc: &str) -> Result<(Vec<Tag>, String)> {
-    if desc.starts_with("Tags:") {
-        trace!("Parsing tags");
-        let parser = tags_parser();
-        let res = parser
-            .parse(desc)
-            .map_err(|mut errs| eyre!(errs.remove(0)))
-            .wrap_err("Failed to parse tags")?;
+/// Whether `tag` is `ancestor` or one of its descendants, such as
+/// `tournament/2026` under `tournament`.
+pub fn tag_is_under(tag: &str, ancestor: &str) -> bool {
+    tag.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
 
-        trace!("Found {} tags: {:?}", res.0.len(), res.0);
+/// The name of `tag`, which must be under `from`, once `from` is renamed to
+/// `to`.
+fn renamed_tag(tag: &str, from: &str, to: &str) -> String {
+    format!("{to}{}", &tag[from.len()..])
+}
 
-        Ok(res)
-    } else {
-        Ok((Vec::new(), desc.to_string()))
+/// Ancestors of a hierarchical tag, closest first.
+fn tag_ancestors(name: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(name), |name| {
+        name.rsplit_once('/').map(|(parent, _)| parent)
+    })
+    .skip(1)
+}
+
+/// Split a description into its tags and the rest of the text. Tags are read
+/// from a first line starting with `Tags:`, in the rich text the game displays.
+/// Anything on that line that isn't a tag is kept at the start of the text.
+/// If no tags are found, the description is returned unchanged.
+pub fn get_tags_from_description(desc: &str) -> (Vec<Tag>, String) {
+    let Some(line) = desc.strip_prefix("Tags:") else {
+        return (Vec::new(), desc.to_string());
+    };
+    let (line, rest) = line.split_once('\n').unwrap_or((line, ""));
+    trace!("Parsing tags");
+    let tokens = match rich_text_parser().parse(line.trim_end_matches('\r')) {
+        Ok(tokens) => tokens,
+        Err(errs) => {
+            warn!(?errs, "Failed to parse tags");
+            return (Vec::new(), desc.to_string());
+        }
+    };
+
+    let mut tags = Vec::new();
+    let mut malformed = String::new();
+    let mut segment = None::<ColorSegment>;
+    for token in tokens {
+        match &token {
+            RichText::Open { name, value }
+                if name.eq_ignore_ascii_case("color") =>
+            {
+                // The game also accepts a new colour without closing the last
+                if let Some(segment) = segment.take() {
+                    segment.finish(&mut tags, &mut malformed);
+                }
+                segment = Some(ColorSegment {
+                    color: value.as_deref().and_then(parse_color),
+                    name: String::new(),
+                    raw: token.to_string(),
+                });
+            }
+            RichText::Close(name) if name.eq_ignore_ascii_case("color") => {
+                if let Some(mut segment) = segment.take() {
+                    segment.raw += &token.to_string();
+                    segment.finish(&mut tags, &mut malformed);
+                }
+            }
+            RichText::Text(text) => match &mut segment {
+                Some(segment) => {
+                    segment.name += text;
+                    segment.raw += text;
+                }
+                None => malformed.push_str(text),
+            },
+            // Other rich text, such as `<b>` or `<size=20>`, only changes how
+            // the tag looks
+            _ => {
+                if let Some(segment) = &mut segment {
+                    segment.raw += &token.to_string();
+                }
+            }
+        }
+    }
+    if let Some(segment) = segment {
+        segment.finish(&mut tags, &mut malformed);
+    }
+    trace!("Found {} tags: {:?}", tags.len(), tags);
+
+    if tags.is_empty() {
+        return (Vec::new(), desc.to_string());
     }
+    let mut text = malformed.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() && !rest.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(rest);
+    (tags, text)
+}
+
+/// Prefix `desc` with a `Tags:` line containing `tags`, as understood by
+/// [`get_tags_from_description`]. `desc` must not already contain tags.
+pub fn format_description(tags: &[Tag], desc: &str) -> String {
+    if tags.is_empty() {
+        return desc.to_string();
+    }
+    format!(
+        "Tags: {}\n{}",
+        tags.iter()
+            .map(|tag| format!(
+                "<color=#{:02x}{:02x}{:02x}>{}</color>",
+                tag.color.red, tag.color.green, tag.color.blue, tag.name
+            ))
+            .collect::<Vec<_>>()
+            .join(" "),
+        desc,
+    )
+}
+
+/// A change to a tag, made to every fleet using it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagChange {
+    /// Rename a tag along with its descendants, so renaming `tournament` also
+    /// renames `tournament/2026`. Fleets that already have a tag with a new
+    /// name keep it, merging the two.
+    Rename {
+        from: String,
+        to: Tag,
+    },
+    Delete(String),
+    Recolor(String, Color),
 }
 
-fn tags_parser() -> impl Parser<char, (Vec<Tag>, String), Error = Simple<char>>
+impl TagChange {
+    /// Apply the change to a fleet's tags, returning whether they changed.
+    pub fn apply(&self, tags: &mut Vec<Tag>) -> bool {
+        let before = tags.clone();
+        match self {
+            TagChange::Rename { from, to } => {
+                let kept = tags
+                    .iter()
+                    .filter(|tag| !tag_is_under(&tag.name, from))
+                    .map(|tag| tag.name.clone())
+                    .collect::<HashSet<_>>();
+                let mut renamed = HashSet::new();
+                tags.retain_mut(|tag| {
+                    if !tag_is_under(&tag.name, from) {
+                        return true;
+                    }
+                    let name = renamed_tag(&tag.name, from, &to.name);
+                    if kept.contains(&name) || !renamed.insert(name.clone()) {
+                        return false;
+                    }
+                    *tag = if name == to.name {
+                        to.clone()
+                    } else {
+                        Tag {
+                            name,
+                            color: tag.color.clone(),
+                        }
+                    };
+                    true
+                });
+            }
+            TagChange::Delete(name) => tags.retain(|tag| tag.name != *name),
+            TagChange::Recolor(name, color) => {
+                for tag in tags.iter_mut().filter(|tag| tag.name == *name) {
+                    tag.color = color.clone();
+                }
+            }
+        }
+        *tags != before
+    }
+
+    /// Apply the change to a fleet description, returning the new description
+    /// if its tags changed.
+    pub fn apply_to_description(&self, desc: &str) -> Option<String> {
+        let (mut tags, desc) = get_tags_from_description(desc);
+        self.apply(&mut tags)
+            .then(|| format_description(&tags, &desc))
+    }
+}
+
+/// Where fleet tags are stored, as configured.
+pub fn tag_storage() -> TagStorage {
+    APP_CONFIG
+        .get()
+        .map(|config| config.lock().unwrap().tag_storage)
+        .unwrap_or_default()
+}
+
+/// Tags of the fleet at `path` with description `desc`, from wherever tags
+/// are stored.
+pub fn stored_tags(path: &Path, desc: &str) -> Vec<Tag> {
+    match tag_storage() {
+        // Tags hidden in game are kept in the database
+        TagStorage::Description => {
+            merge_tags(&get_tags_from_description(desc).0, db_tags(path))
+        }
+        TagStorage::Database => db_tags(path),
+    }
+}
+
+pub fn fleet_tags(fleet: &FleetData) -> Vec<Tag> {
+    stored_tags(&fleet.path, &fleet.description)
+}
+
+/// `tags` followed by any of `other` with a name not already in `tags`.
+pub fn merge_tags(tags: &[Tag], other: Vec<Tag>) -> Vec<Tag> {
+    let mut merged = tags.to_vec();
+    for tag in other {
+        if !merged.iter().any(|t| t.name == tag.name) {
+            merged.push(tag);
+        }
+    }
+    merged
+}
+
+/// Split `tags` into those shown in game and those whose group is hidden.
+pub fn split_hidden(tags: &[Tag]) -> (Vec<Tag>, Vec<Tag>) {
+    let Some(tags_repo) = TAGS_REPO.get() else {
+        return (tags.to_vec(), Vec::new());
+    };
+    let tags_repo = tags_repo.lock().unwrap();
+    tags.iter()
+        .cloned()
+        .partition(|tag| tags_repo.shown_in_game(&tag.name))
+}
+
+/// `desc` prefixed with the tags of the fleet at `path` that are shown in
+/// game. The rest are kept in the tag database.
+pub fn tagged_description(
+    path: &Path,
+    tags: &[Tag],
+    desc: &str,
+) -> Result<String> {
+    let (shown, hidden) = split_hidden(tags);
+    set_db_tags(path, hidden)?;
+    Ok(format_description(&shown, desc))
+}
+
+/// Replace the tags of the fleet at `path`, wherever tags are stored.
+pub fn set_fleet_tags(path: &Path, tags: &[Tag]) -> Result<()> {
+    match tag_storage() {
+        TagStorage::Description => {
+            let mut fleet = read_fleet(path)?;
+            let (_, desc) = get_tags_from_description(
+                fleet.description.as_deref().unwrap_or_default(),
+            );
+            fleet.description = Some(tagged_description(path, tags, &desc)?);
+            write_fleet(path, &fleet)
+        }
+        TagStorage::Database => set_db_tags(path, tags.to_vec()),
+    }
+}
+
+fn db_tags(path: &Path) -> Vec<Tag> {
+    TAG_DB
+        .get()
+        .map(|tag_db| tag_db.lock().unwrap().get(path).to_vec())
+        .unwrap_or_default()
+}
+
+fn set_db_tags(path: &Path, tags: Vec<Tag>) -> Result<()> {
+    let mut tag_db = TAG_DB
+        .get()
+        .ok_or(eyre!("Fleet tags not yet loaded"))?
+        .lock()
+        .unwrap();
+    let hash = hash_fleet_file(path)?;
+    // Picks up the tags of a fleet moved here first
+    let moved = tag_db.identify(path, hash);
+    if !moved && tag_db.get(path) == tags {
+        return Ok(());
+    }
+    tag_db.set(path, hash, tags);
+    save_tag_db(&tag_db)
+}
+
+/// Move tags between the descriptions of the fleets in `paths` and the tag
+/// database, after their groups were shown or hidden in game. Returns the
+/// fleets that were changed, and those that failed with the reason.
+pub fn refresh_fleet_tags(
+    paths: &[PathBuf],
+) -> (Vec<PathBuf>, Vec<(PathBuf, Report)>) {
+    let mut changed = Vec::new();
+    let mut failed = Vec::new();
+    if tag_storage() == TagStorage::Database {
+        return (changed, failed);
+    }
+    for path in paths {
+        let result = read_fleet(path).and_then(|fleet| {
+            let (tags, _) = get_tags_from_description(
+                fleet.description.as_deref().unwrap_or_default(),
+            );
+            let hidden = db_tags(path);
+            let all = merge_tags(&tags, hidden.clone());
+            if split_hidden(&all) == (tags, hidden) {
+                return Ok(false);
+            }
+            set_fleet_tags(path, &all)?;
+            Ok(true)
+        });
+        match result {
+            Ok(true) => changed.push(path.clone()),
+            Ok(false) => {}
+            Err(err) => {
+                warn!(
+                    "Failed to refresh tags of '{}': {:?}",
+                    path.display(),
+                    err
+                );
+                failed.push((path.clone(), err));
+            }
+        }
+    }
+    info!(
+        "Refreshed tags of {} of {} fleets",
+        changed.len(),
+        paths.len()
+    );
+    (changed, failed)
+}
+
+/// Fleets whose tags `change` would modify.
+pub fn fleets_affected_by<'a>(
+    fleets: &'a [FleetData],
+    change: &TagChange,
+) -> Vec<&'a FleetData> {
+    fleets
+        .iter()
+        .filter(|fleet| change.apply(&mut fleet_tags(fleet)))
+        .collect()
+}
+
+/// Change the tags of each fleet in `paths` and record the change in
+/// [`TAGS_REPO`]. Fleets that fail to update are skipped and returned with the
+/// reason.
+pub fn apply_tag_change(
+    paths: &[PathBuf],
+    change: &TagChange,
+) -> Vec<(PathBuf, Report)> {
+    info!("Applying {:?} to {} fleets", change, paths.len());
+    let mut failed = Vec::new();
+    let storage = tag_storage();
+    if storage == TagStorage::Description {
+        for path in paths {
+            let result = read_fleet(path).and_then(|mut fleet| {
+                let desc = fleet.description.as_deref().unwrap_or_default();
+                let Some(desc) = change.apply_to_description(desc) else {
+                    return Ok(());
+                };
+                fleet.description = Some(desc);
+                write_fleet(path, &fleet)
+            });
+            if let Err(err) = result {
+                warn!(
+                    "Failed to update tags of '{}': {:?}",
+                    path.display(),
+                    err
+                );
+                failed.push((path.clone(), err));
+            }
+        }
+    }
+    // Tags kept out of fleet files, or hidden in game
+    if let Some(tag_db) = TAG_DB.get() {
+        let mut tag_db = tag_db.lock().unwrap();
+        for fleet in &mut tag_db.fleets {
+            if paths.contains(&fleet.path) {
+                change.apply(&mut fleet.tags);
+            }
+        }
+        tag_db.fleets.retain(|fleet| !fleet.tags.is_empty());
+        if let Err(err) = save_tag_db(&tag_db) {
+            warn!("Failed to save fleet tags: {:?}", err);
+            failed.extend(paths.iter().map(|path| {
+                (path.clone(), eyre!("Failed to save fleet tags"))
+            }));
+        }
+    }
+    TAGS_REPO
+        .get()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .apply_change(change);
+    if storage == TagStorage::Description {
+        // A renamed tag can move into a group hidden in game
+        let paths = paths
+            .iter()
+            .filter(|path| !failed.iter().any(|(p, _)| p == *path))
+            .cloned()
+            .collect::<Vec<_>>();
+        failed.extend(refresh_fleet_tags(&paths).1);
+    }
+    failed
+}
+
+/// Apply each of `changes` to the fleets among `fleets` it modifies. Returns
+/// the fleets that were changed, and those that failed with the reason.
+pub fn apply_tag_changes(
+    fleets: &[FleetData],
+    changes: &[TagChange],
+) -> (Vec<PathBuf>, Vec<(PathBuf, Report)>) {
+    let mut changed = Vec::new();
+    let mut failed = Vec::new();
+    for change in changes {
+        let paths = fleets_affected_by(fleets, change)
+            .iter()
+            .map(|fleet| fleet.path.clone())
+            .collect::<Vec<_>>();
+        failed.extend(apply_tag_change(&paths, change));
+        changed.extend(paths);
+    }
+    changed.sort();
+    changed.dedup();
+    (changed, failed)
+}
+
+/// Colours Unity rich text understands by name.
+const NAMED_COLORS: [(&str, &str); 23] = [
+    ("aqua", "00ffff"),
+    ("black", "000000"),
+    ("blue", "0000ff"),
+    ("brown", "a52a2a"),
+    ("cyan", "00ffff"),
+    ("darkblue", "0000a0"),
+    ("fuchsia", "ff00ff"),
+    ("green", "008000"),
+    ("grey", "808080"),
+    ("gray", "808080"),
+    ("lightblue", "add8e6"),
+    ("lime", "00ff00"),
+    ("magenta", "ff00ff"),
+    ("maroon", "800000"),
+    ("navy", "000080"),
+    ("olive", "808000"),
+    ("orange", "ffa500"),
+    ("purple", "800080"),
+    ("red", "ff0000"),
+    ("silver", "c0c0c0"),
+    ("teal", "008080"),
+    ("white", "ffffff"),
+    ("yellow", "ffff00"),
+];
+
+/// Parse a rich text colour: a name, `#RGB`, `#RGBA`, `#RRGGBB` or
+/// `#RRGGBBAA`. Transparency is ignored.
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+    if let Some((_, hex)) = NAMED_COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+    {
+        return hex.parse().ok();
+    }
+    let hex = value.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let hex = match hex.len() {
+        3 | 4 => hex.chars().take(3).flat_map(|c| [c, c]).collect(),
+        6 | 8 => hex[..6].to_string(),
+        _ => return None,
+    };
+    hex.parse().ok()
+}
+
+/// A piece of rich text.
+#[derive(Debug, Clone, PartialEq)]
+enum RichText {
+    /// `<name>` or `<name=value>`
+    Open {
+        name: String,
+        value: Option<String>,
+    },
+    /// `</name>`
+    Close(String),
+    Text(String),
+}
+
+impl Display for RichText {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RichText::Open { name, value: None } => write!(f, "<{name}>"),
+            RichText::Open {
+                name,
+                value: Some(value),
+            } => write!(f, "<{name}={value}>"),
+            RichText::Close(name) => write!(f, "</{name}>"),
+            RichText::Text(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+/// A `<color>` tag and the text it colours.
+struct ColorSegment {
+    /// `None` if the colour couldn't be understood
+    color: Option<Color>,
+    name: String,
+    /// The segment as written, to keep if it isn't a valid tag
+    raw: String,
+}
+
+impl ColorSegment {
+    fn finish(self, tags: &mut Vec<Tag>, malformed: &mut String) {
+        let name = self.name.trim();
+        match self.color {
+            Some(color) if !name.is_empty() => tags.push(Tag {
+                name: name.to_string(),
+                color,
+            }),
+            _ => {
+                malformed.push(' ');
+                malformed.push_str(&self.raw);
+                malformed.push(' ');
+            }
+        }
+    }
+}
+
+/// Split rich text into tags and text. Accepts any input, a `<` that doesn't
+/// start a tag is text.
+fn rich_text_parser() -> impl Parser<char, Vec<RichText>, Error = Simple<char>>
 {
-    just("Tags:")
-        .ignore_then(whitespace())
-        .ignore_then(
-            just('<')
-                .ignore_then(just("color="))
-                .ignore_then(take_until(just('>')).map(|(hex, _)| {
-                    // let full = u32::from_str_radix(&String::from_iter(hex), 16)
-                    //     .unwrap();
-
-                    <Color as std::str::FromStr>::from_str(&String::from_iter(
-                        hex,
-                    ))
-                    .expect("Hex should already be guaranteed")
-                }))
-                .then(take_until(just("</color>")))
-                .map(|(col, (text, _))| Tag {
-                    color: col,
-                    name: String::from_iter(text).into(),
-                })
-                .then_ignore(whitespace())
-                .repeated(),
-        )
-        .then(take_until(end()).map(|(text, _)| String::from_iter(text)))
+    let name = || {
+        filter(|c: &char| c.is_ascii_alphabetic() || *c == '-')
+            .repeated()
+            .at_least(1)
+            .collect::<String>()
+    };
+    let value = just('=').ignore_then(
+        filter(|c: &char| *c != '<' && *c != '>')
+            .repeated()
+            .collect::<String>(),
+    );
+    let open = just('<')
+        .ignore_then(name())
+        .then(value.or_not())
+        .then_ignore(just('>'))
+        .map(|(name, value)| RichText::Open { name, value });
+    let close = just("</")
+        .ignore_then(name())
+        .then_ignore(just('>'))
+        .map(RichText::Close);
+    let text = filter(|c: &char| *c != '<')
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .or(any().map(|c: char| c.to_string()))
+        .map(RichText::Text);
+
+    choice((close, open, text)).repeated().then_ignore(end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str, color: &str) -> Tag {
+        Tag {
+            name: name.to_string(),
+            color: color.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn tag_lines() {
+        let parse = |desc: &str| get_tags_from_description(desc);
+
+        let desc = format_description(
+            &[tag("brawler", "ff0000"), tag("meta", "00ff00")],
+            "Line ships\nSecond line",
+        );
+        assert_eq!(
+            parse(&desc),
+            (
+                vec![tag("brawler", "ff0000"), tag("meta", "00ff00")],
+                "Line ships\nSecond line".to_string()
+            )
+        );
+
+        // Named colours, short hex and transparency
+        assert_eq!(
+            parse(
+                "Tags: <color=red>a</color> <color=#0F0>b</color> \
+                 <color=#00f8>c</color> <color=#ffffff80>d</color>"
+            )
+            .0,
+            [
+                tag("a", "ff0000"),
+                tag("b", "00ff00"),
+                tag("c", "0000ff"),
+                tag("d", "ffffff")
+            ]
+        );
+
+        // Formatting inside and around tags, and unterminated tags
+        assert_eq!(
+            parse(
+                "Tags: <b><color=#ff0000><i>a</i></color></b> \
+                 <color=\"blue\"><size=20>b <color=yellow>c\nrest"
+            ),
+            (
+                vec![
+                    tag("a", "ff0000"),
+                    tag("b", "0000ff"),
+                    tag("c", "ffff00")
+                ],
+                "rest".to_string()
+            )
+        );
+
+        // Anything that isn't a tag is kept as text
+        assert_eq!(
+            parse(
+                "Tags: <color=zz>bad</color> <color=red>a</color> a<3 \
+                 <color=#ff0000></color>\nrest"
+            ),
+            (
+                vec![tag("a", "ff0000")],
+                "<color=zz>bad</color> a<3 <color=#ff0000></color>\nrest"
+                    .to_string()
+            )
+        );
+        for desc in ["Tags: <color=zz>", "Tags:", "Tags: <", "No tags here"] {
+            assert_eq!(parse(desc), (Vec::new(), desc.to_string()));
+        }
+    }
+
+    #[test]
+    fn tag_groups() {
+        assert!(tag_is_under("tournament/2026/week3", "tournament"));
+        assert!(tag_is_under("tournament", "tournament"));
+        assert!(!tag_is_under("tournaments", "tournament"));
+        assert_eq!(
+            tag_ancestors("tournament/2026/week3").collect::<Vec<_>>(),
+            ["tournament/2026", "tournament"]
+        );
+
+        let palette = ["ff0000", "00ff00"].map(|hex| hex.parse().unwrap());
+        let repo = TagsRepository {
+            tags: HashMap::from([
+                ("tournament".to_string(), "0000ff".parse().unwrap()),
+                ("2v2".to_string(), "ff0000".parse().unwrap()),
+            ]),
+            groups: vec![TagGroup {
+                name: "Events".to_string(),
+                tags: vec!["tournament".to_string(), "practice".to_string()],
+                palette: palette.to_vec(),
+                show_in_game: false,
+            }],
+        };
+        assert_eq!(
+            repo.group_of("tournament/2026").map(|g| &*g.name),
+            Some("Events")
+        );
+        assert!(repo.group_of("2v2").is_none());
+        assert!(!repo.shown_in_game("practice"));
+        assert!(repo.shown_in_game("2v2"));
+
+        // Known tags keep their colour, new tags in a group take the next
+        // colour of its palette, and other new tags that of their ancestor
+        assert_eq!(repo.suggest_color("2v2"), "ff0000".parse().ok());
+        assert_eq!(
+            repo.suggest_color("practice/ranked"),
+            Some(palette[1].clone())
+        );
+        assert_eq!(repo.suggest_color("2v2/ranked"), "ff0000".parse().ok());
+        assert_eq!(repo.suggest_color("meta"), None);
+
+        let changes = repo.palette_changes(
+            "Events",
+            &[
+                tag("2v2", "ff0000"),
+                tag("practice", "ff0000"),
+                tag("tournament", "0000ff"),
+            ],
+        );
+        assert_eq!(
+            changes,
+            [TagChange::Recolor(
+                "tournament".to_string(),
+                palette[1].clone()
+            )]
+        );
+    }
+
+    #[test]
+    fn tag_changes() {
+        let desc = format_description(
+            &[tag("brawler", "ff0000"), tag("meta", "00ff00")],
+            "Line ships",
+        );
+        let apply = |change: TagChange| {
+            let desc = change.apply_to_description(&desc)?;
+            Some(get_tags_from_description(&desc))
+        };
+
+        let (tags, rest) = apply(TagChange::Rename {
+            from: "brawler".to_string(),
+            to: tag("close range", "0000ff"),
+        })
+        .unwrap();
+        assert_eq!(tags, [tag("close range", "0000ff"), tag("meta", "00ff00")]);
+        assert_eq!(rest, "Line ships");
+
+        // Renaming onto a tag the fleet already has merges them
+        let (tags, _) = apply(TagChange::Rename {
+            from: "brawler".to_string(),
+            to: tag("meta", "00ff00"),
+        })
+        .unwrap();
+        assert_eq!(tags, [tag("meta", "00ff00")]);
+
+        let (tags, _) = apply(TagChange::Recolor(
+            "meta".to_string(),
+            "123456".parse().unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(tags, [tag("brawler", "ff0000"), tag("meta", "123456")]);
+
+        let (tags, rest) =
+            apply(TagChange::Delete("brawler".to_string())).unwrap();
+        assert_eq!(tags, [tag("meta", "00ff00")]);
+        assert_eq!(rest, "Line ships");
+
+        // Fleets without the tag are left alone
+        assert_eq!(apply(TagChange::Delete("missing".to_string())), None);
+        assert_eq!(
+            apply(TagChange::Recolor(
+                "meta".to_string(),
+                "00ff00".parse().unwrap()
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn renaming_descendants() {
+        let mut tags = vec![
+            tag("tournament", "ff0000"),
+            tag("tournament/2026", "00ff00"),
+            tag("tournaments", "0000ff"),
+            tag("cup/2026", "ffff00"),
+        ];
+        let rename = TagChange::Rename {
+            from: "tournament".to_string(),
+            to: tag("cup", "123456"),
+        };
+        assert!(rename.apply(&mut tags));
+        // Descendants keep their colour, and those the fleet already has are
+        // merged
+        assert_eq!(
+            tags,
+            [
+                tag("cup", "123456"),
+                tag("tournaments", "0000ff"),
+                tag("cup/2026", "ffff00")
+            ]
+        );
+
+        let mut repo = TagsRepository::default();
+        repo.tags
+            .insert("tournament".to_string(), "ff0000".parse().unwrap());
+        repo.tags
+            .insert("tournament/2026".to_string(), "00ff00".parse().unwrap());
+        repo.groups.push(TagGroup {
+            name: "events".to_string(),
+            tags: vec!["tournament/2026".to_string()],
+            palette: Vec::new(),
+            show_in_game: true,
+        });
+        let TagChange::Rename { from, to } = &rename else {
+            unreachable!()
+        };
+        repo.rename_stored(from, to);
+        let mut names = repo.tags.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["cup", "cup/2026"]);
+        assert_eq!(repo.groups[0].tags, ["cup/2026"]);
+    }
 }
@@ -1,16 +1,12 @@
-use std::{
-    fs::{File, OpenOptions},
-    path::{Path, PathBuf},
-};
+use std::path::PathBuf;
 
-use color_eyre::Result;
 use dioxus::prelude::*;
 use dioxus_primitives::checkbox::CheckboxState;
 use rfd::AsyncFileDialog;
-use zip::write::FileOptions;
 
 use crate::{
-    components::checkbox::Checkbox, system::config::APP_CONFIG,
+    components::checkbox::Checkbox,
+    system::backup::{backup_to_folder, backup_to_zip},
     ui::dialog::DialogWrapper,
 };
 
@@ -126,102 +122,3 @@ pub fn BackupDialog(signal: Signal<bool>) -> Element {
         }
     }
 }
-
-fn backup_to_zip(out_path: &Path) -> Result<()> {
-    let fleets_root = APP_CONFIG
-        .get()
-        .unwrap()
-        .lock()
-        .unwrap()
-        .saves_dir
-        .join("Fleets");
-
-    let mut file =
-        OpenOptions::new().write(true).create(true).open(out_path)?;
-    let mut zip_writer = zip::ZipWriter::new_stream(&mut file);
-
-    let mut dirs_queue = Vec::new();
-
-    dirs_queue.push(fleets_root.clone());
-
-    while !dirs_queue.is_empty() {
-        let dir = dirs_queue.remove(0);
-        let read_dir = dir.read_dir()?;
-        for child in read_dir {
-            let Ok(entry) = child else { continue };
-            let path = entry.path();
-
-            let sub_path = path.strip_prefix(&fleets_root).unwrap();
-
-            if path.is_dir() {
-                zip_writer
-                    .add_directory_from_path(sub_path, FileOptions::DEFAULT)?;
-                dirs_queue.push(path);
-            } else if path.is_file()
-                && path
-                    .extension()
-                    .is_some_and(|ext| ext.to_str().unwrap() == "fleet")
-            {
-                zip_writer
-                    .start_file_from_path(sub_path, FileOptions::DEFAULT)?;
-                let mut src_file = File::open(&path)?;
-                std::io::copy(&mut src_file, &mut zip_writer)?;
-            }
-        }
-    }
-
-    zip_writer.finish()?;
-
-    Ok(())
-}
-
-fn backup_to_folder(out_path: &Path) -> Result<()> {
-    let fleets_root = APP_CONFIG
-        .get()
-        .unwrap()
-        .lock()
-        .unwrap()
-        .saves_dir
-        .join("Fleets");
-
-    let mut dirs_queue = Vec::new();
-
-    dirs_queue.push(fleets_root.clone());
-
-    while !dirs_queue.is_empty() {
-        let dir = dirs_queue.remove(0);
-        let read_dir = dir.read_dir()?;
-        for child in read_dir {
-            let Ok(entry) = child else { continue };
-            let path = entry.path();
-
-            let sub_path = path.strip_prefix(&fleets_root).unwrap();
-
-            let new_path = out_path.join(sub_path);
-            if path.is_dir() {
-                dirs_queue.push(path);
-                if !new_path.exists() {
-                    trace!("Creating directory: '{}'", new_path.display());
-                    std::fs::create_dir(new_path)?;
-                }
-            } else if path.is_file()
-                && path
-                    .extension()
-                    .is_some_and(|ext| ext.to_str().unwrap() == "fleet")
-            {
-                if new_path.exists() {
-                    trace!("Removing old file: '{}'", new_path.display());
-                    std::fs::remove_file(&new_path)?;
-                }
-                trace!(
-                    "Copying file: '{}' -> '{}'",
-                    path.display(),
-                    new_path.display()
-                );
-                std::fs::copy(&path, &new_path)?;
-            }
-        }
-    }
-
-    Ok(())
-}
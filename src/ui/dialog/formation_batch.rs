@@ -0,0 +1,203 @@
+use std::path::PathBuf;
+
+use dioxus::prelude::*;
+
+use crate::{
+    fleet_data::FleetData,
+    formation_templates::FORMATION_TEMPLATES,
+    util::{
+        formation_batch::{LeadMatch, apply_formation_batch},
+        spawn_async::spawn_async,
+    },
+};
+
+/// Apply a saved formation around a lead ship, found by name or hull, in each
+/// of `fleets`. `on_change` is called with the fleets that were rewritten.
+#[component]
+pub fn FormationBatchDialog(
+    signal: Signal<bool>,
+    fleets: Vec<FleetData>,
+    on_change: EventHandler<Vec<PathBuf>>,
+) -> Element {
+    let formations = use_signal(|| {
+        FORMATION_TEMPLATES
+            .get()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .formations
+            .clone()
+    });
+    let mut selected = use_signal(|| {
+        formations
+            .peek()
+            .first()
+            .map(|formation| formation.name.clone())
+    });
+    let mut match_by_name = use_signal(|| true);
+    let mut name_pattern = use_signal(String::new);
+    let mut hull_type = use_signal(String::new);
+    let mut running = use_signal(|| false);
+    let mut results = use_signal(Vec::<String>::new);
+    let mut failed = use_signal(Vec::<String>::new);
+
+    let mut hull_types = fleets
+        .iter()
+        .flat_map(|fleet| fleet.hull_types.iter().cloned())
+        .collect::<Vec<_>>();
+    hull_types.sort();
+    hull_types.dedup();
+    let paths = fleets
+        .iter()
+        .map(|fleet| fleet.path.clone())
+        .collect::<Vec<_>>();
+
+    let formation = selected().and_then(|name| {
+        formations
+            .read()
+            .iter()
+            .find(|formation| formation.name == name)
+            .cloned()
+    });
+    let lead = if match_by_name() {
+        let pattern = name_pattern.read().trim().to_string();
+        (!pattern.is_empty()).then_some(LeadMatch::Name(pattern))
+    } else {
+        let hull_type = hull_type();
+        (!hull_type.is_empty()).then_some(LeadMatch::Hull(hull_type))
+    };
+    let ready = formation.is_some() && lead.is_some();
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; width: 100%; gap: 10px;",
+            h2 { style: "margin: 0px auto 0px", "Apply Formation to Fleets" }
+            if formations.read().is_empty() {
+                p { class: "bg-text",
+                    "No formations saved yet. Save one from the formation library in the formation viewer."
+                }
+            } else {
+                select {
+                    disabled: running(),
+                    onchange: move |evt: Event<FormData>| selected.set(Some(evt.value())),
+                    for formation in formations() {
+                        option {
+                            value: "{formation.name}",
+                            selected: selected().as_ref() == Some(&formation.name),
+                            "{formation.name} ({formation.template.escorts.len()} escorts)"
+                        }
+                    }
+                }
+            }
+            p { style: "margin: 0px", "Lead ship:" }
+            label {
+                input {
+                    r#type: "radio",
+                    name: "formation-lead",
+                    disabled: running(),
+                    checked: match_by_name(),
+                    onchange: move |_| match_by_name.set(true),
+                }
+                "Named"
+                input {
+                    style: "margin-left: 5px;",
+                    disabled: running() || !match_by_name(),
+                    placeholder: "Name, with * and ? wildcards",
+                    value: "{name_pattern}",
+                    oninput: move |evt| name_pattern.set(evt.value()),
+                }
+            }
+            label {
+                input {
+                    r#type: "radio",
+                    name: "formation-lead",
+                    disabled: running(),
+                    checked: !match_by_name(),
+                    onchange: move |_| match_by_name.set(false),
+                }
+                "With hull"
+                select {
+                    style: "margin-left: 5px;",
+                    disabled: running() || match_by_name(),
+                    onchange: move |evt: Event<FormData>| hull_type.set(evt.value()),
+                    option { value: "", selected: hull_type.read().is_empty(), "Choose a hull" }
+                    for hull in hull_types {
+                        option {
+                            value: "{hull}",
+                            selected: *hull_type.read() == hull,
+                            "{hull}"
+                        }
+                    }
+                }
+            }
+            p { class: "bg-text", style: "margin: 0px",
+                "Ships following the lead and ships in no formation become its escorts."
+            }
+            button {
+                class: "button",
+                style: "height: 30px;",
+                disabled: running() || !ready,
+                onclick: move |_| {
+                    let (Some(formation), Some(lead)) = (formation.clone(), lead.clone()) else {
+                        return;
+                    };
+                    let paths = paths.clone();
+                    running.set(true);
+                    results.clear();
+                    failed.clear();
+                    spawn(async move {
+                        let (changed, errors) = spawn_async(move || {
+                                apply_formation_batch(&formation, &lead, &paths)
+                            })
+                            .await;
+                        running.set(false);
+                        results
+                            .set(
+                                changed
+                                    .iter()
+                                    .map(|(path, assignment)| {
+                                        let mut line = format!(
+                                            "{}: {} escorts placed around {}",
+                                            path.display(),
+                                            assignment.placed.len(),
+                                            assignment.lead,
+                                        );
+                                        if assignment.created {
+                                            line.push_str(" in a new formation");
+                                        }
+                                        if !assignment.unplaced.is_empty() {
+                                            line.push_str(
+                                                &format!(", no slot for {}", assignment.unplaced.join(", ")),
+                                            );
+                                        }
+                                        line
+                                    })
+                                    .collect(),
+                            );
+                        failed
+                            .set(
+                                errors
+                                    .iter()
+                                    .map(|(path, err)| format!("{}: {:#}", path.display(), err))
+                                    .collect(),
+                            );
+                        on_change.call(changed.into_iter().map(|(path, _)| path).collect());
+                    });
+                },
+                if running() {
+                    span { class: "spinner" }
+                } else {
+                    "Apply to {fleets.len()} fleets"
+                }
+            }
+            for result in results() {
+                p { class: "bg-text", style: "margin: 0px", "{result}" }
+            }
+            if !failed.is_empty() {
+                p { class: "search-error", "Some fleets could not be modified:" }
+                for failure in failed() {
+                    p { class: "bg-text", style: "margin: 0px", "{failure}" }
+                }
+            }
+        }
+    }
+}
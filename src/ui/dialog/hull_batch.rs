@@ -0,0 +1,247 @@
+use std::path::PathBuf;
+
+use dioxus::prelude::*;
+
+use crate::{
+    fleet_data::FleetData,
+    hull_templates::{HULL_TEMPLATES, HullTemplate},
+    util::{
+        hull_batch::{
+            HullBatch, HullPart, apply_hull_batch, preview_hull_batch,
+        },
+        spawn_async::spawn_async,
+    },
+};
+
+/// Changes a batch edit would make to one fleet, or why it can't be edited
+pub type FleetPreview = (PathBuf, Result<Vec<(String, String)>, String>);
+
+/// What `batch` would change in each fleet in `paths`, without writing
+/// anything.
+pub fn preview_fleets(
+    batch: &HullBatch,
+    paths: &[PathBuf],
+) -> Vec<FleetPreview> {
+    preview_hull_batch(batch, paths)
+        .into_iter()
+        .map(|(path, changes)| {
+            let changes = changes
+                .map(|changes| {
+                    changes
+                        .iter()
+                        .map(|change| (change.name.clone(), change.describe()))
+                        .collect()
+                })
+                .map_err(|err| format!("{err:#}"));
+            (path, changes)
+        })
+        .collect()
+}
+
+/// The number of ships a batch edit would change
+pub fn preview_change_count(previews: &[FleetPreview]) -> usize {
+    previews
+        .iter()
+        .filter_map(|(_, changes)| changes.as_ref().ok())
+        .map(Vec::len)
+        .sum()
+}
+
+/// The changes a batch edit would make, under the name of each of `fleets`.
+#[component]
+pub fn BatchPreview(
+    fleets: Vec<FleetData>,
+    previews: Vec<FleetPreview>,
+) -> Element {
+    rsx! {
+        div { style: "display: flex; flex-direction: column; max-height: 40vh; overflow-y: auto;",
+            for (path , changes) in previews {
+                h4 { style: "margin: 5px 0px 0px",
+                    {
+                        fleets
+                            .iter()
+                            .find(|fleet| fleet.path == path)
+                            .map_or_else(|| path.display().to_string(), |fleet| fleet.name.clone())
+                    }
+                }
+                match changes {
+                    Err(err) => rsx! {
+                        p { class: "search-error", style: "margin: 0px", "{err}" }
+                    },
+                    Ok(changes) if changes.is_empty() => rsx! {
+                        p { class: "bg-text", style: "margin: 0px", "No changes" }
+                    },
+                    Ok(changes) => rsx! {
+                        for (name , change) in changes {
+                            p { style: "margin: 0px", "{name}: {change}" }
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// Copy some parts of a hull config to every matching liner in `fleets`.
+/// `ship_hull` is the selected ship's hull, offered alongside the saved
+/// templates. The changes are previewed per fleet before anything is written,
+/// and each rewritten fleet is backed up first.
+#[component]
+pub fn HullBatchDialog(
+    signal: Signal<bool>,
+    fleets: Vec<FleetData>,
+    ship_hull: Option<HullTemplate>,
+    on_change: EventHandler<Vec<PathBuf>>,
+) -> Element {
+    let sources = use_signal(|| {
+        let templates = HULL_TEMPLATES
+            .get()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .templates
+            .clone();
+        ship_hull.into_iter().chain(templates).collect::<Vec<_>>()
+    });
+    let mut selected = use_signal(|| 0usize);
+    let mut parts = use_signal(|| HullPart::ALL.to_vec());
+    let mut preview = use_signal(|| None::<Vec<FleetPreview>>);
+    let mut running = use_signal(|| false);
+    let mut backup_dir = use_signal(|| None::<PathBuf>);
+    let mut failed = use_signal(Vec::<String>::new);
+
+    let batch = sources.read().get(selected()).map(|source| HullBatch {
+        hull_type: source.hull_type.clone(),
+        params: source.params.clone(),
+        parts: parts(),
+        ships: None,
+    });
+    let paths = fleets
+        .iter()
+        .map(|fleet| fleet.path.clone())
+        .collect::<Vec<_>>();
+    let change_count =
+        preview.read().as_deref().map_or(0, preview_change_count);
+    let preview_batch = batch.clone();
+    let preview_paths = paths.clone();
+    let apply_batch = batch.clone();
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; width: 100%; gap: 10px;",
+            h2 { style: "margin: 0px auto 0px", "Batch Edit Liner Hulls" }
+            if sources.read().is_empty() {
+                p { class: "bg-text",
+                    "Select a liner or save a hull template to copy from."
+                }
+            } else {
+                select {
+                    disabled: running(),
+                    onchange: move |evt: Event<FormData>| {
+                        selected.set(evt.value().parse().unwrap_or_default());
+                        preview.set(None);
+                    },
+                    for (idx , source) in sources().into_iter().enumerate() {
+                        option {
+                            value: "{idx}",
+                            selected: selected() == idx,
+                            "{source.name} ({source.hull_type})"
+                        }
+                    }
+                }
+            }
+            if let Some(batch) = batch.as_ref() {
+                p { class: "bg-text", style: "margin: 0px", "{batch.params.summary()}" }
+            }
+            div { style: "display: flex; flex-wrap: wrap; gap: 10px;",
+                for part in HullPart::ALL {
+                    label {
+                        input {
+                            r#type: "checkbox",
+                            disabled: running(),
+                            checked: parts.read().contains(&part),
+                            onchange: move |_| {
+                                let mut parts = parts.write();
+                                match parts.iter().position(|p| *p == part) {
+                                    Some(idx) => {
+                                        parts.remove(idx);
+                                    }
+                                    None => parts.push(part),
+                                }
+                                preview.set(None);
+                            },
+                        }
+                        "{part.name()}"
+                    }
+                }
+            }
+            button {
+                class: "button",
+                style: "height: 30px;",
+                disabled: running() || batch.is_none() || parts.is_empty(),
+                onclick: move |_| {
+                    let Some(batch) = preview_batch.clone() else {
+                        return;
+                    };
+                    let paths = preview_paths.clone();
+                    running.set(true);
+                    backup_dir.set(None);
+                    failed.clear();
+                    spawn(async move {
+                        let fleets = spawn_async(move || preview_fleets(&batch, &paths)).await;
+                        preview.set(Some(fleets));
+                        running.set(false);
+                    });
+                },
+                "Preview"
+            }
+            if let Some(previews) = preview() {
+                BatchPreview { fleets: fleets.clone(), previews }
+                button {
+                    class: "button",
+                    style: "height: 30px;",
+                    disabled: running() || change_count == 0,
+                    onclick: move |_| {
+                        let Some(batch) = apply_batch.clone() else {
+                            return;
+                        };
+                        let paths = paths.clone();
+                        running.set(true);
+                        spawn(async move {
+                            let (changed, errors, backup) = spawn_async(move || {
+                                    apply_hull_batch(&batch, &paths)
+                                })
+                                .await;
+                            running.set(false);
+                            preview.set(None);
+                            backup_dir.set(backup);
+                            on_change.call(changed);
+                            failed
+                                .set(
+                                    errors
+                                        .iter()
+                                        .map(|(path, err)| format!("{}: {:#}", path.display(), err))
+                                        .collect(),
+                                );
+                        });
+                    },
+                    if running() {
+                        span { class: "spinner" }
+                    } else {
+                        "Apply to {change_count} ships"
+                    }
+                }
+            }
+            if let Some(dir) = backup_dir() {
+                p { class: "bg-text", style: "margin: 0px",
+                    "The original fleets were backed up to {dir.display()}"
+                }
+            }
+            if !failed.is_empty() {
+                p { class: "search-error", "Some fleets could not be modified:" }
+                for failure in failed() {
+                    p { class: "bg-text", style: "margin: 0px", "{failure}" }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use dioxus::prelude::*;
+
+use crate::{
+    fleet_data::FleetData,
+    hull_templates::{HULL_TEMPLATES, apply_hull_template, fleet_liners},
+    ui::dialog::hull_batch::{
+        BatchPreview, FleetPreview, preview_change_count, preview_fleets,
+    },
+    util::spawn_async::spawn_async,
+};
+
+/// Apply a saved hull template to liners across `fleets`. Every liner the
+/// template fits is chosen until it's unticked. Like a batch edit, the
+/// changes are previewed before anything is written, and each rewritten fleet
+/// is backed up first. `on_change` is called with the fleets that were
+/// rewritten.
+#[component]
+pub fn HullTemplateDialog(
+    signal: Signal<bool>,
+    fleets: Vec<FleetData>,
+    on_change: EventHandler<Vec<PathBuf>>,
+) -> Element {
+    let templates = use_signal(|| {
+        HULL_TEMPLATES
+            .get()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .templates
+            .clone()
+    });
+    let mut selected = use_signal(|| {
+        templates
+            .peek()
+            .first()
+            .map(|template| template.name.clone())
+    });
+    // Ships the user unticked, by fleet path and ship key
+    let mut unticked = use_signal(HashSet::<(PathBuf, String)>::new);
+    let mut preview = use_signal(|| None::<Vec<FleetPreview>>);
+    let mut running = use_signal(|| false);
+    let mut backup_dir = use_signal(|| None::<PathBuf>);
+    let mut failed = use_signal(Vec::<String>::new);
+
+    let paths = fleets
+        .iter()
+        .map(|fleet| fleet.path.clone())
+        .collect::<Vec<_>>();
+    let liners = use_resource(move || {
+        let paths = paths.clone();
+        async move {
+            spawn_async(move || {
+                paths
+                    .into_iter()
+                    .map(|path| {
+                        let liners =
+                            fleet_liners(&path).map_err(|err| format!("{err}"));
+                        (path, liners)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await
+        }
+    });
+
+    let template = selected().and_then(|name| {
+        templates
+            .read()
+            .iter()
+            .find(|template| template.name == name)
+            .cloned()
+    });
+    // Ships to apply the template to in each fleet
+    let chosen = match (liners.read().as_ref(), template.as_ref()) {
+        (Some(liners), Some(template)) => liners
+            .iter()
+            .filter_map(|(path, liners)| {
+                let keys = liners
+                    .as_ref()
+                    .ok()?
+                    .iter()
+                    .filter(|liner| liner.hull_type == template.hull_type)
+                    .filter(|liner| {
+                        !unticked
+                            .read()
+                            .contains(&(path.clone(), liner.key.clone()))
+                    })
+                    .map(|liner| liner.key.clone())
+                    .collect::<Vec<_>>();
+                Some((path.clone(), keys))
+            })
+            .collect::<Vec<_>>(),
+        _ => Vec::new(),
+    };
+    let chosen_count = chosen.iter().map(|(_, keys)| keys.len()).sum::<usize>();
+    let change_count =
+        preview.read().as_deref().map_or(0, preview_change_count);
+    let preview_template = template.clone();
+    let preview_chosen = chosen.clone();
+    let chosen_template = template.clone();
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; width: 100%; gap: 10px;",
+            h2 { style: "margin: 0px auto 0px", "Apply Hull Template" }
+            if templates.read().is_empty() {
+                p { class: "bg-text",
+                    "No hull templates saved yet. Save one from the liner editor."
+                }
+            } else {
+                select {
+                    disabled: running(),
+                    onchange: move |evt: Event<FormData>| {
+                        selected.set(Some(evt.value()));
+                        unticked.clear();
+                        preview.set(None);
+                    },
+                    for template in templates() {
+                        option {
+                            value: "{template.name}",
+                            selected: selected().as_ref() == Some(&template.name),
+                            "{template.name} ({template.hull_type})"
+                        }
+                    }
+                }
+            }
+            if let Some(template) = template.as_ref() {
+                p { class: "bg-text", style: "margin: 0px", "{template.summary()}" }
+                if !template.description.is_empty() {
+                    p { class: "bg-text", style: "margin: 0px", "{template.description}" }
+                }
+            }
+            div { style: "display: flex; flex-direction: column; max-height: 40vh; overflow-y: auto;",
+                match (liners.read().as_ref(), template.as_ref()) {
+                    (None, _) => rsx! {
+                        span { class: "spinner" }
+                    },
+                    (Some(_), None) => rsx! {},
+                    (Some(liners), Some(template)) => rsx! {
+                        for (fleet , (path , ships)) in fleets.iter().zip(liners.iter()) {
+                            h4 { style: "margin: 5px 0px 0px", "{fleet.name}" }
+                            match ships {
+                                Err(err) => rsx! {
+                                    p { class: "search-error", style: "margin: 0px", "{err}" }
+                                },
+                                Ok(ships) => rsx! {
+                                    for liner in ships.iter().filter(|liner| liner.hull_type == template.hull_type) {
+                                        label {
+                                            input {
+                                                r#type: "checkbox",
+                                                disabled: running(),
+                                                checked: !unticked.read().contains(&(path.clone(), liner.key.clone())),
+                                                onchange: {
+                                                    let ship = (path.clone(), liner.key.clone());
+                                                    move |_| {
+                                                        let mut unticked = unticked.write();
+                                                        if !unticked.remove(&ship) {
+                                                            unticked.insert(ship.clone());
+                                                        }
+                                                        preview.set(None);
+                                                    }
+                                                },
+                                            }
+                                            "{liner.name}"
+                                        }
+                                    }
+                                    if !ships.iter().any(|liner| liner.hull_type == template.hull_type) {
+                                        p { class: "bg-text", style: "margin: 0px", "No matching liners" }
+                                    }
+                                },
+                            }
+                        }
+                    },
+                }
+            }
+            button {
+                class: "button",
+                style: "height: 30px;",
+                disabled: running() || chosen_count == 0,
+                onclick: move |_| {
+                    let Some(template) = preview_template.clone() else {
+                        return;
+                    };
+                    let chosen = preview_chosen.clone();
+                    running.set(true);
+                    backup_dir.set(None);
+                    failed.clear();
+                    spawn(async move {
+                        let fleets = spawn_async(move || {
+                                let paths = chosen
+                                    .iter()
+                                    .filter(|(_, keys)| !keys.is_empty())
+                                    .map(|(path, _)| path.clone())
+                                    .collect::<Vec<_>>();
+                                preview_fleets(&template.batch(&chosen), &paths)
+                            })
+                            .await;
+                        preview.set(Some(fleets));
+                        running.set(false);
+                    });
+                },
+                "Preview {chosen_count} ships"
+            }
+            if let Some(previews) = preview() {
+                BatchPreview { fleets: fleets.clone(), previews }
+                button {
+                    class: "button",
+                    style: "height: 30px;",
+                    disabled: running() || change_count == 0,
+                    onclick: move |_| {
+                        let Some(template) = chosen_template.clone() else {
+                            return;
+                        };
+                        let chosen = chosen.clone();
+                        running.set(true);
+                        spawn(async move {
+                            let (changed, errors, backup) = spawn_async(move || {
+                                    apply_hull_template(&template, &chosen)
+                                })
+                                .await;
+                            running.set(false);
+                            preview.set(None);
+                            backup_dir.set(backup);
+                            on_change.call(changed);
+                            failed
+                                .set(
+                                    errors
+                                        .iter()
+                                        .map(|(path, err)| format!("{}: {:#}", path.display(), err))
+                                        .collect(),
+                                );
+                        });
+                    },
+                    if running() {
+                        span { class: "spinner" }
+                    } else {
+                        "Apply to {change_count} ships"
+                    }
+                }
+            }
+            if let Some(dir) = backup_dir() {
+                p { class: "bg-text", style: "margin: 0px",
+                    "The original fleets were backed up to {dir.display()}"
+                }
+            }
+            if !failed.is_empty() {
+                p { class: "search-error", "Some fleets could not be modified:" }
+                for failure in failed() {
+                    p { class: "bg-text", style: "margin: 0px", "{failure}" }
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use dioxus::prelude::*;
 use rfd::AsyncFileDialog;
-use schemas::Fleet;
 
-use crate::{fleet_data::FleetData, system::fleet_io::read_fleet};
+use crate::{
+    fleet_data::FleetData, system::fleet_io::read_fleet,
+    util::merge::merge_fleets,
+};
 
 #[component]
 pub fn MergeFleetsDialog(
@@ -55,118 +57,25 @@ pub fn MergeFleetsDialog(
                             return;
                         };
                         info!("Merging {} fleets into {}", fleet_datas.len(), file.path().display());
-                        let mut out = Fleet {
-                            xmlns_xsd: "".to_string(),
-                            xmlns_xsi: "".to_string(),
-                            text: None,
-                            name: out_name(),
-                            description: Some(
-                                format!(
-                                    "Merged fleets:\n{}",
-                                    fleet_datas
-                                        .iter()
-                                        .map(|f| format!(" - {}", f.name))
-                                        .collect::<Vec<_>>()
-                                        .join("\n"),
-                                ),
-                            ),
-                            version: "".to_string(),
-                            total_points: 0,
-                            faction_key: "".to_string(),
-                            sort_override_order: None,
-                            ships: Some(schemas::Ships {
-                                text: None,
-                                ship: Some(Vec::new()),
-                            }),
-                            missile_types: Some(schemas::MissileTypes {
-                                text: None,
-                                missile_template: Some(Vec::new()),
-                            }),
-                            craft_types: Some(schemas::CraftTypes {
-                                text: None,
-                                craft_template: Some(Vec::new()),
-                            }),
-                            mod_dependencies: Some(schemas::ModDependencies {
-                                unsigned_long: Some(Vec::new()),
-                            }),
-                        };
-                        for fleet_data in fleet_datas {
-                            let fleet = read_fleet(&fleet_data.path).unwrap();
-                            debug!("Pulling data from '{}'", fleet_data.name);
-                            if out.xmlns_xsd.is_empty() {
-                                out.xmlns_xsd = fleet.xmlns_xsd;
-                            }
-                            if out.xmlns_xsi.is_empty() {
-                                out.xmlns_xsi = fleet.xmlns_xsi;
-                            }
-                            if out.text.is_none() && fleet.text.is_some() {
-                                out.text = fleet.text;
+                        let mut fleets = Vec::new();
+                        for fleet_data in &fleet_datas {
+                            match read_fleet(&fleet_data.path) {
+                                Ok(fleet) => fleets.push(fleet),
+                                Err(err) => {
+                                    error!("Failed to read fleet '{}': {:?}", fleet_data.name, err);
+                                    running.set(false);
+                                    return;
+                                }
                             }
-                            if out.version.is_empty() {
-                                out.version = fleet.version;
-                            } else if out.version != fleet.version {
-                                warn!(
-                                    "Merging fleets made in different fleet editor versions. This may cause issues"
-                                );
-                            }
-                            out.total_points += fleet.total_points;
-                            if out.faction_key.is_empty() {
-                                out.faction_key = fleet.faction_key;
-                            } else if out.faction_key != fleet.faction_key {
-                                error!("Merging fleets of different factions");
+                        }
+                        let out = match merge_fleets(out_name(), fleets) {
+                            Ok(out) => out,
+                            Err(err) => {
+                                error!("Failed to merge fleets: {:?}", err);
+                                running.set(false);
                                 return;
                             }
-                            for ship in fleet
-                                .ships
-                                .map(|ships| ships.ship)
-                                .flatten()
-                                .unwrap_or_default()
-                            {
-                                out.ships.as_mut().unwrap().ship.as_mut().unwrap().push(ship);
-                            }
-                            for missile in fleet
-                                .missile_types
-                                .map(|missile_types| missile_types.missile_template)
-                                .flatten()
-                                .unwrap_or_default()
-                            {
-                                out.missile_types
-                                    .as_mut()
-                                    .unwrap()
-                                    .missile_template
-                                    .as_mut()
-                                    .unwrap()
-                                    .push(missile);
-                            }
-                            for craft in fleet
-                                .craft_types
-                                .map(|craft_types| craft_types.craft_template)
-                                .flatten()
-                                .unwrap_or_default()
-                            {
-                                out.craft_types
-                                    .as_mut()
-                                    .unwrap()
-                                    .craft_template
-                                    .as_mut()
-                                    .unwrap()
-                                    .push(craft);
-                            }
-                            for mod_dep in fleet
-                                .mod_dependencies
-                                .map(|mod_dep| mod_dep.unsigned_long)
-                                .flatten()
-                                .unwrap_or_default()
-                            {
-                                out.mod_dependencies
-                                    .as_mut()
-                                    .unwrap()
-                                    .unsigned_long
-                                    .as_mut()
-                                    .unwrap()
-                                    .push(mod_dep);
-                            }
-                        }
+                        };
                         crate::system::fleet_io::write_fleet(file.path(), &out)
                             .expect("Failed to write fleet file");
                         debug!("Merge complete successfully");
@@ -2,10 +2,15 @@ use dioxus::prelude::*;
 
 pub mod backup;
 pub mod error;
+pub mod formation_batch;
+pub mod hull_batch;
+pub mod hull_template;
 pub mod merge_fleets;
+pub mod save_search;
 pub mod settings;
 pub mod spinner;
 pub mod swarm_config;
+pub mod tag_manager;
 
 #[component]
 pub fn DialogWrapper(
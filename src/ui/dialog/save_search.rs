@@ -0,0 +1,74 @@
+use dioxus::prelude::*;
+
+use crate::saved_searches::{SAVED_SEARCHES, SavedSearch};
+
+/// Save `query` as a new folder, or rename the saved search called `renaming`.
+#[component]
+pub fn SaveSearchDialog(
+    signal: Signal<bool>,
+    query: String,
+    saved_searches: Signal<Vec<SavedSearch>>,
+    renaming: Option<String>,
+) -> Element {
+    let mut name = use_signal(|| renaming.clone().unwrap_or_default());
+    let mut error = use_signal(|| None::<String>);
+    let replaces = use_memo(move || {
+        saved_searches
+            .iter()
+            .any(|search| search.name == name().trim())
+    });
+    let title = if renaming.is_some() {
+        "Rename Saved Search"
+    } else {
+        "Save Search"
+    };
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; width: 100%; gap: 10px;",
+            h2 { style: "margin: 0px auto 0px", "{title}" }
+            p { class: "bg-text", style: "margin: 0px auto 0px", "{query}" }
+            input {
+                value: "{name}",
+                placeholder: "Folder name",
+                oninput: move |evt| name.set(evt.value()),
+            }
+            if replaces() && renaming.is_none() {
+                p { class: "bg-text", style: "margin: 0px",
+                    "This will replace the saved search with the same name"
+                }
+            }
+            if let Some(error) = error() {
+                p { class: "search-error", style: "margin: 0px", "{error}" }
+            }
+            button {
+                disabled: name().trim().is_empty(),
+                style: "margin: 0px auto 0px; height: 30px; width: 60%",
+                class: "button",
+                onclick: {
+                    let query = query.clone();
+                    let renaming = renaming.clone();
+                    move |_| {
+                        let name = name().trim().to_string();
+                        let mut repo = SAVED_SEARCHES.get().unwrap().lock().unwrap();
+                        match &renaming {
+                            Some(old_name) => {
+                                if let Err(err) = repo.rename(old_name, name) {
+                                    error.set(Some(err.to_string()));
+                                    return;
+                                }
+                            }
+                            None => repo.add(name, query.clone()),
+                        }
+                        saved_searches.set(repo.searches.clone());
+                        signal.set(false);
+                    }
+                },
+                if renaming.is_some() {
+                    "Rename"
+                } else {
+                    "Save"
+                }
+            }
+        }
+    }
+}
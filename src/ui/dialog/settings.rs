@@ -4,14 +4,24 @@ use dioxus::prelude::*;
 use dioxus_primitives::checkbox::CheckboxState;
 use rfd::AsyncFileDialog;
 
-use crate::{components::checkbox::Checkbox, system::config::APP_CONFIG};
+use crate::{
+    components::checkbox::Checkbox,
+    system::config::{APP_CONFIG, AppConfig, TagStorage},
+    util::spawn_async::spawn_async,
+};
 
+/// `on_tags_moved` is called with the fleets rewritten when tags are moved
+/// into or out of their descriptions.
 #[component]
-pub fn SettingsDialog(signal: Signal<bool>) -> Element {
+pub fn SettingsDialog(
+    signal: Signal<bool>,
+    on_tags_moved: EventHandler<Vec<PathBuf>>,
+) -> Element {
     let mut config =
         use_signal(|| APP_CONFIG.get().unwrap().lock().unwrap().clone());
 
     let mut saving = use_signal(|| false);
+    let mut failed = use_signal(Vec::<String>::new);
 
     rsx! {
         div { style: "width: 70vw; height: 80vh; display: flex; flex-direction: column; justify-content: space-between",
@@ -69,6 +79,34 @@ pub fn SettingsDialog(signal: Signal<bool>) -> Element {
                             },
                         }
                     }
+                    p { "Load fleets from newer game versions" }
+                    div { style: "display: flex; flex-direction: row; justify-content: center;",
+                        Checkbox {
+                            checked: if config.read().tolerant_parsing { CheckboxState::Checked } else { CheckboxState::Unchecked },
+                            on_checked_change: move |checked| {
+                                match checked {
+                                    CheckboxState::Checked => config.write().tolerant_parsing = true,
+                                    CheckboxState::Indeterminate => {}
+                                    CheckboxState::Unchecked => config.write().tolerant_parsing = false,
+                                }
+                            },
+                        }
+                    }
+                    p { title: "Keep tags in NebTools instead of each fleet's description, so they aren't visible in game",
+                        "Store tags outside fleet files"
+                    }
+                    div { style: "display: flex; flex-direction: row; justify-content: center;",
+                        Checkbox {
+                            checked: if config.read().tag_storage == TagStorage::Database { CheckboxState::Checked } else { CheckboxState::Unchecked },
+                            on_checked_change: move |checked| {
+                                match checked {
+                                    CheckboxState::Checked => config.write().tag_storage = TagStorage::Database,
+                                    CheckboxState::Indeterminate => {}
+                                    CheckboxState::Unchecked => config.write().tag_storage = TagStorage::Description,
+                                }
+                            },
+                        }
+                    }
                 }
                 div { style: "margin-top: 10px; display: flex; flex-direction: column; width: 100%;",
                     div { style: "display: flex; flex-direction: row; width: 100%; justify-content: space-between;",
@@ -103,6 +141,16 @@ pub fn SettingsDialog(signal: Signal<bool>) -> Element {
                     }
                 }
             }
+            if !failed.is_empty() {
+                div { style: "max-height: 20vh; overflow-y: auto;",
+                    p { class: "search-error",
+                        "Some fleets' tags could not be moved:"
+                    }
+                    for failure in failed() {
+                        p { class: "bg-text", style: "margin: 0px", "{failure}" }
+                    }
+                }
+            }
             div { style: "display: flex; flex-direction: row; justify-content: center; gap: 30px;",
                 button {
                     class: "button",
@@ -117,8 +165,39 @@ pub fn SettingsDialog(signal: Signal<bool>) -> Element {
                     onclick: move |_| {
                         spawn(async move {
                             saving.set(true);
-                            *APP_CONFIG.get().unwrap().lock().unwrap() = config();
+                            let config = config();
+                            // Changed by moving the tags, once they're stored there
+                            let tag_storage = {
+                                let mut app_config = APP_CONFIG.get().unwrap().lock().unwrap();
+                                let tag_storage = app_config.tag_storage;
+                                *app_config = AppConfig {
+                                    tag_storage,
+                                    ..config.clone()
+                                };
+                                tag_storage
+                            };
                             crate::system::config::save_app_config().unwrap();
+                            if config.tag_storage != tag_storage {
+                                let result = spawn_async(move || {
+                                        crate::tag_db::move_tags(config.tag_storage)
+                                    })
+                                    .await;
+                                let errors = match result {
+                                    Ok((changed, errors)) => {
+                                        on_tags_moved.call(changed);
+                                        errors
+                                            .iter()
+                                            .map(|(path, err)| format!("{}: {}", path.display(), err))
+                                            .collect::<Vec<_>>()
+                                    }
+                                    Err(err) => vec![format!("{:?}", err)],
+                                };
+                                if !errors.is_empty() {
+                                    failed.set(errors);
+                                    saving.set(false);
+                                    return;
+                                }
+                            }
                             saving.set(false);
                             signal.set(false);
                         });
@@ -1,13 +1,25 @@
 use dioxus::prelude::*;
 
 #[component]
-pub fn SpinnerDialog(title: Option<String>) -> Element {
+pub fn SpinnerDialog(
+    title: Option<String>,
+    /// Number of steps done and the total, for long running tasks
+    progress: Option<(usize, usize)>,
+) -> Element {
     rsx! {
         div { style: "display: flex; flex-direction: column",
             if let Some(title) = title {
                 h2 { margin: "0 auto 0", "{title}" }
             }
             span { style: "margin: 10px auto 0;", class: "spinner" }
+            if let Some((done, total)) = progress {
+                progress {
+                    style: "margin: 10px auto 0;",
+                    value: "{done}",
+                    max: "{total}",
+                }
+                p { class: "bg-text", margin: "5px auto 0", "{done} / {total}" }
+            }
         }
     }
 }
@@ -0,0 +1,441 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use dioxus::prelude::*;
+use dioxus_primitives::checkbox::CheckboxState;
+use palette::{Hsv, IntoColor, encoding::Srgb, rgb::Rgb};
+
+use crate::{
+    components::{checkbox::Checkbox, color_picker::ColorPicker},
+    fleet_data::FleetData,
+    tags::{
+        Color, TAGS_REPO, Tag, TagChange, TagGroup, apply_tag_change,
+        apply_tag_changes, fleet_tags, fleets_affected_by, refresh_fleet_tags,
+        tag_is_under,
+    },
+    util::spawn_async::spawn_async,
+};
+
+/// Fleets that were rewritten, and those that failed with the reason.
+type TagJobResult = (Vec<PathBuf>, Vec<(PathBuf, color_eyre::Report)>);
+
+fn to_hsv(color: &Color) -> Hsv<Srgb, f64> {
+    let color: Rgb<Srgb, f64> = color.into_format();
+    color.into_color()
+}
+
+fn from_hsv(color: Hsv<Srgb, f64>) -> Color {
+    let color: Rgb<Srgb, f64> = color.into_color();
+    Color(color.into_format())
+}
+
+/// Rename, merge, recolor and delete tags across every fleet, and organise
+/// them into groups. `on_change` is called with the fleets that were
+/// rewritten.
+#[component]
+pub fn TagManagerDialog(
+    signal: Signal<bool>,
+    fleets: Vec<FleetData>,
+    on_change: EventHandler<Vec<PathBuf>>,
+) -> Element {
+    let mut selected = use_signal(|| None::<String>);
+    let mut new_name = use_signal(String::new);
+    let mut new_color = use_signal(|| Hsv::<Srgb, f64>::new(1.0, 1.0, 1.0));
+    let mut color_picker_open = use_signal(|| false);
+    let mut pending = use_signal(|| None::<TagChange>);
+    let mut running = use_signal(|| false);
+    let mut failed = use_signal(Vec::<String>::new);
+    let mut groups =
+        use_signal(|| TAGS_REPO.get().unwrap().lock().unwrap().groups.clone());
+    let mut new_group = use_signal(String::new);
+
+    let mut save_group = move |group: TagGroup| {
+        let mut tags_repo = TAGS_REPO.get().unwrap().lock().unwrap();
+        tags_repo.set_group(group);
+        groups.set(tags_repo.groups.clone());
+    };
+    // Run a change to many fleets in the background, reporting the fleets it
+    // rewrote and any failures
+    let mut run = move |job: Box<dyn FnOnce() -> TagJobResult + Send>| {
+        running.set(true);
+        failed.clear();
+        spawn(async move {
+            let (rewritten, errors) = spawn_async(job).await;
+            running.set(false);
+            on_change.call(rewritten);
+            failed.set(
+                errors
+                    .iter()
+                    .map(|(path, err)| format!("{}: {}", path.display(), err))
+                    .collect(),
+            );
+        });
+    };
+
+    // Every known tag, with its colour and how many fleets use it
+    let mut tags = BTreeMap::<String, (Color, usize)>::new();
+    for (name, color) in &TAGS_REPO.get().unwrap().lock().unwrap().tags {
+        tags.insert(name.clone(), (color.clone(), 0));
+    }
+    for fleet in &fleets {
+        for tag in fleet_tags(fleet) {
+            tags.entry(tag.name).or_insert((tag.color, 0)).1 += 1;
+        }
+    }
+
+    // Fleets with any of `names` or their descendants, whose tags may have to
+    // move in or out of their descriptions when a group is shown or hidden
+    let fleets_under = |names: &[String]| {
+        fleets
+            .iter()
+            .filter(|fleet| {
+                fleet_tags(fleet).iter().any(|tag| {
+                    names.iter().any(|name| tag_is_under(&tag.name, name))
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let known_tags = tags
+        .iter()
+        .map(|(name, (color, _))| Tag {
+            name: name.clone(),
+            color: color.clone(),
+        })
+        .collect::<Vec<_>>();
+    let selected_group = selected().and_then(|name| {
+        let tags_repo = TAGS_REPO.get().unwrap().lock().unwrap();
+        tags_repo.group_of(&name).map(|group| group.name.clone())
+    });
+
+    let affected = pending
+        .read()
+        .as_ref()
+        .map(|change| fleets_affected_by(&fleets, change))
+        .unwrap_or_default();
+    let affected_paths = affected
+        .iter()
+        .map(|fleet| fleet.path.clone())
+        .collect::<Vec<_>>();
+
+    let rename_target = new_name().trim().to_string();
+    let merge_color = tags.get(&rename_target).map(|(color, _)| color.clone());
+    let merge_hint = match merge_color {
+        Some(_) => format!("Fleets with both tags keep only '{rename_target}'"),
+        None => String::new(),
+    };
+    let summary = pending.read().as_ref().map(|change| match change {
+        TagChange::Rename { from, to } => {
+            format!("Rename '{from}' to '{}'", to.name)
+        }
+        TagChange::Delete(name) => format!("Delete '{name}'"),
+        TagChange::Recolor(name, _) => format!("Recolor '{name}'"),
+    });
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; width: 100%; gap: 10px;",
+            h2 { style: "margin: 0px auto 0px", "Manage Tags" }
+            if tags.is_empty() {
+                p { class: "bg-text", "No fleets have tags yet" }
+            }
+            div { style: "display: grid; max-height: 40vh; overflow-y: auto;",
+                for (name , (color , count)) in tags.clone() {
+                    button {
+                        key: "{name}",
+                        class: if selected().as_ref() == Some(&name) { "list-button selected" } else { "list-button" },
+                        display: "flex",
+                        flex_direction: "row",
+                        justify_content: "space-between",
+                        onclick: {
+                            let name = name.clone();
+                            let color = color.clone();
+                            move |_| {
+                                new_name.set(name.clone());
+                                new_color.set(to_hsv(&color));
+                                selected.set(Some(name.clone()));
+                                pending.set(None);
+                                failed.clear();
+                            }
+                        },
+                        span { style: format!("color: rgb({}, {}, {})", color.red, color.green, color.blue),
+                            "{name}"
+                        }
+                        span { class: "bg-text", "{count} fleets" }
+                    }
+                }
+            }
+            if let Some(name) = selected() {
+                div { style: "display: flex; flex-direction: row; gap: 5px;",
+                    input {
+                        style: "flex: 1;",
+                        value: "{new_name}",
+                        oninput: move |evt| new_name.set(evt.value()),
+                    }
+                    button {
+                        class: "button",
+                        disabled: rename_target.is_empty() || rename_target == name,
+                        title: "{merge_hint}",
+                        onclick: {
+                            let name = name.clone();
+                            let rename_target = rename_target.clone();
+                            let color = merge_color.clone();
+                            move |_| {
+                                let color = color.clone().unwrap_or_else(|| from_hsv(new_color()));
+                                pending
+                                    .set(
+                                        Some(TagChange::Rename {
+                                            from: name.clone(),
+                                            to: Tag {
+                                                name: rename_target.clone(),
+                                                color,
+                                            },
+                                        }),
+                                    );
+                            }
+                        },
+                        if merge_color.is_some() {
+                            "Merge"
+                        } else {
+                            "Rename"
+                        }
+                    }
+                }
+                div { style: "display: flex; flex-direction: row; gap: 5px;",
+                    ColorPicker {
+                        open: color_picker_open(),
+                        flex_grow: 0,
+                        color: new_color,
+                        on_open_change: move |now_open| color_picker_open.set(now_open),
+                        on_color_change: move |c: Hsv<Srgb, f64>| new_color.set(c),
+                    }
+                    button {
+                        class: "button",
+                        style: "flex: 1;",
+                        onclick: {
+                            let name = name.clone();
+                            move |_| {
+                                pending.set(Some(TagChange::Recolor(name.clone(), from_hsv(new_color()))));
+                            }
+                        },
+                        "Recolor"
+                    }
+                    button {
+                        class: "button",
+                        style: "flex: 1;",
+                        onclick: {
+                            let name = name.clone();
+                            move |_| pending.set(Some(TagChange::Delete(name.clone())))
+                        },
+                        "Delete"
+                    }
+                }
+                div { style: "display: flex; flex-direction: row; align-items: center; gap: 5px;",
+                    span { "Group" }
+                    select {
+                        style: "flex: 1;",
+                        disabled: running(),
+                        onchange: {
+                            let name = name.clone();
+                            let moving = fleets_under(&[name.clone()]);
+                            move |evt: Event<FormData>| {
+                                let group = Some(evt.value()).filter(|group| !group.is_empty());
+                                let mut tags_repo = TAGS_REPO.get().unwrap().lock().unwrap();
+                                tags_repo.move_to_group(&name, group.as_deref());
+                                groups.set(tags_repo.groups.clone());
+                                drop(tags_repo);
+                                let paths = moving.iter().map(|fleet| fleet.path.clone()).collect::<Vec<_>>();
+                                run(Box::new(move || refresh_fleet_tags(&paths)));
+                            }
+                        },
+                        option { value: "", selected: selected_group.is_none(), "None" }
+                        for group in groups() {
+                            option {
+                                value: "{group.name}",
+                                selected: selected_group.as_ref() == Some(&group.name),
+                                "{group.name}"
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some(summary) = summary {
+                p { style: "margin: 0px",
+                    "{summary}: {affected.len()} fleets will be modified"
+                }
+                div { style: "display: grid; max-height: 20vh; overflow-y: auto;",
+                    for fleet in &affected {
+                        p { class: "bg-text", style: "margin: 0px",
+                            " - {fleet.name} ({fleet.short_path.display()})"
+                        }
+                    }
+                }
+                div { style: "display: flex; flex-direction: row; justify-content: center; gap: 10px;",
+                    button {
+                        style: "width: 30%; height: 30px;",
+                        class: "button",
+                        disabled: running(),
+                        onclick: move |_| pending.set(None),
+                        "Cancel"
+                    }
+                    button {
+                        style: "width: 30%; height: 30px;",
+                        class: "button",
+                        disabled: running(),
+                        onclick: move |_| {
+                            let Some(change) = pending() else {
+                                return;
+                            };
+                            let paths = affected_paths.clone();
+                            running.set(true);
+                            spawn(async move {
+                                let rewritten = paths.clone();
+                                let errors = spawn_async(move || apply_tag_change(&paths, &change))
+                                    .await;
+                                running.set(false);
+                                pending.set(None);
+                                selected.set(None);
+                                on_change.call(rewritten);
+                                if errors.is_empty() {
+                                    signal.set(false);
+                                } else {
+                                    failed
+                                        .set(
+                                            errors
+                                                .iter()
+                                                .map(|(path, err)| format!("{}: {}", path.display(), err))
+                                                .collect(),
+                                        );
+                                }
+                            });
+                        },
+                        if running() {
+                            span { class: "spinner" }
+                        } else {
+                            "Apply"
+                        }
+                    }
+                }
+            }
+            h3 { style: "margin: 0px", "Groups" }
+            for group in groups() {
+                div {
+                    key: "{group.name}",
+                    style: "display: flex; flex-direction: row; align-items: center; gap: 5px;",
+                    span { style: "flex: 1;", "{group.name}" }
+                    for (idx , color) in group.palette.iter().enumerate() {
+                        span {
+                            class: "palette-swatch",
+                            title: "Remove from palette",
+                            style: format!("background: rgb({}, {}, {})", color.red, color.green, color.blue),
+                            onclick: {
+                                let mut group = group.clone();
+                                move |_| {
+                                    group.palette.remove(idx);
+                                    save_group(group.clone());
+                                }
+                            },
+                        }
+                    }
+                    button {
+                        class: "button",
+                        title: "Add the colour picked above to the palette",
+                        onclick: {
+                            let mut group = group.clone();
+                            move |_| {
+                                group.palette.push(from_hsv(new_color()));
+                                save_group(group.clone());
+                            }
+                        },
+                        "+"
+                    }
+                    Checkbox {
+                        checked: if group.show_in_game { CheckboxState::Checked } else { CheckboxState::Unchecked },
+                        disabled: running(),
+                        on_checked_change: {
+                            let mut group = group.clone();
+                            let moving = fleets_under(&group.tags);
+                            move |checked| {
+                                group.show_in_game = matches!(checked, CheckboxState::Checked);
+                                save_group(group.clone());
+                                let paths = moving.iter().map(|fleet| fleet.path.clone()).collect::<Vec<_>>();
+                                run(Box::new(move || refresh_fleet_tags(&paths)));
+                            }
+                        },
+                    }
+                    span { title: "Whether the group's tags are written into fleet descriptions",
+                        "In game"
+                    }
+                    button {
+                        class: "button",
+                        title: "Recolour the group's tags with its palette",
+                        disabled: running() || group.palette.is_empty(),
+                        onclick: {
+                            let changes = TAGS_REPO
+                                .get()
+                                .unwrap()
+                                .lock()
+                                .unwrap()
+                                .palette_changes(&group.name, &known_tags);
+                            let fleets = fleets.clone();
+                            move |_| {
+                                let changes = changes.clone();
+                                let fleets = fleets.clone();
+                                run(Box::new(move || apply_tag_changes(&fleets, &changes)));
+                            }
+                        },
+                        "Apply Palette"
+                    }
+                    button {
+                        class: "button",
+                        disabled: running(),
+                        onclick: {
+                            let group = group.clone();
+                            let moving = fleets_under(&group.tags);
+                            move |_| {
+                                let mut tags_repo = TAGS_REPO.get().unwrap().lock().unwrap();
+                                tags_repo.remove_group(&group.name);
+                                groups.set(tags_repo.groups.clone());
+                                drop(tags_repo);
+                                // Hidden tags are shown again
+                                let paths = moving.iter().map(|fleet| fleet.path.clone()).collect::<Vec<_>>();
+                                run(Box::new(move || refresh_fleet_tags(&paths)));
+                            }
+                        },
+                        "Delete"
+                    }
+                }
+            }
+            div { style: "display: flex; flex-direction: row; gap: 5px;",
+                input {
+                    style: "flex: 1;",
+                    value: "{new_group}",
+                    placeholder: "Group name",
+                    oninput: move |evt| new_group.set(evt.value()),
+                }
+                button {
+                    class: "button",
+                    disabled: new_group().trim().is_empty()
+                        || groups.iter().any(|group| group.name == new_group().trim()),
+                    onclick: move |_| {
+                        save_group(TagGroup {
+                            name: new_group().trim().to_string(),
+                            tags: Vec::new(),
+                            palette: Vec::new(),
+                            show_in_game: true,
+                        });
+                        new_group.clear();
+                    },
+                    "Add Group"
+                }
+            }
+            if running() && pending.read().is_none() {
+                span { class: "spinner", style: "margin: 0px auto 0px" }
+            }
+            if !failed.is_empty() {
+                p { class: "search-error", "Some fleets could not be modified:" }
+                for failure in failed() {
+                    p { class: "bg-text", style: "margin: 0px", "{failure}" }
+                }
+            }
+        }
+    }
+}
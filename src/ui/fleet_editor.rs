@@ -1,26 +1,40 @@
 use arboard::Clipboard;
 use dioxus::prelude::*;
+use palette::{Hsv, IntoColor, encoding::Srgb, rgb::Rgb};
 use schemas::Ship;
 
 use crate::{
-    components::dropdown_menu::{
-        DropdownMenu, DropdownMenuContent, DropdownMenuItem,
-        DropdownMenuTrigger,
+    components::{
+        color_picker::ColorPicker,
+        dropdown_menu::{
+            DropdownMenu, DropdownMenuContent, DropdownMenuItem,
+            DropdownMenuTrigger,
+        },
     },
-    fleet_edit::{self, EditableHullParams, get_ln_editable_hull_params},
+    dressings::{DRESSING_SLOTS, dressing_options},
+    fleet_edit::{
+        self, EditableHullParams, HullPaint, LinerHull,
+        get_ln_editable_hull_params, variant_label,
+    },
+    hull_templates::{HULL_TEMPLATES, HullTemplate},
 };
 
+/// Edit the hull of a liner. `on_apply_paint` is called to paint every liner
+/// in the fleet like this one.
 #[component]
-pub fn ShipEditor(mut ship: Signal<Option<Ship>>) -> Element {
+pub fn ShipEditor(
+    mut ship: Signal<Option<Ship>>,
+    on_apply_paint: EventHandler<HullPaint>,
+) -> Element {
     let memo = use_memo(move || {
         let ship_read = ship.read();
         let Some(ship_r) = ship_read.as_ref() else {
             return (rsx! { "No ship selected" }, None);
         };
 
-        if ship_r.hull_type != "Stock/Bulk Hauler" {
+        let Some(hull) = LinerHull::of(ship_r) else {
             return (rsx! { "Ship is not a liner" }, None);
-        }
+        };
 
         let hull_params = get_ln_editable_hull_params(ship_r);
 
@@ -30,6 +44,7 @@ pub fn ShipEditor(mut ship: Signal<Option<Ship>>) -> Element {
                     ShipConfigTable {
                         key: "{ship_r.name}{hull_params:?}",
                         ship,
+                        hull,
                         hull_params: hull_params.clone(),
                     }
                 },
@@ -92,6 +107,93 @@ pub fn ShipEditor(mut ship: Signal<Option<Ship>>) -> Element {
                 }
             }
             {ship_config_table}
+            if let Some(ship_read) = ship.read().as_ref() {
+                if hull_params.read().is_some() {
+                    HullPaintEditor {
+                        key: "{ship_read.name}",
+                        ship,
+                        on_apply_paint,
+                    }
+                    HullTemplatePicker { ship }
+                }
+            }
+        }
+    }
+}
+
+fn tint_to_hsv([r, g, b, _]: [u8; 4]) -> Hsv<Srgb, f64> {
+    let color: Rgb<Srgb, f64> = Rgb::<Srgb, u8>::new(r, g, b).into_format();
+    color.into_color()
+}
+
+#[component]
+fn HullPaintEditor(
+    ship: Signal<Option<Ship>>,
+    on_apply_paint: EventHandler<HullPaint>,
+) -> Element {
+    let paint = use_memo(move || {
+        ship.read()
+            .as_ref()
+            .and_then(|ship| ship.hull_config.as_ref())
+            .map(HullPaint::of)
+            .unwrap_or_default()
+    });
+    let mut set_paint = move |paint: HullPaint| {
+        let mut ship_w = ship.write();
+        if let Some(hull_config) =
+            ship_w.as_mut().and_then(|ship| ship.hull_config.as_mut())
+        {
+            paint.apply(hull_config);
+        }
+    };
+
+    let mut tint = use_signal(|| tint_to_hsv(paint.peek().tint));
+    let mut tint_picker_open = use_signal(|| false);
+    // Follow changes made elsewhere, like pasting a config
+    use_effect(move || tint.set(tint_to_hsv(paint().tint)));
+
+    rsx! {
+        div { style: "display: flex; flex-direction: row; align-items: center; gap: 6px; margin-top: 6px;",
+            "Tint"
+            ColorPicker {
+                open: tint_picker_open(),
+                flex_grow: 0,
+                color: tint,
+                on_open_change: move |now_open| tint_picker_open.set(now_open),
+                on_color_change: move |c: Hsv<Srgb, f64>| {
+                    tint.set(c);
+                    let color: Rgb<Srgb, f64> = c.into_color();
+                    let color: Rgb<Srgb, u8> = color.into_format();
+                    let mut new_paint = paint();
+                    new_paint.tint[..3].copy_from_slice(&[color.red, color.green, color.blue]);
+                    set_paint(new_paint);
+                },
+            }
+            "Texture"
+            for (axis , axis_name) in ["X", "Y", "Z"].into_iter().enumerate() {
+                label { "{axis_name}" }
+                input {
+                    r#type: "number",
+                    style: "width: 60px;",
+                    step: "0.05",
+                    min: "0",
+                    max: "1",
+                    value: "{paint().texture_variation[axis]}",
+                    onchange: move |e| {
+                        let Ok(value) = e.value().parse::<f32>() else {
+                            return;
+                        };
+                        let mut new_paint = paint();
+                        new_paint.texture_variation[axis] = value.clamp(0.0, 1.0);
+                        set_paint(new_paint);
+                    },
+                }
+            }
+            button {
+                class: "button",
+                onclick: move |_| on_apply_paint.call(paint()),
+                "Apply to all liners"
+            }
         }
     }
 }
@@ -119,70 +221,45 @@ pub fn ChevronDown() -> Element {
 fn DressingDropdown(
     segment: usize,
     slot: usize,
+    hull: &'static LinerHull,
     hull_params: Signal<EditableHullParams>,
 ) -> Element {
-    let segment_key = match segment {
-        0 => {
-            let segment_key_idx = hull_params.read().bow_type;
-            crate::fleet_edit::BULK_BOWS[segment_key_idx]
-        }
-        1 => {
-            let segment_key_idx = hull_params.read().core_type;
-            crate::fleet_edit::BULK_CORES[segment_key_idx]
-        }
-        _ => {
-            panic!()
-        }
-    };
-    let dressings = crate::dressings::LN_DRESSINGS
-        .get(&(segment_key, slot))
-        .unwrap();
-
-    let mut selected_dressing = use_signal(|| match segment {
-        0 => hull_params.read().bow_dressings[slot] as usize,
-        1 => hull_params.read().core_dressings[slot] as usize,
-        _ => panic!(),
+    let dressings = use_memo(move || {
+        hull.segment_key(&hull_params.read(), segment)
+            .map(|key| dressing_options(key, slot))
+            .unwrap_or_default()
     });
+    let selected_dressing =
+        use_memo(move || hull_params.read().dressings(segment)[slot] as usize);
 
-    // This can occur when a segment changes type but it has dressings set, so we just reset the dressing to 0.
-    if *selected_dressing.read() >= dressings.len() {
-        *selected_dressing.write() = 0;
+    if dressings().is_empty() {
+        return rsx! {};
     }
 
-    use_effect(move || {
-        let idx = selected_dressing.read().clone();
-        // This can occur when a segment changes type but it has dressings set, so we just reset the dressing to 0.
-        if idx >= dressings.len() {
-            *selected_dressing.write() = 0;
-            return;
-        }
-        let idx = idx as u8;
-        match segment {
-            0 => hull_params.write().bow_dressings[slot] = idx,
-            1 => hull_params.write().core_dressings[slot] = idx,
-            _ => panic!(),
-        }
-    });
-
     rsx! {
         DropdownMenu {
             // The dropdown menu trigger is the button that will display the dropdown menu when clicked.
             DropdownMenuTrigger {
                 {
-                    let selected_dressing_text = dressings[selected_dressing.read().clone()];
+                    let selected_dressing_text = dressings()
+                        .get(selected_dressing())
+                        .copied()
+                        .unwrap_or("Unknown");
                     rsx! { "{selected_dressing_text}" }
                 }
                 ChevronDown {}
             }
             // The dropdown menu content contains all the items that will be displayed in the dropdown menu.
             DropdownMenuContent {
-                for (idx , dressing) in dressings.iter().enumerate() {
+                for (idx , dressing) in dressings().iter().enumerate() {
                     DropdownMenuItem {
                         // The index of the item, used to determine the order in which items are displayed.
                         index: idx,
                         // The value of the item which will be passed to the on_select callback when the item is selected.
                         value: idx,
-                        on_select: move |value: usize| { selected_dressing.set(value) }, // This callback is triggered when the item is selected.,
+                        on_select: move |value: usize| {
+                            hull_params.write().dressings_mut(segment)[slot] = value as u8;
+                        }, // This callback is triggered when the item is selected.,
                         "{dressing}"
                     }
                 }
@@ -194,69 +271,54 @@ fn DressingDropdown(
 #[component]
 fn SegmentTypeDropdown(
     segment: usize,
+    hull: &'static LinerHull,
     hull_params: Signal<EditableHullParams>,
 ) -> Element {
-    let mut selected_segment_type = use_signal(|| {
-        let segment_type = match segment {
-            0 => hull_params.read().bow_type,
-            1 => hull_params.read().core_type,
-            2 => hull_params.read().stern_type,
-            _ => panic!(),
-        };
-        match segment_type {
-            0 => "A",
-            1 => "B",
-            2 => "C",
-            _ => panic!(),
+    // Read from `hull_params` so the dropdown is reverted along with a
+    // rejected configuration
+    let selected_segment_type =
+        use_memo(move || hull_params.read().segment_type(segment));
+    let mut select_segment_type = move |segment_type: usize| {
+        if hull_params.peek().segment_type(segment) == segment_type {
+            return;
         }
-    });
-    use_effect(move || {
-        let segment_type = match *selected_segment_type.read() {
-            "A" => 0,
-            "B" => 1,
-            "C" => 2,
-            _ => panic!(),
-        };
         trace!(%segment, %segment_type, "Updating segment type");
+        let mut hull_params = hull_params.write();
         match segment {
-            0 => hull_params.write().bow_type = segment_type,
-            1 => hull_params.write().core_type = segment_type,
-            2 => hull_params.write().stern_type = segment_type,
+            0 => hull_params.bow_type = segment_type,
+            1 => hull_params.core_type = segment_type,
+            2 => hull_params.stern_type = segment_type,
             _ => panic!(),
         }
-    });
+        // Dressings differ between segment types
+        *hull_params.dressings_mut(segment) = [0; DRESSING_SLOTS];
+        // A bridge on a segment that can't carry it moves to one that can
+        if hull_params.superstructure_loc == segment
+            && !hull.can_mount_bridge(&hull_params, segment)
+            && let Some(loc) =
+                (0..3).find(|&loc| hull.can_mount_bridge(&hull_params, loc))
+        {
+            hull_params.superstructure_loc = loc;
+        }
+    };
     rsx! {
         DropdownMenu {
             // The dropdown menu trigger is the button that will display the dropdown menu when clicked.
             DropdownMenuTrigger {
-                "{selected_segment_type}"
+                "{variant_label(selected_segment_type())}"
                 ChevronDown {}
             }
             // The dropdown menu content contains all the items that will be displayed in the dropdown menu.
             DropdownMenuContent {
-                DropdownMenuItem {
-                    // The index of the item, used to determine the order in which items are displayed.
-                    index: 0usize,
-                    // The value of the item which will be passed to the on_select callback when the item is selected.
-                    value: "A",
-                    on_select: move |value: &'static str| { selected_segment_type.set(value) }, // This callback is triggered when the item is selected.,
-                    "A"
-                }
-                DropdownMenuItem {
-                    // The index of the item, used to determine the order in which items are displayed.
-                    index: 1usize,
-                    // The value of the item which will be passed to the on_select callback when the item is selected.
-                    value: "B",
-                    on_select: move |value: &'static str| { selected_segment_type.set(value) }, // This callback is triggered when the item is selected.,
-                    "B"
-                }
-                DropdownMenuItem {
-                    // The index of the item, used to determine the order in which items are displayed.
-                    index: 2usize,
-                    // The value of the item which will be passed to the on_select callback when the item is selected.
-                    value: "C",
-                    on_select: move |value: &'static str| { selected_segment_type.set(value) }, // This callback is triggered when the item is selected.,
-                    "C"
+                for idx in 0..hull.segments(segment).len() {
+                    DropdownMenuItem {
+                        // The index of the item, used to determine the order in which items are displayed.
+                        index: idx,
+                        // The value of the item which will be passed to the on_select callback when the item is selected.
+                        value: idx,
+                        on_select: move |value: usize| { select_segment_type(value) }, // This callback is triggered when the item is selected.,
+                        "{variant_label(idx)}"
+                    }
                 }
             }
         }
@@ -266,46 +328,48 @@ fn SegmentTypeDropdown(
 #[component]
 fn ShipConfigTable(
     ship: Signal<Option<Ship>>,
+    hull: &'static LinerHull,
     hull_params: EditableHullParams,
 ) -> Element {
     debug!("Creating ShipConfigTable");
 
-    let mut selected_bridge_loc = use_signal(|| hull_params.superstructure_loc);
     let mut hull_params = use_signal(|| hull_params);
+    // Why the last change was refused
+    let mut error = use_signal(|| None::<String>);
 
     use_effect(move || {
-        hull_params.write().superstructure_loc =
-            selected_bridge_loc.read().clone();
-    });
-
-    use_effect(move || {
-        let hull_params = &*hull_params.read();
-        let mut ship_w = ship.write();
-        let ship = ship_w.as_mut();
-        if let Some(ship) = ship {
+        let params = hull_params.read().clone();
+        let current =
+            ship.peek().as_ref().and_then(get_ln_editable_hull_params);
+        let Some(current) = current.filter(|current| *current != params) else {
+            return;
+        };
+        if let Err(err) = hull.validate(&params) {
+            warn!(%err, "Invalid hull configuration");
+            error.set(Some(err.to_string()));
+            // Puts the dropdowns back to the ship's configuration
+            hull_params.set(current);
+            return;
+        }
+        error.set(None);
+        if let Some(ship) = ship.write().as_mut() {
             debug!("Updating hull configuration for ship '{}'", ship.name);
-            fleet_edit::set_ln_hull_config(ship, hull_params.clone());
+            fleet_edit::set_ln_hull_config(ship, params);
         }
     });
 
-    let mut selected_bridge_type =
-        use_signal(|| match hull_params.read().superstructure_type {
-            0 => "A",
-            1 => "B",
-            2 => "C",
-            3 => "D",
-            _ => panic!(),
-        });
-    use_effect(move || {
-        let bridge_type = selected_bridge_type();
-        hull_params.write().superstructure_type = match bridge_type {
-            "A" => 0,
-            "B" => 1,
-            "C" => 2,
-            "D" => 3,
-            _ => unreachable!(),
-        }
-    });
+    // Slots no segment offers dressings for aren't shown
+    let dressing_slots = (0..DRESSING_SLOTS)
+        .filter(|&slot| {
+            (0..3).any(|segment| {
+                hull.segment_key(&hull_params.read(), segment)
+                    .is_some_and(|key| !dressing_options(key, slot).is_empty())
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let selected_bridge_loc = move || hull_params.read().superstructure_loc;
+    let selected_bridge_type = move || hull_params.read().superstructure_type;
 
     rsx! {
         table { style: "table-layout: fixed;",
@@ -332,13 +396,13 @@ fn ShipConfigTable(
                     td { "Segment Type" }
                     td {}
                     td {
-                        SegmentTypeDropdown { segment: 0, hull_params }
+                        SegmentTypeDropdown { segment: 0, hull, hull_params }
                     }
                     td {
-                        SegmentTypeDropdown { segment: 1, hull_params }
+                        SegmentTypeDropdown { segment: 1, hull, hull_params }
                     }
                     td {
-                        SegmentTypeDropdown { segment: 2, hull_params }
+                        SegmentTypeDropdown { segment: 2, hull, hull_params }
                     }
                 }
 
@@ -347,33 +411,17 @@ fn ShipConfigTable(
                     td {
                         DropdownMenu {
                             DropdownMenuTrigger {
-                                "{selected_bridge_type}"
+                                "{variant_label(selected_bridge_type())}"
                                 ChevronDown {}
                             }
                             DropdownMenuContent {
-                                DropdownMenuItem {
-                                    index: 0usize,
-                                    value: "A",
-                                    on_select: move |value: &'static str| { selected_bridge_type.set(value) },
-                                    "A"
-                                }
-                                DropdownMenuItem {
-                                    index: 1usize,
-                                    value: "B",
-                                    on_select: move |value: &'static str| { selected_bridge_type.set(value) },
-                                    "B"
-                                }
-                                DropdownMenuItem {
-                                    index: 2usize,
-                                    value: "C",
-                                    on_select: move |value: &'static str| { selected_bridge_type.set(value) },
-                                    "C"
-                                }
-                                DropdownMenuItem {
-                                    index: 3usize,
-                                    value: "D",
-                                    on_select: move |value: &'static str| { selected_bridge_type.set(value) },
-                                    "D"
+                                for idx in 0..hull.bridges.len() {
+                                    DropdownMenuItem {
+                                        index: idx,
+                                        value: idx,
+                                        on_select: move |value: usize| { hull_params.write().superstructure_type = value },
+                                        "{variant_label(idx)}"
+                                    }
                                 }
                             }
                         }
@@ -383,8 +431,9 @@ fn ShipConfigTable(
                             r#type: "radio",
                             name: "superstructure_loc",
                             checked: selected_bridge_loc() == 0,
+                            disabled: !hull.can_mount_bridge(&hull_params.read(), 0),
                             oninput: move |_| {
-                                *selected_bridge_loc.write() = 0;
+                                hull_params.write().superstructure_loc = 0;
                             },
                         }
                     }
@@ -393,8 +442,9 @@ fn ShipConfigTable(
                             r#type: "radio",
                             name: "superstructure_loc",
                             checked: selected_bridge_loc() == 1,
+                            disabled: !hull.can_mount_bridge(&hull_params.read(), 1),
                             oninput: move |_| {
-                                *selected_bridge_loc.write() = 1;
+                                hull_params.write().superstructure_loc = 1;
                             },
                         }
                     }
@@ -403,26 +453,160 @@ fn ShipConfigTable(
                             r#type: "radio",
                             name: "superstructure_loc",
                             checked: selected_bridge_loc() == 2,
+                            disabled: !hull.can_mount_bridge(&hull_params.read(), 2),
                             oninput: move |_| {
-                                *selected_bridge_loc.write() = 2;
+                                hull_params.write().superstructure_loc = 2;
                             },
                         }
                     }
                 }
 
-            // for slot in 0..8 {
-            //     tr {
-            //         td { "Dressing Slot {slot+1}" }
-            //         td {}
-            //         td {
-            //             DressingDropdown { segment: 0, slot, hull_params }
-            //         }
-            //         td {
-            //             DressingDropdown { segment: 1, slot, hull_params }
-            //         }
-            //         td {}
-            //     }
-            // }
+                for slot in dressing_slots {
+                    tr {
+                        td { "Dressing Slot {slot+1}" }
+                        td {}
+                        td {
+                            DressingDropdown { segment: 0, slot, hull, hull_params }
+                        }
+                        td {
+                            DressingDropdown { segment: 1, slot, hull, hull_params }
+                        }
+                        td {
+                            DressingDropdown { segment: 2, slot, hull, hull_params }
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(error) = error() {
+            p { class: "search-error", style: "margin: 0px", "{error}" }
+        }
+    }
+}
+
+/// Save the ship's hull as a named template, or apply a saved one.
+#[component]
+fn HullTemplatePicker(ship: Signal<Option<Ship>>) -> Element {
+    let mut templates = use_signal(|| {
+        HULL_TEMPLATES
+            .get()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .templates
+            .clone()
+    });
+    let mut new_name = use_signal(String::new);
+    let mut new_description = use_signal(String::new);
+    let mut status = use_signal(String::new);
+
+    let Some((hull_type, hull_params)) =
+        ship.read().as_ref().and_then(|ship| {
+            let hull_params = get_ln_editable_hull_params(ship)?;
+            Some((ship.hull_type.clone(), hull_params))
+        })
+    else {
+        return rsx! {};
+    };
+    let fitting = templates
+        .read()
+        .iter()
+        .filter(|template| template.hull_type == hull_type)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; gap: 5px; margin-top: 10px;",
+            h4 { style: "margin: 0px", "Templates" }
+            if fitting.is_empty() {
+                p { class: "bg-text", style: "margin: 0px", "No templates for this hull yet" }
+            }
+            for template in fitting {
+                div {
+                    key: "{template.name}",
+                    style: "display: flex; flex-direction: row; align-items: center; gap: 5px;",
+                    div { style: "display: flex; flex-direction: column; flex: 1; min-width: 0;",
+                        span { "{template.name}" }
+                        span { class: "bg-text", "{template.summary()}" }
+                        if !template.description.is_empty() {
+                            span { class: "bg-text", "{template.description}" }
+                        }
+                    }
+                    button {
+                        class: "button",
+                        onclick: {
+                            let template = template.clone();
+                            move |_| {
+                                let fits = ship
+                                    .peek()
+                                    .as_ref()
+                                    .and_then(LinerHull::of)
+                                    .map(|hull| hull.validate(&template.params));
+                                match fits {
+                                    Some(Ok(())) => {
+                                        let mut ship_w = ship.write();
+                                        if let Some(ship) = ship_w.as_mut() {
+                                            fleet_edit::set_ln_hull_config(ship, template.params.clone());
+                                        }
+                                        status.set(format!("Applied '{}'", template.name));
+                                    }
+                                    Some(Err(err)) => status.set(format!("'{}' doesn't fit: {err}", template.name)),
+                                    None => {}
+                                }
+                            }
+                        },
+                        "Apply"
+                    }
+                    button {
+                        class: "button",
+                        onclick: {
+                            let name = template.name.clone();
+                            move |_| {
+                                let mut repo = HULL_TEMPLATES.get().unwrap().lock().unwrap();
+                                repo.remove(&name);
+                                templates.set(repo.templates.clone());
+                                status.set(format!("Deleted '{name}'"));
+                            }
+                        },
+                        "Delete"
+                    }
+                }
+            }
+            form {
+                style: "display: flex; flex-direction: row; gap: 5px;",
+                onsubmit: move |_| {
+                    let name = new_name().trim().to_string();
+                    if name.is_empty() {
+                        return;
+                    }
+                    let mut repo = HULL_TEMPLATES.get().unwrap().lock().unwrap();
+                    repo.add(HullTemplate {
+                        name: name.clone(),
+                        description: new_description().trim().to_string(),
+                        hull_type: hull_type.clone(),
+                        params: hull_params.clone(),
+                    });
+                    templates.set(repo.templates.clone());
+                    new_name.set(String::new());
+                    new_description.set(String::new());
+                    status.set(format!("Saved '{name}'"));
+                },
+                input {
+                    style: "flex: 1;",
+                    placeholder: "Template name",
+                    value: "{new_name}",
+                    oninput: move |evt| new_name.set(evt.value()),
+                }
+                input {
+                    style: "flex: 2;",
+                    placeholder: "Description",
+                    value: "{new_description}",
+                    oninput: move |evt| new_description.set(evt.value()),
+                }
+                button { class: "button", r#type: "submit", "Save as Template" }
+            }
+            if !status.read().is_empty() {
+                p { class: "bg-text", style: "margin: 0px", "{status}" }
             }
         }
     }
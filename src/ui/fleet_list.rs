@@ -1,4 +1,9 @@
-use std::{ops::DerefMut, time::Duration};
+use std::{
+    ops::{DerefMut, Range},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 use dioxus::{
     desktop::{Config, WindowBuilder, use_muda_event_handler},
@@ -10,26 +15,48 @@ use palette::{
     encoding::{self, Srgb},
     rgb::Rgb,
 };
+use rfd::AsyncFileDialog;
 use schemas::Ship;
 
 use crate::{
     components::color_picker::ColorPicker,
     fleet_data::FleetData,
-    system::{audio::AUDIO_HANDLER, config::load_app_config},
-    tags::{Color, TAGS_REPO, Tag},
+    fleet_edit::{
+        HullPaint, LinerHull, get_ln_editable_hull_params, paint_liners,
+    },
+    hull_templates::HullTemplate,
+    saved_searches::{SAVED_SEARCHES, SavedSearch},
+    system::{
+        audio::AUDIO_HANDLER,
+        config::{TagStorage, load_app_config},
+        fleet_io::{changed_on_disk, written_by_nebtools},
+        load_fleets::{
+            LoadProgress, load_fleets_with_progress, refresh_fleets,
+        },
+        watcher::{FleetChange, FleetWatcher, watch_fleets},
+    },
+    tags::{Color, TAGS_REPO, Tag, tag_storage},
     ui::{
         dialog::{
             DialogWrapper,
             backup::BackupDialog,
             error::{ErrorDialog, ErrorType},
+            formation_batch::FormationBatchDialog,
+            hull_batch::HullBatchDialog,
+            hull_template::HullTemplateDialog,
             merge_fleets::MergeFleetsDialog,
+            save_search::SaveSearchDialog,
             settings::SettingsDialog,
             spinner::SpinnerDialog,
+            tag_manager::TagManagerDialog,
         },
         fleet_editor::ShipEditor,
         formations::FleetFormationViewer,
     },
-    util::spawn_async::spawn_async,
+    util::{
+        search::{SearchMatch, parse_search_text},
+        spawn_async::spawn_async,
+    },
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -39,14 +66,72 @@ pub enum FleetEditorTab {
     LinerEditor,
 }
 
+/// Load fleets on a background thread, updating `progress` with the number of
+/// fleets loaded so far and the total.
+async fn load_fleets_reporting(
+    mut progress: Signal<Option<(usize, usize)>>,
+) -> color_eyre::Result<Vec<FleetData>> {
+    let load_progress = Arc::new(LoadProgress::default());
+    let loading = spawn_async({
+        let load_progress = load_progress.clone();
+        move || {
+            let (mut fleets, changed) =
+                load_fleets_with_progress(None, &load_progress)?;
+            // New and changed fleets are tagged by the auto tag rules
+            crate::auto_tags::auto_tag_loaded(&mut fleets, &changed);
+            Ok(fleets)
+        }
+    });
+    let poll = spawn(async move {
+        loop {
+            progress.set(Some(load_progress.get()));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    });
+    let fleets = loading.await;
+    poll.cancel();
+    progress.set(None);
+    fleets
+}
+
+/// Split `text` into runs of characters that are and aren't inside `spans`,
+/// so matched parts can be highlighted.
+fn highlight(text: &str, spans: &[Range<usize>]) -> Vec<(String, bool)> {
+    let mut pieces: Vec<(String, bool)> = Vec::new();
+    for (idx, c) in text.chars().enumerate() {
+        let highlighted = spans.iter().any(|span| span.contains(&idx));
+        match pieces.last_mut() {
+            Some((piece, last)) if *last == highlighted => piece.push(c),
+            _ => pieces.push((c.to_string(), highlighted)),
+        }
+    }
+    pieces
+}
+
 #[component]
 pub fn FleetList() -> Element {
+    let mut load_progress = use_signal(|| None::<(usize, usize)>);
+    let mut saved_searches = use_signal(Vec::<SavedSearch>::new);
     let mut fleets = use_resource(async move || {
         // Load app configuration first
         spawn_async(load_app_config).await.unwrap();
         spawn_async(crate::tags::init_tags).await;
+        spawn_async(crate::tag_db::init_tag_db).await;
+        spawn_async(crate::auto_tags::init_auto_tags).await;
+        spawn_async(crate::saved_searches::init_saved_searches).await;
+        spawn_async(crate::hull_templates::init_hull_templates).await;
+        spawn_async(crate::formation_templates::init_formation_templates).await;
+        saved_searches.set(
+            SAVED_SEARCHES
+                .get()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .searches
+                .clone(),
+        );
         // Then load fleets (load_fleets requires APP_CONFIG to be set)
-        spawn_async(|| crate::system::load_fleets::load_fleets(None)).await
+        load_fleets_reporting(load_progress).await
     });
 
     let mut description = use_signal(String::new);
@@ -104,17 +189,13 @@ pub fn FleetList() -> Element {
     let mut tags = use_signal(|| {
         let binding = description.read();
         let desc = binding.as_str();
-        let tags = crate::tags::get_tags_from_description(desc);
-        match tags {
-            Ok((tags, _)) => tags,
-            Err(err) => {
-                warn!(?err, "Failed to retrieve tags from fleet description");
-                vec![]
-            }
-        }
+        crate::tags::get_tags_from_description(desc).0
     });
 
     let mut tags_dirty = use_signal(|| false);
+    // Changed whenever tags stored outside fleet files change, as the fleet
+    // list can't see those
+    let mut tags_version = use_signal(|| 0usize);
 
     let mut merge_fleets_dialog_open = use_signal(|| false);
 
@@ -146,8 +227,31 @@ pub fn FleetList() -> Element {
         }};
     }
 
+    // Read the selected fleet again if NebTools rewrote any of `paths` in the
+    // background. The watcher picks up the other fleets, but later edits to
+    // the selected fleet would write back its old contents.
+    macro_rules! reload_if_selected {
+        ($paths:expr) => {{
+            let is_selected = selected_fleet_data
+                .peek()
+                .as_ref()
+                .is_some_and(|f| $paths.contains(&f.path));
+            if is_selected {
+                prev_path.set(None);
+                selected_fleet.restart();
+            }
+        }};
+    }
+
     let mut show_settings_dialog = use_signal(|| false);
     let mut show_backup_dialog = use_signal(|| false);
+    let mut show_save_search_dialog = use_signal(|| false);
+    // The saved search being renamed by the save search dialog, if any
+    let mut renaming_search = use_signal(|| None::<SavedSearch>);
+    let mut show_tag_manager_dialog = use_signal(|| false);
+    let mut show_hull_template_dialog = use_signal(|| false);
+    let mut show_hull_batch_dialog = use_signal(|| false);
+    let mut show_formation_batch_dialog = use_signal(|| false);
 
     let menu_handler =
         use_coroutine(move |mut rx: UnboundedReceiver<String>| async move {
@@ -161,10 +265,8 @@ pub fn FleetList() -> Element {
                         selected_fleet_idx.set(None);
                         selected_ship.set(None);
                         selected_ship_idx.set(None);
-                        let new_fleets = spawn_async(|| {
-                            crate::system::load_fleets::load_fleets(None)
-                        })
-                        .await;
+                        let new_fleets =
+                            load_fleets_reporting(load_progress).await;
                         fleets.set(Some(new_fleets));
                         show_spinner_dialog.set(false);
                     }
@@ -177,7 +279,7 @@ pub fn FleetList() -> Element {
                             .lock()
                             .unwrap()
                             .cache_dir
-                            .join("fleets_data.bin");
+                            .join(crate::system::load_fleets::FLEET_CACHE_FILE);
                         if let Err(err) = std::fs::remove_file(&cache_path) {
                             show_spinner_dialog.set(false);
                             error_popup!(
@@ -191,6 +293,51 @@ pub fn FleetList() -> Element {
                     "fleets-backup" => {
                         show_backup_dialog.set(true);
                     }
+                    "fleets-export-searches" => {
+                        let Some(path) = AsyncFileDialog::new()
+                            .add_filter("Saved Searches", &["toml"])
+                            .set_file_name("saved_searches.toml")
+                            .save_file()
+                            .await
+                        else {
+                            continue;
+                        };
+                        let result = SAVED_SEARCHES
+                            .get()
+                            .unwrap()
+                            .lock()
+                            .unwrap()
+                            .export(path.path());
+                        if let Err(err) = result {
+                            error_popup!(
+                                "Failed to export saved searches",
+                                format!("{:?}", err),
+                                ErrorType::Warn
+                            );
+                        }
+                    }
+                    "fleets-import-searches" => {
+                        let Some(path) = AsyncFileDialog::new()
+                            .add_filter("Saved Searches", &["toml"])
+                            .pick_file()
+                            .await
+                        else {
+                            continue;
+                        };
+                        let mut repo =
+                            SAVED_SEARCHES.get().unwrap().lock().unwrap();
+                        match repo.import(path.path()) {
+                            Ok(count) => {
+                                info!("Imported {} saved searches", count);
+                                saved_searches.set(repo.searches.clone());
+                            }
+                            Err(err) => error_popup!(
+                                "Failed to import saved searches",
+                                format!("{:?}", err),
+                                ErrorType::Warn
+                            ),
+                        }
+                    }
                     "edit-preferences" => {
                         show_settings_dialog.set(true);
                     }
@@ -207,6 +354,134 @@ pub fn FleetList() -> Element {
 
                         dioxus::desktop::window().new_window(dom, config);
                     }
+                    "tools-tags" => {
+                        show_tag_manager_dialog.set(true);
+                    }
+                    "tools-auto-tag" => {
+                        let paths = match fleets.peek().as_ref() {
+                            Some(Ok(fleets)) => fleets
+                                .iter()
+                                .map(|f| f.path.clone())
+                                .collect::<Vec<_>>(),
+                            _ => continue,
+                        };
+                        // Pick up any edits to the rules file
+                        if let Err(err) =
+                            spawn_async(crate::auto_tags::reload_auto_tags)
+                                .await
+                        {
+                            error_popup!(
+                                "Failed to load auto tag rules",
+                                format!("{:?}", err),
+                                ErrorType::Warn
+                            );
+                            continue;
+                        }
+                        show_spinner!("Applying auto tags");
+                        let (changed, failed) = spawn_async(move || {
+                            let tagged =
+                                crate::auto_tags::auto_tag_fleets(&paths);
+                            // Keeps the rewritten fleets from being parsed
+                            // again on the next load
+                            if let Err(err) = refresh_fleets(&tagged.0) {
+                                warn!(?err, "Failed to update the fleet cache");
+                            }
+                            tagged
+                        })
+                        .await;
+                        show_spinner_dialog.set(false);
+                        reload_if_selected!(changed);
+                        *tags_version.write() += 1;
+                        if !failed.is_empty() {
+                            error_popup!(
+                                "Failed to auto tag some fleets",
+                                failed
+                                    .iter()
+                                    .map(|(path, err)| format!(
+                                        "{}: {:?}",
+                                        path.display(),
+                                        err
+                                    ))
+                                    .collect::<Vec<_>>()
+                                    .join("\n"),
+                                ErrorType::Warn
+                            );
+                        }
+                    }
+                    "tools-export-tags" => {
+                        if tag_storage() == TagStorage::Description {
+                            error_popup!(
+                                "Tags are already in fleet descriptions",
+                                "Tags are only stored separately when \"Store \
+                                 tags outside fleet files\" is enabled in \
+                                 Preferences.",
+                                ErrorType::User
+                            );
+                            continue;
+                        }
+                        let paths = match fleets.peek().as_ref() {
+                            Some(Ok(fleets)) => fleets
+                                .iter()
+                                .map(|f| f.path.clone())
+                                .collect::<Vec<_>>(),
+                            _ => continue,
+                        };
+                        show_spinner!("Writing tags to descriptions");
+                        let (changed, failed) = spawn_async(move || {
+                            crate::tag_db::export_tags(&paths)
+                        })
+                        .await;
+                        show_spinner_dialog.set(false);
+                        reload_if_selected!(changed);
+                        if !failed.is_empty() {
+                            error_popup!(
+                                "Failed to write tags to some fleets",
+                                failed
+                                    .iter()
+                                    .map(|(path, err)| format!(
+                                        "{}: {:?}",
+                                        path.display(),
+                                        err
+                                    ))
+                                    .collect::<Vec<_>>()
+                                    .join("\n"),
+                                ErrorType::Warn
+                            );
+                        }
+                    }
+                    "tools-hull-template" => {
+                        if selected_fleet_idx.peek().is_none() {
+                            error_popup!(
+                                "No fleet selected",
+                                "Select the fleets to apply the template to",
+                                ErrorType::User
+                            );
+                        } else {
+                            show_hull_template_dialog.set(true);
+                        }
+                    }
+                    "tools-hull-batch" => {
+                        if selected_fleet_idx.peek().is_none() {
+                            error_popup!(
+                                "No fleet selected",
+                                "Select the fleets to edit",
+                                ErrorType::User
+                            );
+                        } else {
+                            show_hull_batch_dialog.set(true);
+                        }
+                    }
+                    "tools-formation-batch" => {
+                        if selected_fleet_idx.peek().is_none() {
+                            error_popup!(
+                                "No fleet selected",
+                                "Select the fleets to apply the formation to",
+                                ErrorType::User
+                            );
+                        } else {
+                            show_formation_batch_dialog.set(true);
+                        }
+                    }
                     "tools-merge" => {
                         if !selected_fleet_idx.read().is_some() {
                             error_popup!(
@@ -249,16 +524,16 @@ pub fn FleetList() -> Element {
     use_effect(move || {
         let binding = description.read();
         let desc = binding.as_str();
-        match crate::tags::get_tags_from_description(desc) {
-            Ok((new_tags, _)) => {
-                // Only update if tags actually differ, to avoid spurious writes
-                if *tags.peek() != new_tags {
-                    tags.set(new_tags);
-                }
-            }
-            Err(err) => {
-                warn!(?err, "Failed to retrieve tags from fleet description");
-            }
+        tags_version();
+        let path = selected_fleet_data
+            .read()
+            .as_ref()
+            .map(|f| f.path.clone())
+            .unwrap_or_default();
+        let new_tags = crate::tags::stored_tags(&path, desc);
+        // Only update if tags actually differ, to avoid spurious writes
+        if *tags.peek() != new_tags {
+            tags.set(new_tags);
         }
     });
     // When the tags are updated, insert them into the description.
@@ -270,30 +545,35 @@ pub fn FleetList() -> Element {
         }
         tags_dirty.set(false);
 
-        let desc =
-            crate::tags::get_tags_from_description(description.peek().as_str())
-                .map(|x| x.1)
-                .unwrap_or_default();
-
-        let new_desc = if tags.is_empty() {
-            desc
-        } else {
-            format!(
-                "Tags: {}\n{}",
-                tags.iter()
-                    .map(|tag| format!(
-                        "<color=#{:02x}{:02x}{:02x}>{}</color>",
-                        tag.color.red,
-                        tag.color.green,
-                        tag.color.blue,
-                        tag.name
-                    ))
-                    .collect::<Vec<_>>()
-                    .join(" "),
-                desc,
-            )
+        let Some(path) =
+            selected_fleet_data.peek().as_ref().map(|f| f.path.clone())
+        else {
+            return;
+        };
+        let result = match tag_storage() {
+            TagStorage::Description => {
+                let desc = crate::tags::get_tags_from_description(
+                    description.peek().as_str(),
+                )
+                .1;
+                // Tags hidden in game are saved straight away, the rest with
+                // the description
+                crate::tags::tagged_description(&path, &tags, &desc)
+                    .map(|new_desc| description.set(new_desc))
+            }
+            TagStorage::Database => crate::tags::set_fleet_tags(&path, &tags),
         };
-        description.set(new_desc);
+        match result {
+            Ok(()) => *tags_version.write() += 1,
+            Err(err) => {
+                error_popup!(
+                    "Failed to save tags",
+                    format!("{:?}", err),
+                    ErrorType::Warn
+                );
+                error!("Failed to save tags: {:?}", err);
+            }
+        }
     });
 
     // When the selected_ship is updated, save the fleet.
@@ -376,8 +656,163 @@ pub fn FleetList() -> Element {
         };
     });
 
+    // Paint every liner in the selected fleet like the selected ship.
+    let apply_paint = move |paint: HullPaint| {
+        let Some(path) = selected_fleet_data
+            .read()
+            .as_ref()
+            .map(|fleet_data| fleet_data.path.clone())
+        else {
+            return;
+        };
+        let mut fleet_w = selected_fleet.write();
+        let Some(Some(fleet)) = fleet_w.as_mut() else {
+            return;
+        };
+        let painted = paint_liners(fleet, &paint);
+        info!("Painted {painted} liners in '{}'", path.display());
+        if let Err(err) = crate::system::fleet_io::write_fleet(&path, fleet) {
+            error_popup!(
+                "Failed to write fleet file",
+                format!("{:?}", err),
+                ErrorType::Warn
+            );
+            error!("Failed to write fleet file: {:?}", err);
+        }
+    };
+
     let mut secondary_selected_fleet_idxs = use_signal(|| Vec::<usize>::new());
 
+    // Apply fleets added, changed or removed outside NebTools, such as by the
+    // game, to the fleet list.
+    let fleet_changes = use_coroutine(
+        move |mut rx: UnboundedReceiver<FleetChange>| async move {
+            while let Some(change) = rx.next().await {
+                let mut fleets_w = fleets.as_mut();
+                let Some(Ok(all_fleets)) = fleets_w.deref_mut() else {
+                    continue;
+                };
+                match change {
+                    FleetChange::Updated(fleet_data) => {
+                        let path = fleet_data.path.clone();
+                        match all_fleets.iter().position(|f| f.path == path) {
+                            Some(idx) => all_fleets[idx] = *fleet_data,
+                            None => all_fleets.push(*fleet_data),
+                        }
+                        drop(fleets_w);
+
+                        // NebTools' own saves need nothing more, and auto
+                        // tagging them would undo tags removed by hand
+                        if written_by_nebtools(&path) {
+                            continue;
+                        }
+                        let is_selected = selected_fleet_data
+                            .peek()
+                            .as_ref()
+                            .is_some_and(|f| f.path == path);
+                        if is_selected && changed_on_disk(&path) {
+                            // Selecting the fleet again reloads it, including
+                            // the description
+                            prev_path.set(None);
+                            error_popup!(
+                                "Fleet changed on disk",
+                                format!(
+                                    "'{}' was changed by another program. \
+                                     Select it again to load the changes, \
+                                     edits can't be saved until then.",
+                                    path.display()
+                                ),
+                                ErrorType::Warn
+                            );
+                        }
+
+                        // New fleets and those changed by other programs are
+                        // tagged by the auto tag rules
+                        let (tagged, _) = spawn_async(move || {
+                            crate::auto_tags::auto_tag_fleets(
+                                std::slice::from_ref(&path),
+                            )
+                        })
+                        .await;
+                        if !tagged.is_empty() {
+                            reload_if_selected!(tagged);
+                            *tags_version.write() += 1;
+                        }
+                    }
+                    FleetChange::Removed(path) => {
+                        // Saving a fleet replaces the file, which can look
+                        // like a removal
+                        if path.exists() {
+                            continue;
+                        }
+                        let removed = all_fleets
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, f)| f.path.starts_with(&path))
+                            .map(|(idx, _)| idx)
+                            .collect::<Vec<_>>();
+                        if removed.is_empty() {
+                            continue;
+                        }
+                        for &idx in removed.iter().rev() {
+                            all_fleets.remove(idx);
+                        }
+                        drop(fleets_w);
+
+                        // Fleets after a removed one move up the list
+                        let new_idx = |idx: usize| {
+                            (!removed.contains(&idx)).then(|| {
+                                idx - removed
+                                    .iter()
+                                    .filter(|&&r| r < idx)
+                                    .count()
+                            })
+                        };
+                        secondary_selected_fleet_idxs.with_mut(|idxs| {
+                            *idxs = idxs
+                                .iter()
+                                .filter_map(|&i| new_idx(i))
+                                .collect()
+                        });
+                        let Some(idx) = *selected_fleet_idx.peek() else {
+                            continue;
+                        };
+                        if let Some(idx) = new_idx(idx) {
+                            selected_fleet_idx.set(Some(idx));
+                        } else {
+                            selected_fleet_data.set(None);
+                            selected_fleet_idx.set(None);
+                            selected_ship.set(None);
+                            selected_ship_idx.set(None);
+                            error_popup!(
+                                "Fleet deleted",
+                                format!(
+                                    "'{}' was deleted by another program.",
+                                    path.display()
+                                ),
+                                ErrorType::Warn
+                            );
+                        }
+                    }
+                }
+            }
+        },
+    );
+    // Watch the Fleets directory whenever fleets are loaded. Reloading drops
+    // the old watcher first, in case the saves directory was changed.
+    let mut fleet_watcher = use_signal(|| None::<FleetWatcher>);
+    let fleets_loaded = use_memo(move || matches!(*fleets.read(), Some(Ok(_))));
+    use_effect(move || {
+        if !fleets_loaded() {
+            fleet_watcher.set(None);
+            return;
+        }
+        match watch_fleets(fleet_changes.tx()) {
+            Ok(watcher) => fleet_watcher.set(Some(watcher)),
+            Err(err) => warn!("Failed to watch fleets: {:?}", err),
+        }
+    });
+
     let mut search_text = use_signal(String::new);
     let search_filters = use_memo(move || {
         // Reset all selections on search
@@ -388,13 +823,50 @@ pub fn FleetList() -> Element {
         selected_fleet_data.set(None);
         secondary_selected_fleet_idxs.clear();
 
-        crate::util::search::parse_search_text(search_text())
+        parse_search_text(&search_text())
+    });
+    // Number of fleets in each saved search, or None if it's invalid.
+    let folder_counts = use_memo(move || {
+        tags_version();
+        let fleets = fleets.read();
+        let Some(Ok(fleets)) = fleets.as_ref() else {
+            return Vec::new();
+        };
+        saved_searches
+            .iter()
+            .map(|search| {
+                let filters = parse_search_text(&search.query).ok()?;
+                Some(fleets.iter().filter(|f| filters.matches(f)).count())
+            })
+            .collect::<Vec<_>>()
+    });
+
+    // Fleets to show and how they matched, most relevant first. Nothing is
+    // filtered out while the search has an error.
+    let search_results = use_memo(move || {
+        tags_version();
+        let fleets = fleets.read();
+        let Some(Ok(fleets)) = fleets.as_ref() else {
+            return Vec::new();
+        };
+        match search_filters.read().as_ref() {
+            Ok(filters) => filters.rank(fleets),
+            Err(_) => (0..fleets.len())
+                .map(|idx| (idx, SearchMatch::default()))
+                .collect(),
+        }
     });
 
     rsx! {
         DialogWrapper { signal: show_settings_dialog,
             if show_settings_dialog() {
-                SettingsDialog { signal: show_settings_dialog }
+                SettingsDialog {
+                    signal: show_settings_dialog,
+                    on_tags_moved: move |paths: Vec<PathBuf>| {
+                        reload_if_selected!(paths);
+                        *tags_version.write() += 1;
+                    },
+                }
             } else {
 
             }
@@ -406,9 +878,148 @@ pub fn FleetList() -> Element {
 
             }
         }
+        DialogWrapper { signal: show_save_search_dialog,
+            if show_save_search_dialog() {
+                SaveSearchDialog {
+                    signal: show_save_search_dialog,
+                    query: renaming_search.read().as_ref().map_or(search_text(), |search| search.query.clone()),
+                    saved_searches,
+                    renaming: renaming_search().map(|search| search.name),
+                }
+            } else {
+
+            }
+        }
+        DialogWrapper { signal: show_tag_manager_dialog,
+            if show_tag_manager_dialog() {
+                {
+                    let fleets = fleets.read();
+                    let Some(Ok(all_fleets)) = fleets.as_ref() else {
+                        return rsx! {};
+                    };
+                    rsx! {
+                        TagManagerDialog {
+                            signal: show_tag_manager_dialog,
+                            fleets: all_fleets.clone(),
+                            on_change: move |paths: Vec<PathBuf>| {
+                                reload_if_selected!(paths);
+                                *tags_version.write() += 1;
+                            },
+                        }
+                    }
+                }
+            } else {
+
+            }
+        }
         DialogWrapper { signal: show_spinner_dialog, non_exitable: true,
             if show_spinner_dialog() {
-                SpinnerDialog { title: spinner_title() }
+                SpinnerDialog { title: spinner_title(), progress: load_progress() }
+            } else {
+
+            }
+        }
+        DialogWrapper { signal: show_hull_template_dialog,
+            if show_hull_template_dialog() {
+                {
+                    let fleets = fleets.read();
+                    let Some(Ok(all_fleets)) = fleets.as_ref() else {
+                        return rsx! {};
+                    };
+                    let mut fleet_idxs = secondary_selected_fleet_idxs();
+                    if let Some(idx) = selected_fleet_idx() {
+                        fleet_idxs.push(idx);
+                    }
+                    fleet_idxs.sort();
+                    fleet_idxs.dedup();
+                    let fleets = fleet_idxs
+                        .into_iter()
+                        .map(|idx| all_fleets[idx].clone())
+                        .collect::<Vec<_>>();
+                    rsx! {
+                        HullTemplateDialog {
+                            signal: show_hull_template_dialog,
+                            fleets,
+                            on_change: move |paths: Vec<PathBuf>| {
+                                reload_if_selected!(paths);
+                            },
+                        }
+                    }
+                }
+            } else {
+
+            }
+        }
+        DialogWrapper { signal: show_hull_batch_dialog,
+            if show_hull_batch_dialog() {
+                {
+                    let fleets = fleets.read();
+                    let Some(Ok(all_fleets)) = fleets.as_ref() else {
+                        return rsx! {};
+                    };
+                    let mut fleet_idxs = secondary_selected_fleet_idxs();
+                    if let Some(idx) = selected_fleet_idx() {
+                        fleet_idxs.push(idx);
+                    }
+                    fleet_idxs.sort();
+                    fleet_idxs.dedup();
+                    let fleets = fleet_idxs
+                        .into_iter()
+                        .map(|idx| all_fleets[idx].clone())
+                        .collect::<Vec<_>>();
+                    let ship_hull = selected_ship
+                        .peek()
+                        .as_ref()
+                        .and_then(|ship| {
+                            Some(HullTemplate {
+                                name: format!("{} (selected ship)", ship.name),
+                                description: String::new(),
+                                hull_type: ship.hull_type.clone(),
+                                params: get_ln_editable_hull_params(ship)?,
+                            })
+                        });
+                    rsx! {
+                        HullBatchDialog {
+                            signal: show_hull_batch_dialog,
+                            fleets,
+                            ship_hull,
+                            on_change: move |paths: Vec<PathBuf>| {
+                                reload_if_selected!(paths);
+                            },
+                        }
+                    }
+                }
+            } else {
+
+            }
+        }
+        DialogWrapper { signal: show_formation_batch_dialog,
+            if show_formation_batch_dialog() {
+                {
+                    let fleets = fleets.read();
+                    let Some(Ok(all_fleets)) = fleets.as_ref() else {
+                        return rsx! {};
+                    };
+                    let mut fleet_idxs = secondary_selected_fleet_idxs();
+                    if let Some(idx) = selected_fleet_idx() {
+                        fleet_idxs.push(idx);
+                    }
+                    fleet_idxs.sort();
+                    fleet_idxs.dedup();
+                    let fleets = fleet_idxs
+                        .into_iter()
+                        .map(|idx| all_fleets[idx].clone())
+                        .collect::<Vec<_>>();
+                    rsx! {
+                        FormationBatchDialog {
+                            signal: show_formation_batch_dialog,
+                            fleets,
+                            on_change: move |paths: Vec<PathBuf>| {
+                                reload_if_selected!(paths);
+                            },
+                        }
+                    }
+                }
             } else {
 
             }
@@ -469,12 +1080,85 @@ pub fn FleetList() -> Element {
             // Fleets List
             div { display: "flex", flex_direction: "column", min_height: 0,
                 h2 { margin: 0, padding: 0, flex_shrink: 0, "Fleets" }
-                input {
-                    value: "{search_text}",
-                    style: "margin: 0px; margin-bottom: 2px;",
-                    id: "search-bar",
-                    placeholder: "Search fleets",
-                    oninput: move |evt| { search_text.set(evt.value()) },
+                div { style: "display: flex; flex-direction: row; gap: 2px; margin-bottom: 2px;",
+                    input {
+                        value: "{search_text}",
+                        style: "margin: 0px; flex: 1;",
+                        id: "search-bar",
+                        placeholder: "Search fleets",
+                        oninput: move |evt| { search_text.set(evt.value()) },
+                    }
+                    button {
+                        class: "button",
+                        title: "Save this search as a folder",
+                        disabled: search_text().trim().is_empty() || search_filters.read().is_err(),
+                        onclick: move |_| {
+                            renaming_search.set(None);
+                            show_save_search_dialog.set(true);
+                        },
+                        "Save"
+                    }
+                }
+                if let Err(err) = search_filters.read().as_ref() {
+                    p { class: "search-error", "{err}" }
+                }
+                // Saved searches, shown as folders of the fleets they match
+                div { style: "display: grid; flex-shrink: 0;",
+                    for (search , count) in saved_searches().into_iter().zip(folder_counts()) {
+                        {
+                            let open = search_text() == search.query;
+                            let query = search.query.clone();
+                            let name = search.name.clone();
+                            let renamed = search.clone();
+                            rsx! {
+                                button {
+                                    key: "{search.name}",
+                                    class: if open { "list-button folder selected" } else { "list-button folder" },
+                                    display: "flex",
+                                    flex_direction: "row",
+                                    justify_content: "space-between",
+                                    align_items: "center",
+                                    title: "{search.query}",
+                                    onclick: move |_| {
+                                        if open {
+                                            search_text.set(String::new());
+                                        } else {
+                                            search_text.set(query.clone());
+                                        }
+                                    },
+                                    span { "{search.name}" }
+                                    span {
+                                        class: if open { "bg-text selected" } else { "bg-text" },
+                                        match count {
+                                            Some(count) => rsx! { "{count}" },
+                                            None => rsx! { "invalid search" },
+                                        }
+                                        span {
+                                            class: "folder-remove",
+                                            title: "Rename saved search",
+                                            onclick: move |evt| {
+                                                evt.stop_propagation();
+                                                renaming_search.set(Some(renamed.clone()));
+                                                show_save_search_dialog.set(true);
+                                            },
+                                            "✎"
+                                        }
+                                        span {
+                                            class: "folder-remove",
+                                            title: "Remove saved search",
+                                            onclick: move |evt| {
+                                                evt.stop_propagation();
+                                                let mut repo = SAVED_SEARCHES.get().unwrap().lock().unwrap();
+                                                repo.remove(&name);
+                                                saved_searches.set(repo.searches.clone());
+                                            },
+                                            "×"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
                 div {
                     style: "
@@ -488,21 +1172,18 @@ pub fn FleetList() -> Element {
                     class: "hide-scroll",
                     match fleets.read().as_ref() {
                         Some(Ok(fleets)) => rsx! {
-                            for (idx , fleet) in fleets.iter().enumerate() {
+                            for (idx , found) in search_results() {
                                 {
-                                    let fleet = fleet.clone();
-                                    if !search_filters.read().matches(&fleet) {
-                                        return rsx! {};
-                                    }
+                                    let fleet = fleets[idx].clone();
+                                    let unknown_fields = fleet.unknown_fields.join("\n");
 
-                                    let selected = use_memo(move || {
-                                        selected_fleet_idx() == Some(idx)
-                                            || secondary_selected_fleet_idxs.iter().any(|idx2| *idx2 == idx)
-                                    });
+                                    // Not a memo, rows move around as the search changes
+                                    let selected = selected_fleet_idx() == Some(idx)
+                                        || secondary_selected_fleet_idxs.iter().any(|idx2| *idx2 == idx);
                                     rsx! {
                                         button {
                                             onmouseenter: move |_| {
-                                                if !selected() {
+                                                if !selected {
                                                     AUDIO_HANDLER.play_hover_sound();
                                                 }
                                             },
@@ -513,7 +1194,7 @@ pub fn FleetList() -> Element {
                                             justify_content: "space-between",
                                             align_items: "center",
                                             key: "{fleet.path.display()}",
-                                            class: if selected() { "list-button selected" } else { "list-button" },
+                                            class: if selected { "list-button selected" } else { "list-button" },
                                             onclick: move |evt| {
                                                 let mods = evt.modifiers();
                                                 if mods.ctrl() && selected_fleet_idx().is_some() {
@@ -536,8 +1217,23 @@ pub fn FleetList() -> Element {
                                                 selected_fleet_data.set(Some(fleet.clone()));
                                                 selected_fleet_idx.set(Some(idx));
                                             },
-                                            "{fleet.name}"
-                                            p { class: if selected() { "bg-text selected" } else { "bg-text" }, "{fleet.short_path.display()}" }
+                                            span {
+                                                for (piece , highlighted) in highlight(&fleet.name, &found.name_spans) {
+                                                    span { class: if highlighted { "search-highlight" } else { "" }, "{piece}" }
+                                                }
+                                                if !unknown_fields.is_empty() {
+                                                    span {
+                                                        class: "newer-badge",
+                                                        title: "Saved by a newer game version. These fields will be kept but can't be edited:\n{unknown_fields}",
+                                                        "newer than NebTools understands"
+                                                    }
+                                                }
+                                            }
+                                            p { class: if selected { "bg-text selected" } else { "bg-text" },
+                                                for (piece , highlighted) in highlight(&fleet.short_path.display().to_string(), &found.path_spans) {
+                                                    span { class: if highlighted { "search-highlight" } else { "" }, "{piece}" }
+                                                }
+                                            }
                                         }
                                     }
                                 }
@@ -550,7 +1246,12 @@ pub fn FleetList() -> Element {
                             }
                         }
                         None => rsx! {
-                            div { "Loading fleets…" }
+                            div {
+                                "Loading fleets…"
+                                if let Some((loaded, total)) = load_progress() {
+                                    " ({loaded}/{total})"
+                                }
+                            }
                         },
                     }
                 }
@@ -571,7 +1272,7 @@ pub fn FleetList() -> Element {
                         disabled: !selected_ship
                             .read()
                             .as_ref()
-                            .is_some_and(|ship| ship.hull_type == "Stock/Bulk Hauler"),
+                            .is_some_and(|ship| LinerHull::of(ship).is_some()),
                         class: "button",
                         style: "height: 30px;",
                         onclick: move |_| { fleet_editor_tab.set(FleetEditorTab::LinerEditor) },
@@ -581,7 +1282,7 @@ pub fn FleetList() -> Element {
                 match fleet_editor_tab() {
                     FleetEditorTab::Blank => rsx! { "" },
                     FleetEditorTab::LinerEditor => rsx! {
-                        ShipEditor { ship: selected_ship }
+                        ShipEditor { ship: selected_ship, on_apply_paint: apply_paint }
                     },
                     FleetEditorTab::FormationViewer => rsx! {
                         FleetFormationViewer {
@@ -606,32 +1307,27 @@ pub fn FleetList() -> Element {
                                     h4 { "Description" }
                                     textarea {
                                         height: "200px",
+                                        // Tags kept outside the fleet leave the description as it is
                                         value: {
-                                            crate::tags::get_tags_from_description(description.read().as_str())
-                                                .map(|x| x.1)
-                                                .unwrap_or_default()
+                                            if tag_storage() == TagStorage::Database {
+                                                description()
+                                            } else {
+                                                crate::tags::get_tags_from_description(description.read().as_str())
+                                                    .1
+                                            }
                                         },
                                         oninput: move |evt| {
-                                            description
-                                                .set(
-                                                    format!(
-                                                        "Tags: {}\n{}",
-                                                        tags
-                                                            .iter()
-                                                            .map(|tag| {
-                                                                format!(
-                                                                    "<color=#{:02x}{:02x}{:02x}>{}</color>",
-                                                                    tag.color.red,
-                                                                    tag.color.green,
-                                                                    tag.color.blue,
-                                                                    tag.name,
-                                                                )
-                                                            })
-                                                            .collect::<Vec<_>>()
-                                                            .join(" "),
-                                                        evt.value(),
-                                                    ),
-                                                )
+                                            if tag_storage() == TagStorage::Database {
+                                                description.set(evt.value())
+                                            } else {
+                                                description
+                                                    .set(
+                                                        crate::tags::format_description(
+                                                            &crate::tags::split_hidden(&tags.read()).0,
+                                                            &evt.value(),
+                                                        ),
+                                                    )
+                                            }
                                         },
                                     }
                                 }
@@ -675,12 +1371,12 @@ pub fn FleetList() -> Element {
                                                     margin: 0,
                                                     text_align: "left",
                                                     height: "40px",
-                                                    class: if selected() { "list-button selected" } else { "list-button" },
+                                                    class: if selected { "list-button selected" } else { "list-button" },
                                                     onclick: move |_| {
                                                         trace!("Selecting ship {}", ship.name);
                                                         selected_ship.set(Some(ship.clone()));
                                                         selected_ship_idx.set(Some(idx));
-                                                        if ship.hull_type.as_str() == "Stock/Bulk Hauler" {
+                                                        if LinerHull::of(&ship).is_some() {
                                                             fleet_editor_tab.set(FleetEditorTab::LinerEditor)
                                                         }
                                                     },
@@ -783,7 +1479,7 @@ fn Tags(tags: Signal<Vec<Tag>>, tags_dirty: Signal<bool>) -> Element {
                         value: "{new_tag_name}",
                         oninput: move |evt| {
                             new_tag_name.set(evt.value());
-                            match TAGS_REPO.get().unwrap().lock().unwrap().get_tag(&evt.value()) {
+                            match TAGS_REPO.get().unwrap().lock().unwrap().suggest_color(&evt.value()) {
                                 Some(color) => {
                                     let color: Rgb<Srgb, f64> = color.into_format();
                                     let color: Hsv<Srgb, f64> = color.into_color();
@@ -0,0 +1,149 @@
+use dioxus::prelude::*;
+
+use crate::{
+    formation_templates::{FORMATION_TEMPLATES, SavedFormation},
+    ui::formations::FormationTemplate,
+};
+
+/// Saved formations, to preview and apply to the current formation or to save
+/// it as a new one. The formation viewer previews `selected` while `open` is
+/// set.
+///
+/// `current` and `escort_hulls` describe the current formation's escorts, and
+/// `unplaced` lists the ships the last applied formation had no slot for.
+#[component]
+pub fn FormationLibrary(
+    open: Signal<bool>,
+    selected: Signal<Option<SavedFormation>>,
+    current: FormationTemplate,
+    escort_hulls: Vec<String>,
+    unplaced: Signal<Option<Vec<String>>>,
+    on_apply: EventHandler<SavedFormation>,
+) -> Element {
+    let mut formations = use_signal(|| {
+        FORMATION_TEMPLATES
+            .get()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .formations
+            .clone()
+    });
+    let mut new_name = use_signal(String::new);
+    let mut new_description = use_signal(String::new);
+
+    let selected_name = selected.read().as_ref().map(|f| f.name.clone());
+    let unplaced_names = unplaced().map(|names| names.join(", "));
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; width: 50%; gap: 5px;",
+            h4 { style: "margin: 5px 0px 0px", "Formation Library" }
+            if formations.read().is_empty() {
+                p { class: "bg-text", style: "margin: 0px", "No formations saved yet" }
+            }
+            div { style: "display: flex; flex-direction: column; max-height: 30vh; overflow-y: auto; gap: 5px;",
+                for formation in formations() {
+                    div {
+                        key: "{formation.name}",
+                        style: "display: flex; flex-direction: row; align-items: center; gap: 5px;",
+                        label { style: "display: flex; flex-direction: column; flex: 1; min-width: 0;",
+                            span {
+                                input {
+                                    r#type: "radio",
+                                    name: "formation-library",
+                                    checked: selected_name.as_ref() == Some(&formation.name),
+                                    onchange: {
+                                        let formation = formation.clone();
+                                        move |_| {
+                                            selected.set(Some(formation.clone()));
+                                            unplaced.set(None);
+                                        }
+                                    },
+                                }
+                                "{formation.name} ({formation.template.escorts.len()} escorts)"
+                            }
+                            if !formation.description.is_empty() {
+                                span { class: "bg-text", "{formation.description}" }
+                            }
+                        }
+                        button {
+                            class: "button",
+                            onclick: {
+                                let name = formation.name.clone();
+                                move |_| {
+                                    let mut repo = FORMATION_TEMPLATES.get().unwrap().lock().unwrap();
+                                    repo.remove(&name);
+                                    formations.set(repo.formations.clone());
+                                    if selected.peek().as_ref().is_some_and(|f| f.name == name) {
+                                        selected.set(None);
+                                    }
+                                }
+                            },
+                            "Delete"
+                        }
+                    }
+                }
+            }
+            form {
+                style: "display: flex; flex-direction: row; gap: 5px;",
+                onsubmit: move |_| {
+                    let name = new_name().trim().to_string();
+                    if name.is_empty() {
+                        return;
+                    }
+                    let mut repo = FORMATION_TEMPLATES.get().unwrap().lock().unwrap();
+                    repo.add(SavedFormation {
+                        name,
+                        description: new_description().trim().to_string(),
+                        template: current.clone(),
+                        hulls: escort_hulls.clone(),
+                    });
+                    formations.set(repo.formations.clone());
+                    new_name.set(String::new());
+                    new_description.set(String::new());
+                },
+                input {
+                    style: "flex: 1;",
+                    placeholder: "Formation name",
+                    value: "{new_name}",
+                    oninput: move |evt| new_name.set(evt.value()),
+                }
+                input {
+                    style: "flex: 2;",
+                    placeholder: "Description",
+                    value: "{new_description}",
+                    oninput: move |evt| new_description.set(evt.value()),
+                }
+                button { class: "button", r#type: "submit", "Save current" }
+            }
+            if let Some(names) = unplaced_names {
+                if names.is_empty() {
+                    p { class: "bg-text", style: "margin: 0px", "Every escort was placed" }
+                } else {
+                    p { class: "search-error", style: "margin: 0px",
+                        "No slot for {names}, so they were left where they were"
+                    }
+                }
+            }
+            div { style: "display: flex; flex-direction: row; gap: 3px;",
+                button {
+                    class: "button",
+                    style: "width: 120px;",
+                    disabled: selected_name.is_none(),
+                    onclick: move |_| {
+                        if let Some(formation) = selected() {
+                            on_apply.call(formation);
+                        }
+                    },
+                    "Apply"
+                }
+                button {
+                    class: "button",
+                    style: "width: 120px;",
+                    onclick: move |_| open.set(false),
+                    "Close"
+                }
+            }
+        }
+    }
+}
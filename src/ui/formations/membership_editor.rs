@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use dioxus::prelude::*;
+
+/// The guide of every ship in the fleet, to attach ships to formations,
+/// detach them or move them between formations. Choosing an unformed ship as
+/// a guide starts a new formation.
+///
+/// `ships` are `(key, name, guide key)`, and `on_change` is called with an
+/// escort's key and its new guide. `error` holds why the last change was
+/// refused.
+#[component]
+pub fn MembershipEditor(
+    open: Signal<bool>,
+    ships: Vec<(String, String, Option<String>)>,
+    error: Signal<Option<String>>,
+    on_change: EventHandler<(String, Option<String>)>,
+) -> Element {
+    let guides = ships
+        .iter()
+        .filter_map(|(_, _, guide)| guide.clone())
+        .collect::<HashSet<_>>();
+    let options = ships
+        .iter()
+        .map(|(key, name, guide)| (key.clone(), name.clone(), guide.is_some()))
+        .collect::<Vec<_>>();
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; width: 50%; gap: 5px;",
+            h4 { style: "margin: 5px 0px 0px", "Formation Members" }
+            p { class: "bg-text", style: "margin: 0px",
+                "Ships leading a formation can't follow another ship. Detach their escorts first."
+            }
+            div { style: "display: grid; grid-template-columns: 40% 60%; max-height: 30vh; overflow-y: auto;",
+                for (key , name , guide) in ships {
+                    "{name}"
+                    select {
+                        disabled: guides.contains(&key),
+                        onchange: {
+                            let key = key.clone();
+                            move |evt: Event<FormData>| {
+                                let value = evt.value();
+                                on_change.call((key.clone(), (!value.is_empty()).then_some(value)));
+                            }
+                        },
+                        option {
+                            value: "",
+                            selected: guide.is_none(),
+                            if guides.contains(&key) {
+                                "Leads a formation"
+                            } else {
+                                "No formation"
+                            }
+                        }
+                        for (guide_key , guide_name , follows) in options.clone() {
+                            if guide_key != key {
+                                option {
+                                    value: "{guide_key}",
+                                    disabled: follows,
+                                    selected: guide.as_ref() == Some(&guide_key),
+                                    "Follow {guide_name}"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some(error) = error() {
+                p { class: "search-error", style: "margin: 0px", "{error}" }
+            }
+            button {
+                class: "button",
+                style: "width: 120px;",
+                onclick: move |_| open.set(false),
+                "Close"
+            }
+        }
+    }
+}
@@ -1,11 +1,17 @@
-use std::str::FromStr;
+use std::{collections::HashSet, str::FromStr};
 
 use arboard::Clipboard;
 use dioxus::prelude::*;
 use schemas::{Fleet, InitialFormation, RelativePosition, Ship};
 use serde::{Deserialize, Serialize};
 
+mod library;
+mod membership_editor;
+mod shape_editor;
+pub mod shapes;
 pub mod swarm;
+mod transform_editor;
+pub mod transforms;
 mod viewer3d;
 
 use crate::{
@@ -14,12 +20,22 @@ use crate::{
         DropdownMenuTrigger,
     },
     fleet_data::FleetData,
+    formation_templates::{SavedFormation, place_escorts},
     system::audio::AUDIO_HANDLER,
     ui::{
         dialog::{DialogWrapper, swarm_config::SwarmConfigDialog},
         fleet_editor::ChevronDown,
-        formations::viewer3d::{Canvas3D, MappedScene, Point3, Scene},
+        formations::{
+            library::FormationLibrary,
+            membership_editor::MembershipEditor,
+            shape_editor::ShapeEditor,
+            shapes::{ShapeConfig, generate_formation},
+            transform_editor::TransformEditor,
+            transforms::Transform,
+            viewer3d::{Canvas3D, MappedScene, Point3, Scene},
+        },
     },
+    util::formation_membership::{clean_formations, set_guide},
 };
 
 #[component]
@@ -36,9 +52,12 @@ pub fn FleetFormationViewer(
     // Formations update -> fleet update gated behind this signal.
     // Enabled when UI makes changes to formations that must be saved.
     let mut fleet_dirty = use_signal(|| false);
+    // Formations as they were before each group edit, by formation index
+    let mut undo_stack = use_signal(Vec::<(usize, Formation)>::new);
     use_effect(move || {
         fleet_data.read();
         fleet_dirty.set(true);
+        undo_stack.write().clear();
     });
     use_effect(move || {
         if !fleet_dirty() {
@@ -129,26 +148,120 @@ pub fn FleetFormationViewer(
         names
     });
 
+    // `HullType` of each escort in the selected formation
+    let escort_hulls = use_memo(move || {
+        let fleet = fleet.read();
+        let formations = formations.read();
+        let (Some(Some(fleet)), Some(formation)) = (
+            fleet.as_ref(),
+            formations
+                .as_ref()
+                .and_then(|formations| formations.get(selected_formation())),
+        ) else {
+            return Vec::new();
+        };
+        let ships = fleet.ships.as_ref().and_then(|ships| ships.ship.as_ref());
+        formation
+            .escorts
+            .iter()
+            .map(|(key, _)| {
+                ships
+                    .into_iter()
+                    .flatten()
+                    .find(|ship| ship.key == *key)
+                    .map(|ship| ship.hull_type.clone())
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>()
+    });
+
+    // Every ship in the fleet as `(key, name, guide key)`
+    let fleet_ships = use_memo(move || {
+        let fleet = fleet.read();
+        let Some(Some(fleet)) = fleet.as_ref() else {
+            return Vec::new();
+        };
+        fleet
+            .ships
+            .as_ref()
+            .and_then(|ships| ships.ship.as_ref())
+            .into_iter()
+            .flatten()
+            .map(|ship| {
+                (
+                    ship.key.clone(),
+                    ship.name.clone(),
+                    ship.initial_formation
+                        .as_ref()
+                        .map(|formation| formation.guide_key.clone()),
+                )
+            })
+            .collect::<Vec<_>>()
+    });
+
     let mut near_point: Signal<Option<usize>> = use_signal(|| None);
     let mut selected_point: Signal<Option<usize>> = use_signal(|| None);
 
+    // While open, the scene previews the formation laid out as a shape
+    let mut show_shape_editor = use_signal(|| false);
+    let shape_config = use_signal(ShapeConfig::default);
+    // While open, the scene previews the transform
+    let mut show_transform_editor = use_signal(|| false);
+    let transform = use_signal(Transform::default);
+    let excluded_escorts = use_signal(HashSet::<String>::new);
+    // While open, the scene previews the selected saved formation
+    let mut show_library = use_signal(|| false);
+    let library_selection = use_signal(|| None::<SavedFormation>);
+    let mut unplaced_escorts = use_signal(|| None::<Vec<String>>);
+    let mut show_members = use_signal(|| false);
+    let mut membership_error = use_signal(|| None::<String>);
+
     let mut scene = use_signal(|| None);
-    let mut old_form_lead = use_signal(String::new);
     use_effect(move || {
         let formations = formations.read();
-        let Some(formations) = formations.as_ref() else {
+        let Some(formation) = formations
+            .as_ref()
+            .and_then(|formations| formations.get(selected_formation()))
+        else {
+            scene.set(None);
             return;
         };
-        let Some(formation) = formations.get(selected_formation()) else {
+        trace!("Updating scene");
+        let mut preview = formation.clone();
+        if show_shape_editor() {
+            // Shapes that can't be laid out aren't previewed
+            let shape_config = shape_config.read();
+            if shape_config.error().is_none() {
+                preview.apply_template(&generate_formation(
+                    &shape_config,
+                    formation.escorts.len(),
+                ));
+            }
+        } else if show_transform_editor() {
+            preview.transform(&transform(), &excluded_escorts.read());
+        } else if show_library() {
+            if let Some(saved) = library_selection.read().as_ref() {
+                preview.place(&place_escorts(saved, &escort_hulls.read()));
+            }
+        }
+        scene.set(Some(formation_to_scene(&preview)));
+    });
+
+    let mut old_form_lead = use_signal(String::new);
+    use_effect(move || {
+        let formations = formations.read();
+        let Some(formations) = formations.as_ref() else {
             return;
         };
-        trace!("Updating scene");
-        if formation.lead_ship != old_form_lead() {
+        // No formation is left to select after the last one is dissolved,
+        // but the fleet still needs writing
+        if let Some(formation) = formations.get(selected_formation())
+            && formation.lead_ship != old_form_lead()
+        {
             selected_point.set(None);
             near_point.set(None);
             old_form_lead.set(formation.lead_ship.clone());
         }
-        scene.set(Some(formation_to_scene(formation)));
 
         trace!("Updating fleet formation");
         let mut fleet = fleet.write();
@@ -156,41 +269,30 @@ pub fn FleetFormationViewer(
             return;
         };
 
-        let mut empty_vec = Vec::new();
-        for formation in formations {
-            for (key, point) in &formation.escorts {
-                let mut matched_ship = None;
-                for ship in fleet
-                    .ships
-                    .as_mut()
-                    .map(|ships| ships.ship.as_mut())
-                    .flatten()
-                    .unwrap_or(&mut empty_vec)
-                {
-                    if &ship.key == key {
-                        matched_ship = Some(ship);
-                        break;
-                    } else if &ship.key == &formation.lead_ship {
-                        ship.initial_formation = None;
-                    }
-                }
-                let Some(ship) = matched_ship else {
-                    warn!(
-                        "Could not find matching ship for formation in fleet"
-                    );
-                    continue;
-                };
-
-                ship.initial_formation = Some(InitialFormation {
+        // Ships in no formation lose any entry they had
+        for ship in fleet
+            .ships
+            .as_mut()
+            .and_then(|ships| ships.ship.as_mut())
+            .into_iter()
+            .flatten()
+        {
+            ship.initial_formation = formations.iter().find_map(|formation| {
+                let (_, point) = formation
+                    .escorts
+                    .iter()
+                    .find(|(key, _)| *key == ship.key)?;
+                Some(InitialFormation {
                     guide_key: formation.lead_ship.clone(),
                     relative_position: RelativePosition {
                         x: point.x / 10.0,
                         y: point.y / 10.0,
                         z: point.z / 10.0,
                     },
-                });
-            }
+                })
+            });
         }
+        clean_formations(fleet);
 
         let fleet_data = fleet_data.read();
         let Some(fleet_data) = fleet_data.as_ref() else {
@@ -276,6 +378,44 @@ pub fn FleetFormationViewer(
         selected_ship.set(ship);
     };
 
+    // Make `escort` follow `guide`, or leave its formation, then show the
+    // formation that changed
+    let mut change_guide = move |escort: String, guide: Option<String>| {
+        let current_lead = formations
+            .peek()
+            .as_ref()
+            .and_then(|formations| formations.get(selected_formation()))
+            .map(|formation| formation.lead_ship.clone());
+        let new_formations = {
+            let mut fleet = fleet.write();
+            let Some(Some(fleet)) = fleet.as_mut() else {
+                return;
+            };
+            if let Err(err) = set_guide(fleet, &escort, guide.as_deref()) {
+                warn!(?err, "Failed to change formation membership");
+                membership_error.set(Some(err.to_string()));
+                return;
+            }
+            get_formations(fleet)
+        };
+        info!("Changed guide of '{}' to {:?}", escort, guide);
+        membership_error.set(None);
+        let lead = guide.or(current_lead);
+        selected_formation.set(
+            new_formations
+                .iter()
+                .position(|formation| {
+                    Some(&formation.lead_ship) == lead.as_ref()
+                })
+                .unwrap_or_default(),
+        );
+        selected_point.set(None);
+        near_point.set(None);
+        // Formation indexes have moved
+        undo_stack.write().clear();
+        formations.set(Some(new_formations));
+    };
+
     let mut show_ctx = use_signal(|| false);
 
     let mut show_swarm_dialog = use_signal(|| false);
@@ -402,15 +542,74 @@ pub fn FleetFormationViewer(
 
                         info!("Importing formation: {:?}", & new_form);
 
-                        for ((_, old_escort), new_escort) in formation
-                            .escorts
-                            .iter_mut()
-                            .zip(new_form.escorts.into_iter())
+                        formation.apply_template(&new_form);
+                    },
+                    "Paste from clipboard"
+                }
+                button {
+                    style: "width: 140px;",
+                    class: "button",
+                    onclick: move |_| {
+                        show_transform_editor.set(false);
+                        show_library.set(false);
+                        show_members.set(false);
+                        show_shape_editor.set(true);
+                    },
+                    "Generate formation"
+                }
+                button {
+                    style: "width: 140px;",
+                    class: "button",
+                    onclick: move |_| {
+                        show_shape_editor.set(false);
+                        show_library.set(false);
+                        show_members.set(false);
+                        show_transform_editor.set(true);
+                    },
+                    "Transform escorts"
+                }
+                button {
+                    style: "width: 140px;",
+                    class: "button",
+                    onclick: move |_| {
+                        show_shape_editor.set(false);
+                        show_transform_editor.set(false);
+                        show_members.set(false);
+                        unplaced_escorts.set(None);
+                        show_library.set(true);
+                    },
+                    "Formation library"
+                }
+                button {
+                    style: "width: 120px;",
+                    class: "button",
+                    onclick: move |_| {
+                        show_shape_editor.set(false);
+                        show_transform_editor.set(false);
+                        show_library.set(false);
+                        membership_error.set(None);
+                        show_members.set(true);
+                    },
+                    "Edit members"
+                }
+                button {
+                    style: "width: 80px;",
+                    class: "button",
+                    disabled: undo_stack.read().is_empty(),
+                    onclick: move |_| {
+                        let Some((idx, previous)) = undo_stack.write().pop() else {
+                            return;
+                        };
+                        let mut formations = formations.write();
+                        if let Some(formation) = formations
+                            .as_mut()
+                            .and_then(|formations| formations.get_mut(idx))
                         {
-                            *old_escort = new_escort.into();
+                            info!("Undoing formation edit");
+                            *formation = previous;
                         }
                     },
-                    "Paste from clipboard"
+                    "Undo"
                 }
             }
         }
@@ -484,6 +683,30 @@ pub fn FleetFormationViewer(
                         },
                         "Compress swarm"
                     }
+                    button {
+                        class: "context-button",
+                        onmouseenter: move |_| AUDIO_HANDLER.play_hover_sound(),
+                        onclick: move |_| {
+                            show_ctx.set(false);
+                            show_transform_editor.set(false);
+                            show_library.set(false);
+                            show_members.set(false);
+                            show_shape_editor.set(true);
+                        },
+                        "Generate formation"
+                    }
+                    button {
+                        class: "context-button",
+                        onmouseenter: move |_| AUDIO_HANDLER.play_hover_sound(),
+                        onclick: move |_| {
+                            show_ctx.set(false);
+                            show_shape_editor.set(false);
+                            show_library.set(false);
+                            show_members.set(false);
+                            show_transform_editor.set(true);
+                        },
+                        "Transform escorts"
+                    }
                     if let Some(selected_point) = selected_point() {
                         if selected_point != 0 {
                             button {
@@ -502,6 +725,47 @@ pub fn FleetFormationViewer(
                                 },
                                 "Make leader"
                             }
+                            button {
+                                class: "context-button",
+                                onmouseenter: move |_| AUDIO_HANDLER.play_hover_sound(),
+                                onclick: move |_| {
+                                    show_ctx.set(false);
+                                    let escort = formations
+                                        .peek()
+                                        .as_ref()
+                                        .and_then(|formations| formations.get(selected_formation()))
+                                        .and_then(|formation| formation.escorts.get(selected_point - 1))
+                                        .map(|(key, _)| key.clone());
+                                    if let Some(escort) = escort {
+                                        change_guide(escort, None);
+                                    }
+                                },
+                                "Detach from formation"
+                            }
+                        } else {
+                            button {
+                                class: "context-button",
+                                onmouseenter: move |_| AUDIO_HANDLER.play_hover_sound(),
+                                onclick: move |_| {
+                                    show_ctx.set(false);
+                                    let escorts = formations
+                                        .peek()
+                                        .as_ref()
+                                        .and_then(|formations| formations.get(selected_formation()))
+                                        .map(|formation| {
+                                            formation
+                                                .escorts
+                                                .iter()
+                                                .map(|(key, _)| key.clone())
+                                                .collect::<Vec<_>>()
+                                        })
+                                        .unwrap_or_default();
+                                    for escort in escorts {
+                                        change_guide(escort, None);
+                                    }
+                                },
+                                "Dissolve formation"
+                            }
                         }
                     }
                 }
@@ -525,6 +789,113 @@ pub fn FleetFormationViewer(
             }
         }
 
+        if show_shape_editor() {
+            ShapeEditor {
+                open: show_shape_editor,
+                config: shape_config,
+                on_apply: move |config: ShapeConfig| {
+                    let mut formations = formations.write();
+                    let Some(formations) = formations.as_mut() else {
+                        return;
+                    };
+                    let Some(formation) = formations.get_mut(selected_formation()) else {
+                        return;
+                    };
+                    info!(?config, "Generating formation");
+                    undo_stack.write().push((selected_formation(), formation.clone()));
+                    let template = generate_formation(&config, formation.escorts.len());
+                    formation.apply_template(&template);
+                },
+            }
+        }
+
+        if show_transform_editor() {
+            {
+                let escorts = formations
+                    .read()
+                    .as_ref()
+                    .and_then(|formations| formations.get(selected_formation()))
+                    .map(|formation| {
+                        formation
+                            .escorts
+                            .iter()
+                            .zip(ship_names.read().iter().skip(1))
+                            .map(|((key, _), name)| (key.clone(), name.clone()))
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                rsx! {
+                    TransformEditor {
+                        open: show_transform_editor,
+                        transform,
+                        escorts,
+                        excluded: excluded_escorts,
+                        on_apply: move |transform: Transform| {
+                            let mut formations = formations.write();
+                            let Some(formations) = formations.as_mut() else {
+                                return;
+                            };
+                            let Some(formation) = formations.get_mut(selected_formation()) else {
+                                return;
+                            };
+                            info!(?transform, "Transforming formation");
+                            undo_stack.write().push((selected_formation(), formation.clone()));
+                            formation.transform(&transform, &excluded_escorts.read());
+                        },
+                    }
+                }
+            }
+        }
+
+        if show_library() {
+            {
+                let current = formations
+                    .read()
+                    .as_ref()
+                    .and_then(|formations| formations.get(selected_formation()))
+                    .map(Formation::to_template)
+                    .unwrap_or(FormationTemplate { escorts: Vec::new() });
+                rsx! {
+                    FormationLibrary {
+                        open: show_library,
+                        selected: library_selection,
+                        current,
+                        escort_hulls: escort_hulls(),
+                        unplaced: unplaced_escorts,
+                        on_apply: move |saved: SavedFormation| {
+                            let positions = place_escorts(&saved, &escort_hulls.read());
+                            let unplaced = positions
+                                .iter()
+                                .zip(ship_names.read().iter().skip(1))
+                                .filter(|(position, _)| position.is_none())
+                                .map(|(_, name)| name.clone())
+                                .collect();
+                            let mut formations = formations.write();
+                            let Some(formations) = formations.as_mut() else {
+                                return;
+                            };
+                            let Some(formation) = formations.get_mut(selected_formation()) else {
+                                return;
+                            };
+                            info!("Applying saved formation '{}'", saved.name);
+                            undo_stack.write().push((selected_formation(), formation.clone()));
+                            formation.place(&positions);
+                            unplaced_escorts.set(Some(unplaced));
+                        },
+                    }
+                }
+            }
+        }
+
+        if show_members() {
+            MembershipEditor {
+                open: show_members,
+                ships: fleet_ships(),
+                error: membership_error,
+                on_change: move |(escort, guide): (String, Option<String>)| change_guide(escort, guide),
+            }
+        }
+
         if let Some(selected_point) = selected_point() {
             if selected_point == 0 {
                 "Ship is leader"
@@ -609,6 +980,34 @@ impl Formation {
         }
         FormationTemplate { escorts: points }
     }
+
+    /// Transform every escort whose key isn't `excluded`.
+    fn transform(&mut self, transform: &Transform, excluded: &HashSet<String>) {
+        for (key, point) in &mut self.escorts {
+            if !excluded.contains(key) {
+                *point = transform.apply((*point).into()).into();
+            }
+        }
+    }
+
+    /// Move each escort to its position, if it has one.
+    fn place(&mut self, positions: &[Option<Point3Serde>]) {
+        for ((_, point), position) in self.escorts.iter_mut().zip(positions) {
+            if let Some(position) = position {
+                *point = (*position).into();
+            }
+        }
+    }
+
+    /// Move the escorts to the template's positions, in order. Escorts
+    /// without a position stay where they are.
+    fn apply_template(&mut self, template: &FormationTemplate) {
+        for ((_, old_escort), new_escort) in
+            self.escorts.iter_mut().zip(template.escorts.iter())
+        {
+            *old_escort = (*new_escort).into();
+        }
+    }
 }
 
 fn get_formations(fleet: &Fleet) -> Vec<Formation> {
@@ -0,0 +1,126 @@
+use std::str::FromStr;
+
+use dioxus::prelude::*;
+
+use crate::ui::formations::shapes::{
+    FormationShape, MAX_GRID_SIZE, ShapeConfig,
+};
+
+/// Settings for laying out a formation as a shape. The formation viewer
+/// previews the shape while `open` is set.
+#[component]
+pub fn ShapeEditor(
+    open: Signal<bool>,
+    config: Signal<ShapeConfig>,
+    on_apply: EventHandler<ShapeConfig>,
+) -> Element {
+    let shape = config.read().shape;
+    let error = config.read().error();
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; width: 50%; gap: 5px;",
+            h4 { style: "margin: 5px 0px 0px", "Generate Formation" }
+            div { style: "display: grid; grid-template-columns: 40% 60%;",
+                "Shape:"
+                select {
+                    onchange: move |evt: Event<FormData>| {
+                        if let Some(shape) = FormationShape::ALL
+                            .into_iter()
+                            .find(|shape| shape.name() == evt.value())
+                        {
+                            config.write().shape = shape;
+                        }
+                    },
+                    for option_shape in FormationShape::ALL {
+                        option {
+                            value: option_shape.name(),
+                            selected: option_shape == shape,
+                            "{option_shape.name()}"
+                        }
+                    }
+                }
+                "Spacing:"
+                input {
+                    value: "{config.read().spacing}",
+                    oninput: move |evt| {
+                        if let Ok(parsed) = f64::from_str(&evt.value()) {
+                            config.write().spacing = parsed;
+                        }
+                    },
+                }
+                if shape.has_sweep() {
+                    "Sweep (degrees):"
+                    input {
+                        value: "{config.read().sweep}",
+                        oninput: move |evt| {
+                            if let Ok(parsed) = f64::from_str(&evt.value()) {
+                                config.write().sweep = parsed;
+                            }
+                        },
+                    }
+                }
+                if shape.has_columns() {
+                    "Columns (0 for auto):"
+                    input {
+                        value: "{config.read().columns}",
+                        oninput: move |evt| {
+                            if let Ok(parsed) = usize::from_str(&evt.value()) {
+                                config.write().columns = parsed.min(MAX_GRID_SIZE);
+                            }
+                        },
+                    }
+                }
+                if shape == FormationShape::LayeredWall {
+                    "Layers:"
+                    input {
+                        value: "{config.read().layers}",
+                        oninput: move |evt| {
+                            if let Ok(parsed) = usize::from_str(&evt.value()) {
+                                config.write().layers = parsed.clamp(1, MAX_GRID_SIZE);
+                            }
+                        },
+                    }
+                }
+                "Heading (degrees):"
+                input {
+                    value: "{config.read().heading}",
+                    oninput: move |evt| {
+                        if let Ok(parsed) = f64::from_str(&evt.value()) {
+                            config.write().heading = parsed;
+                        }
+                    },
+                }
+                "Elevation (degrees):"
+                input {
+                    value: "{config.read().elevation}",
+                    oninput: move |evt| {
+                        if let Ok(parsed) = f64::from_str(&evt.value()) {
+                            config.write().elevation = parsed;
+                        }
+                    },
+                }
+            }
+            if let Some(error) = error {
+                p { class: "search-error", style: "margin: 0px", "{error}" }
+            }
+            div { style: "display: flex; flex-direction: row; gap: 3px;",
+                button {
+                    class: "button",
+                    style: "width: 120px;",
+                    disabled: error.is_some(),
+                    onclick: move |_| {
+                        on_apply.call(config());
+                        open.set(false);
+                    },
+                    "Apply"
+                }
+                button {
+                    class: "button",
+                    style: "width: 120px;",
+                    onclick: move |_| open.set(false),
+                    "Cancel"
+                }
+            }
+        }
+    }
+}
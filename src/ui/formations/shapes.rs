@@ -0,0 +1,398 @@
+//! Escort positions for common formation shapes around the lead ship.
+//!
+//! Shapes are laid out with +Z ahead of the lead, +Y up and +X to the right,
+//! then turned by the heading and elevation.
+
+use std::f64::consts::PI;
+
+use crate::ui::formations::{FormationTemplate, Point3Serde};
+
+/// Most columns or layers a box or wall can have
+pub const MAX_GRID_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormationShape {
+    LineAbreast,
+    Column,
+    Wedge,
+    Echelon,
+    Box,
+    Ring,
+    SphereShell,
+    LayeredWall,
+}
+
+impl FormationShape {
+    pub const ALL: [FormationShape; 8] = [
+        FormationShape::LineAbreast,
+        FormationShape::Column,
+        FormationShape::Wedge,
+        FormationShape::Echelon,
+        FormationShape::Box,
+        FormationShape::Ring,
+        FormationShape::SphereShell,
+        FormationShape::LayeredWall,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FormationShape::LineAbreast => "Line abreast",
+            FormationShape::Column => "Column",
+            FormationShape::Wedge => "Wedge",
+            FormationShape::Echelon => "Echelon",
+            FormationShape::Box => "Box",
+            FormationShape::Ring => "Ring",
+            FormationShape::SphereShell => "Sphere shell",
+            FormationShape::LayeredWall => "Layered wall",
+        }
+    }
+
+    /// Whether the shape's arms can be swept back.
+    pub fn has_sweep(self) -> bool {
+        matches!(self, FormationShape::Wedge | FormationShape::Echelon)
+    }
+
+    /// Whether the shape is a grid with a choice of columns.
+    pub fn has_columns(self) -> bool {
+        matches!(self, FormationShape::Box | FormationShape::LayeredWall)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeConfig {
+    pub shape: FormationShape,
+    /// Distance between neighbouring ships
+    pub spacing: f64,
+    /// Angle between the arms of a wedge or echelon and straight behind the
+    /// lead, in degrees
+    pub sweep: f64,
+    /// Ships across a box or wall, 0 to pick automatically
+    pub columns: usize,
+    /// Layers of a layered wall, one behind the other
+    pub layers: usize,
+    /// Degrees to turn the formation to the right
+    pub heading: f64,
+    /// Degrees to tilt the formation upwards
+    pub elevation: f64,
+}
+
+impl Default for ShapeConfig {
+    fn default() -> Self {
+        ShapeConfig {
+            shape: FormationShape::LineAbreast,
+            spacing: 200.0,
+            sweep: 45.0,
+            columns: 0,
+            layers: 2,
+            heading: 0.0,
+            elevation: 0.0,
+        }
+    }
+}
+
+impl ShapeConfig {
+    /// Why the shape can't be laid out with this config, if it can't. Any of
+    /// these would put ships on top of each other or out of the map.
+    pub fn error(&self) -> Option<&'static str> {
+        let positive = |value: f64| value.is_finite() && value > 0.0;
+        if !positive(self.spacing) {
+            Some("Spacing must be more than 0")
+        } else if self.shape.has_sweep()
+            && !(positive(self.sweep) && self.sweep < 180.0)
+        {
+            Some("Sweep must be between 0 and 180 degrees")
+        } else if !self.heading.is_finite() || !self.elevation.is_finite() {
+            Some("Heading and elevation must be numbers")
+        } else {
+            None
+        }
+    }
+}
+
+/// Positions for `escorts` ships in the configured shape. Check
+/// [`ShapeConfig::error`] first.
+pub fn generate_formation(
+    config: &ShapeConfig,
+    escorts: usize,
+) -> FormationTemplate {
+    let spacing = config.spacing;
+    let sweep = config.sweep.to_radians();
+    // Ships alternate right and left of the lead, working outwards
+    let side = |i: usize| if i.is_multiple_of(2) { 1.0 } else { -1.0 };
+    let rank = |i: usize| (i / 2 + 1) as f64 * spacing;
+
+    let points = match config.shape {
+        FormationShape::LineAbreast => (0..escorts)
+            .map(|i| [side(i) * rank(i), 0.0, 0.0])
+            .collect(),
+        FormationShape::Column => (0..escorts)
+            .map(|i| [0.0, 0.0, -((i + 1) as f64) * spacing])
+            .collect(),
+        FormationShape::Wedge => (0..escorts)
+            .map(|i| {
+                let r = rank(i);
+                [side(i) * r * sweep.sin(), 0.0, -r * sweep.cos()]
+            })
+            .collect(),
+        FormationShape::Echelon => (0..escorts)
+            .map(|i| {
+                let r = (i + 1) as f64 * spacing;
+                [r * sweep.sin(), 0.0, -r * sweep.cos()]
+            })
+            .collect(),
+        FormationShape::Ring => {
+            // Neighbours are `spacing` apart, but never closer to the lead
+            let radius = if escorts > 1 {
+                (spacing / (2.0 * (PI / escorts as f64).sin())).max(spacing)
+            } else {
+                spacing
+            };
+            (0..escorts)
+                .map(|i| {
+                    let angle = 2.0 * PI * i as f64 / escorts as f64;
+                    [radius * angle.sin(), 0.0, radius * angle.cos()]
+                })
+                .collect()
+        }
+        FormationShape::SphereShell => {
+            // Spread evenly over the sphere with a Fibonacci lattice, giving
+            // each ship about `spacing` squared of the surface
+            let n = escorts as f64;
+            let radius = (spacing * (n / (4.0 * PI)).sqrt()).max(spacing);
+            let golden_angle = PI * (3.0 - 5f64.sqrt());
+            (0..escorts)
+                .map(|i| {
+                    let y = 1.0 - 2.0 * (i as f64 + 0.5) / n;
+                    let ring = (1.0 - y * y).sqrt();
+                    let angle = golden_angle * i as f64;
+                    [
+                        radius * ring * angle.sin(),
+                        radius * y,
+                        radius * ring * angle.cos(),
+                    ]
+                })
+                .collect()
+        }
+        FormationShape::Box => {
+            let ships = escorts + 1;
+            let columns = match config.columns.min(MAX_GRID_SIZE) {
+                0 => (ships as f64).cbrt().ceil() as usize,
+                columns => columns,
+            };
+            let layers = ships.div_ceil(columns * columns);
+            grid(ships, [columns, columns, layers], spacing)
+        }
+        FormationShape::LayeredWall => {
+            let ships = escorts + 1;
+            let layers = config.layers.clamp(1, MAX_GRID_SIZE);
+            let per_layer = ships.div_ceil(layers);
+            let columns = match config.columns.min(MAX_GRID_SIZE) {
+                0 => (per_layer as f64).sqrt().ceil() as usize,
+                columns => columns,
+            };
+            let rows = per_layer.div_ceil(columns);
+            grid(ships, [columns, rows, layers], spacing)
+        }
+    };
+
+    FormationTemplate {
+        escorts: points
+            .into_iter()
+            .map(|point| orient(point, config))
+            .collect(),
+    }
+}
+
+/// Fill a grid of `[columns, rows, layers]` with `ships` from the front layer
+/// back. The lead takes the slot nearest the middle and the other slots are
+/// returned relative to it.
+fn grid(
+    ships: usize,
+    [columns, rows, layers]: [usize; 3],
+    spacing: f64,
+) -> Vec<[f64; 3]> {
+    let centre = |idx: usize, count: usize| {
+        (idx as f64 - (count - 1) as f64 / 2.0) * spacing
+    };
+    let mut slots = (0..layers)
+        .flat_map(|layer| {
+            (0..rows).flat_map(move |row| {
+                (0..columns).map(move |column| {
+                    [
+                        centre(column, columns),
+                        centre(row, rows),
+                        -centre(layer, layers),
+                    ]
+                })
+            })
+        })
+        .take(ships)
+        .collect::<Vec<_>>();
+    let distance = |p: &[f64; 3]| p.iter().map(|x| x * x).sum::<f64>();
+    let Some(lead) = (0..slots.len())
+        .min_by(|&a, &b| distance(&slots[a]).total_cmp(&distance(&slots[b])))
+    else {
+        return slots;
+    };
+    let lead = slots.remove(lead);
+    slots
+        .into_iter()
+        .map(|p| [p[0] - lead[0], p[1] - lead[1], p[2] - lead[2]])
+        .collect()
+}
+
+/// Turn a point by the config's elevation then heading, to the nearest
+/// centimetre.
+fn orient([x, y, z]: [f64; 3], config: &ShapeConfig) -> Point3Serde {
+    let (pitch_sin, pitch_cos) = config.elevation.to_radians().sin_cos();
+    let (y, z) = (y * pitch_cos + z * pitch_sin, z * pitch_cos - y * pitch_sin);
+    let (yaw_sin, yaw_cos) = config.heading.to_radians().sin_cos();
+    let (x, z) = (x * yaw_cos + z * yaw_sin, z * yaw_cos - x * yaw_sin);
+    let round = |v: f64| (v * 100.0).round() / 100.0 + 0.0;
+    Point3Serde {
+        x: round(x),
+        y: round(y),
+        z: round(z),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closest_pair(points: &[Point3Serde]) -> f64 {
+        let lead = Point3Serde {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let all = [&[lead], points].concat();
+        let mut closest = f64::MAX;
+        for (i, a) in all.iter().enumerate() {
+            for b in &all[i + 1..] {
+                let dist = ((a.x - b.x).powi(2)
+                    + (a.y - b.y).powi(2)
+                    + (a.z - b.z).powi(2))
+                .sqrt();
+                closest = closest.min(dist);
+            }
+        }
+        closest
+    }
+
+    #[test]
+    fn shapes() {
+        let config = ShapeConfig {
+            spacing: 100.0,
+            ..Default::default()
+        };
+        for shape in FormationShape::ALL {
+            let config = ShapeConfig {
+                shape,
+                ..config.clone()
+            };
+            for escorts in [0, 1, 7, 26] {
+                let template = generate_formation(&config, escorts);
+                assert_eq!(template.escorts.len(), escorts, "{shape:?}");
+                if escorts > 0 {
+                    // Nobody is put on top of anyone else
+                    let closest = closest_pair(&template.escorts);
+                    assert!(closest > 80.0, "{shape:?} {escorts}: {closest}");
+                }
+            }
+        }
+
+        let line = |shape| {
+            let config = ShapeConfig {
+                shape,
+                spacing: 100.0,
+                ..Default::default()
+            };
+            generate_formation(&config, 3)
+                .escorts
+                .iter()
+                .map(|p| (p.x, p.y, p.z))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            line(FormationShape::LineAbreast),
+            [(100.0, 0.0, 0.0), (-100.0, 0.0, 0.0), (200.0, 0.0, 0.0)]
+        );
+        assert_eq!(
+            line(FormationShape::Column),
+            [(0.0, 0.0, -100.0), (0.0, 0.0, -200.0), (0.0, 0.0, -300.0)]
+        );
+        assert_eq!(
+            line(FormationShape::Wedge),
+            [
+                (70.71, 0.0, -70.71),
+                (-70.71, 0.0, -70.71),
+                (141.42, 0.0, -141.42)
+            ]
+        );
+
+        // Huge grids are clamped rather than overflowing
+        for shape in [FormationShape::Box, FormationShape::LayeredWall] {
+            let config = ShapeConfig {
+                shape,
+                spacing: 100.0,
+                columns: usize::MAX,
+                layers: usize::MAX,
+                ..Default::default()
+            };
+            assert_eq!(generate_formation(&config, 7).escorts.len(), 7);
+        }
+
+        // A column heading right trails off to the left
+        let turned = ShapeConfig {
+            shape: FormationShape::Column,
+            spacing: 100.0,
+            heading: 90.0,
+            ..Default::default()
+        };
+        let first = generate_formation(&turned, 1).escorts[0];
+        assert_eq!((first.x, first.y, first.z), (-100.0, 0.0, 0.0));
+        let climbing = ShapeConfig {
+            heading: 0.0,
+            elevation: 90.0,
+            ..turned
+        };
+        let first = generate_formation(&climbing, 1).escorts[0];
+        assert_eq!((first.x, first.y, first.z), (0.0, -100.0, 0.0));
+    }
+
+    #[test]
+    fn invalid_configs() {
+        let wedge = ShapeConfig {
+            shape: FormationShape::Wedge,
+            ..Default::default()
+        };
+        assert_eq!(wedge.error(), None);
+        for spacing in [0.0, -100.0, f64::NAN, f64::INFINITY] {
+            let config = ShapeConfig {
+                spacing,
+                ..wedge.clone()
+            };
+            assert!(config.error().is_some(), "{spacing}");
+        }
+        for sweep in [0.0, -45.0, 180.0, f64::NAN] {
+            let config = ShapeConfig {
+                sweep,
+                ..wedge.clone()
+            };
+            assert!(config.error().is_some(), "{sweep}");
+        }
+        // Shapes without arms don't use the sweep
+        let column = ShapeConfig {
+            shape: FormationShape::Column,
+            sweep: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(column.error(), None);
+        let tilted = ShapeConfig {
+            elevation: f64::NAN,
+            ..column
+        };
+        assert!(tilted.error().is_some());
+    }
+}
@@ -0,0 +1,175 @@
+use std::{collections::HashSet, str::FromStr};
+
+use dioxus::prelude::*;
+
+use crate::ui::formations::transforms::Transform;
+
+/// Settings for moving a group of escorts at once. The formation viewer
+/// previews the transform while `open` is set.
+///
+/// `escorts` are the formation's escorts as `(key, name)`, and `excluded`
+/// holds the keys of those the transform leaves alone.
+#[component]
+pub fn TransformEditor(
+    open: Signal<bool>,
+    transform: Signal<Transform>,
+    escorts: Vec<(String, String)>,
+    excluded: Signal<HashSet<String>>,
+    on_apply: EventHandler<Transform>,
+) -> Element {
+    let current = transform();
+
+    rsx! {
+        div { style: "display: flex; flex-direction: column; width: 50%; gap: 5px;",
+            h4 { style: "margin: 5px 0px 0px", "Transform Escorts" }
+            div { style: "display: grid; grid-template-columns: 40% 60%;",
+                "Transform:"
+                select {
+                    onchange: move |evt: Event<FormData>| {
+                        if let Some(new) = Transform::named(&evt.value()) {
+                            transform.set(new);
+                        }
+                    },
+                    for name in Transform::NAMES {
+                        option {
+                            value: name,
+                            selected: current.name() == name,
+                            "{name}"
+                        }
+                    }
+                }
+                match current {
+                    Transform::Rotate { axis, degrees } => rsx! {
+                        VectorInputs {
+                            label: "Axis",
+                            value: axis,
+                            on_change: move |axis| transform.set(Transform::Rotate { axis, degrees }),
+                        }
+                        "Degrees:"
+                        input {
+                            value: "{degrees}",
+                            oninput: move |evt| {
+                                if let Ok(degrees) = f64::from_str(&evt.value()) {
+                                    transform.set(Transform::Rotate { axis, degrees });
+                                }
+                            },
+                        }
+                    },
+                    Transform::Mirror { normal } => rsx! {
+                        VectorInputs {
+                            label: "Plane normal",
+                            value: normal,
+                            on_change: move |normal| transform.set(Transform::Mirror { normal }),
+                        }
+                    },
+                    Transform::Scale { factors } => {
+                        let uniform = if factors[0] == factors[1] && factors[1] == factors[2] {
+                            factors[0].to_string()
+                        } else {
+                            String::new()
+                        };
+                        rsx! {
+                            "Uniform:"
+                            input {
+                                value: "{uniform}",
+                                oninput: move |evt| {
+                                    if let Ok(factor) = f64::from_str(&evt.value()) {
+                                        transform
+                                            .set(Transform::Scale {
+                                                factors: [factor; 3],
+                                            });
+                                    }
+                                },
+                            }
+                            VectorInputs {
+                                label: "Factor",
+                                value: factors,
+                                on_change: move |factors| transform.set(Transform::Scale { factors }),
+                            }
+                        }
+                    }
+                    Transform::Snap { grid } => rsx! {
+                        "Grid size:"
+                        input {
+                            value: "{grid}",
+                            oninput: move |evt| {
+                                if let Ok(grid) = f64::from_str(&evt.value()) {
+                                    transform.set(Transform::Snap { grid });
+                                }
+                            },
+                        }
+                    },
+                    Transform::Translate { offset } => rsx! {
+                        VectorInputs {
+                            label: "Offset",
+                            value: offset,
+                            on_change: move |offset| transform.set(Transform::Translate { offset }),
+                        }
+                    },
+                }
+            }
+            p { style: "margin: 0px", "Apply to:" }
+            div { style: "display: flex; flex-direction: column; max-height: 20vh; overflow-y: auto;",
+                for (key , name) in escorts {
+                    label {
+                        input {
+                            r#type: "checkbox",
+                            checked: !excluded.read().contains(&key),
+                            onchange: {
+                                let key = key.clone();
+                                move |_| {
+                                    let mut excluded = excluded.write();
+                                    if !excluded.remove(&key) {
+                                        excluded.insert(key.clone());
+                                    }
+                                }
+                            },
+                        }
+                        "{name}"
+                    }
+                }
+            }
+            div { style: "display: flex; flex-direction: row; gap: 3px;",
+                button {
+                    class: "button",
+                    style: "width: 120px;",
+                    onclick: move |_| {
+                        on_apply.call(transform());
+                        open.set(false);
+                    },
+                    "Apply"
+                }
+                button {
+                    class: "button",
+                    style: "width: 120px;",
+                    onclick: move |_| open.set(false),
+                    "Cancel"
+                }
+            }
+        }
+    }
+}
+
+/// X, Y and Z inputs for a vector, as rows of a two column grid.
+#[component]
+fn VectorInputs(
+    label: &'static str,
+    value: [f64; 3],
+    on_change: EventHandler<[f64; 3]>,
+) -> Element {
+    rsx! {
+        for (axis , axis_name) in ["X", "Y", "Z"].into_iter().enumerate() {
+            "{label} {axis_name}:"
+            input {
+                value: "{value[axis]}",
+                oninput: move |evt| {
+                    if let Ok(parsed) = f64::from_str(&evt.value()) {
+                        let mut value = value;
+                        value[axis] = parsed;
+                        on_change.call(value);
+                    }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Group transforms for the escorts of a formation. Rotations, mirrors and
+//! scales are centred on the lead ship.
+
+use crate::ui::formations::Point3Serde;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    /// Turn about `axis` by `degrees`, clockwise looking along the axis
+    Rotate {
+        axis: [f64; 3],
+        degrees: f64,
+    },
+    /// Reflect across the plane through the lead with this normal
+    Mirror {
+        normal: [f64; 3],
+    },
+    /// Multiply each axis by a factor
+    Scale {
+        factors: [f64; 3],
+    },
+    /// Round each axis to the nearest multiple of `grid`
+    Snap {
+        grid: f64,
+    },
+    Translate {
+        offset: [f64; 3],
+    },
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::Rotate {
+            axis: [0.0, 1.0, 0.0],
+            degrees: 0.0,
+        }
+    }
+}
+
+impl Transform {
+    pub const NAMES: [&str; 5] =
+        ["Rotate", "Mirror", "Scale", "Snap to grid", "Translate"];
+
+    /// The transform called `name` in [`Transform::NAMES`]. Apart from
+    /// mirroring left to right, it does nothing until its settings change.
+    pub fn named(name: &str) -> Option<Transform> {
+        Some(match name {
+            "Rotate" => Transform::default(),
+            "Mirror" => Transform::Mirror {
+                normal: [1.0, 0.0, 0.0],
+            },
+            "Scale" => Transform::Scale {
+                factors: [1.0, 1.0, 1.0],
+            },
+            "Snap to grid" => Transform::Snap { grid: 0.0 },
+            "Translate" => Transform::Translate {
+                offset: [0.0, 0.0, 0.0],
+            },
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transform::Rotate { .. } => Transform::NAMES[0],
+            Transform::Mirror { .. } => Transform::NAMES[1],
+            Transform::Scale { .. } => Transform::NAMES[2],
+            Transform::Snap { .. } => Transform::NAMES[3],
+            Transform::Translate { .. } => Transform::NAMES[4],
+        }
+    }
+
+    pub fn apply(&self, point: Point3Serde) -> Point3Serde {
+        let p = [point.x, point.y, point.z];
+        let [x, y, z] = match *self {
+            Transform::Rotate { axis, degrees } => {
+                let Some(k) = normalise(axis) else {
+                    return point;
+                };
+                // Rodrigues' rotation formula
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                let k_cross_p = [
+                    k[1] * p[2] - k[2] * p[1],
+                    k[2] * p[0] - k[0] * p[2],
+                    k[0] * p[1] - k[1] * p[0],
+                ];
+                let k_dot_p = dot(k, p);
+                [0, 1, 2].map(|i| {
+                    p[i] * cos
+                        + k_cross_p[i] * sin
+                        + k[i] * k_dot_p * (1.0 - cos)
+                })
+            }
+            Transform::Mirror { normal } => {
+                let Some(n) = normalise(normal) else {
+                    return point;
+                };
+                let distance = dot(n, p);
+                [0, 1, 2].map(|i| p[i] - 2.0 * distance * n[i])
+            }
+            Transform::Scale { factors } => {
+                [0, 1, 2].map(|i| p[i] * factors[i])
+            }
+            Transform::Snap { grid } if grid > 0.0 => {
+                p.map(|v| (v / grid).round() * grid)
+            }
+            Transform::Snap { .. } => p,
+            Transform::Translate { offset } => {
+                [0, 1, 2].map(|i| p[i] + offset[i])
+            }
+        };
+        Point3Serde { x, y, z }
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalise(v: [f64; 3]) -> Option<[f64; 3]> {
+    let length = dot(v, v).sqrt();
+    (length > f64::EPSILON).then(|| v.map(|x| x / length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64, z: f64) -> Point3Serde {
+        Point3Serde { x, y, z }
+    }
+
+    fn rounded(p: Point3Serde) -> (f64, f64, f64) {
+        let round = |v: f64| (v * 1000.0).round() / 1000.0 + 0.0;
+        (round(p.x), round(p.y), round(p.z))
+    }
+
+    #[test]
+    fn transforms() {
+        let p = point(100.0, 20.0, -50.0);
+        let apply = |transform: Transform| rounded(transform.apply(p));
+
+        for name in Transform::NAMES {
+            let transform = Transform::named(name).unwrap();
+            assert_eq!(transform.name(), name);
+            if name != "Mirror" {
+                assert_eq!(apply(transform), (100.0, 20.0, -50.0));
+            }
+        }
+
+        let rotate = |axis, degrees| apply(Transform::Rotate { axis, degrees });
+        assert_eq!(rotate([0.0, 1.0, 0.0], 90.0), (-50.0, 20.0, -100.0));
+        assert_eq!(rotate([0.0, 0.0, 2.0], 180.0), (-100.0, -20.0, -50.0));
+        assert_eq!(rotate([1.0, 1.0, 1.0], 120.0), (-50.0, 100.0, 20.0));
+        assert_eq!(rotate([0.0; 3], 90.0), (100.0, 20.0, -50.0));
+
+        let mirror = |normal| apply(Transform::Mirror { normal });
+        assert_eq!(mirror([1.0, 0.0, 0.0]), (-100.0, 20.0, -50.0));
+        assert_eq!(mirror([0.0, -3.0, 0.0]), (100.0, -20.0, -50.0));
+
+        let scale = Transform::Scale {
+            factors: [2.0, 0.5, -1.0],
+        };
+        assert_eq!(apply(scale), (200.0, 10.0, 50.0));
+        assert_eq!(apply(Transform::Snap { grid: 75.0 }), (75.0, 0.0, -75.0));
+        let translate = Transform::Translate {
+            offset: [-100.0, 5.0, 0.0],
+        };
+        assert_eq!(apply(translate), (0.0, 25.0, -50.0));
+    }
+}
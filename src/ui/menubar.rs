@@ -14,6 +14,8 @@ pub struct Menubars {
     pub fleets_reload: MenuItem,
     pub fleets_clear_cache: MenuItem,
     pub fleets_backup: MenuItem,
+    pub fleets_export_searches: MenuItem,
+    pub fleets_import_searches: MenuItem,
 
     // Edit
     pub edit_preferences: MenuItem,
@@ -23,6 +25,12 @@ pub struct Menubars {
     pub tools_menu: Submenu,
     pub tools_winpred: MenuItem,
     pub tools_merge: MenuItem,
+    pub tools_tags: MenuItem,
+    pub tools_auto_tag: MenuItem,
+    pub tools_export_tags: MenuItem,
+    pub tools_hull_template: MenuItem,
+    pub tools_hull_batch: MenuItem,
+    pub tools_formation_batch: MenuItem,
 
     // Help
     pub help_menu: Submenu,
@@ -42,11 +50,25 @@ impl Menubars {
         );
         let fleets_backup =
             MenuItem::with_id("fleets-backup", "Backup Fleets", true, None);
+        let fleets_export_searches = MenuItem::with_id(
+            "fleets-export-searches",
+            "Export Saved Searches",
+            true,
+            None,
+        );
+        let fleets_import_searches = MenuItem::with_id(
+            "fleets-import-searches",
+            "Import Saved Searches",
+            true,
+            None,
+        );
         fleets_menu
             .append_items(&[
                 &fleets_reload,
                 &fleets_clear_cache,
                 &fleets_backup,
+                &fleets_export_searches,
+                &fleets_import_searches,
             ])
             .unwrap();
 
@@ -60,8 +82,45 @@ impl Menubars {
             MenuItem::with_id("tools-winpred", "Win Predictor", true, None);
         let tools_merge =
             MenuItem::with_id("tools-merge", "Merge Fleets", true, None);
+        let tools_tags =
+            MenuItem::with_id("tools-tags", "Manage Tags", true, None);
+        let tools_auto_tag =
+            MenuItem::with_id("tools-auto-tag", "Apply Auto Tags", true, None);
+        let tools_export_tags = MenuItem::with_id(
+            "tools-export-tags",
+            "Write Tags to Descriptions",
+            true,
+            None,
+        );
+        let tools_hull_template = MenuItem::with_id(
+            "tools-hull-template",
+            "Apply Hull Template",
+            true,
+            None,
+        );
+        let tools_hull_batch = MenuItem::with_id(
+            "tools-hull-batch",
+            "Batch Edit Liner Hulls",
+            true,
+            None,
+        );
+        let tools_formation_batch = MenuItem::with_id(
+            "tools-formation-batch",
+            "Apply Formation to Fleets",
+            true,
+            None,
+        );
         tools_menu
-            .append_items(&[&tools_winpred, &tools_merge])
+            .append_items(&[
+                &tools_winpred,
+                &tools_merge,
+                &tools_tags,
+                &tools_auto_tag,
+                &tools_export_tags,
+                &tools_hull_template,
+                &tools_hull_batch,
+                &tools_formation_batch,
+            ])
             .unwrap();
 
         let help_menu = Submenu::new("Help", true);
@@ -74,11 +133,19 @@ impl Menubars {
             fleets_reload,
             fleets_clear_cache,
             fleets_backup,
+            fleets_export_searches,
+            fleets_import_searches,
             edit_menu,
             edit_preferences,
             tools_menu,
             tools_winpred,
             tools_merge,
+            tools_tags,
+            tools_auto_tag,
+            tools_export_tags,
+            tools_hull_template,
+            tools_hull_batch,
+            tools_formation_batch,
             help_menu,
             help_open_log,
         }
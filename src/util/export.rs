@@ -12,7 +12,14 @@ const LN_CONFIG_PREFIX: &'static str = "LNCONFIG:";
 const FORMATION_PREFIX: &'static str = "FORM:";
 
 pub fn export_hull_config(config: &EditableHullParams) -> Result<String> {
-    let ser = ser_to_hex(config)?;
+    let base = HullParamsV1::from(config);
+    // Optional fields are written in order, so without stern dressings the
+    // paint can't be written either
+    let ser = match (&config.stern_dressings, &config.paint) {
+        (Some(stern), Some(paint)) => ser_to_hex(&(base, stern, paint))?,
+        (Some(stern), None) => ser_to_hex(&(base, stern))?,
+        (None, _) => ser_to_hex(&base)?,
+    };
     Ok(format!("{LN_CONFIG_PREFIX}{ser}"))
 }
 
@@ -20,7 +27,62 @@ pub fn import_hull_config(s: &str) -> Result<EditableHullParams> {
     let Some(hex) = s.strip_prefix(LN_CONFIG_PREFIX) else {
         bail!("String not a valid ln config");
     };
-    hex_to_ser(hex)
+    let bytes = hex_to_bytes(hex)?;
+
+    // Fields added after the first version come last and are optional, so
+    // older configs still import
+    let (base, rest) = postcard::take_from_bytes::<HullParamsV1>(&bytes)?;
+    let mut params = EditableHullParams::from(base);
+    if !rest.is_empty() {
+        let (stern_dressings, rest) = postcard::take_from_bytes(rest)?;
+        params.stern_dressings = Some(stern_dressings);
+        if !rest.is_empty() {
+            let (paint, _) = postcard::take_from_bytes(rest)?;
+            params.paint = Some(paint);
+        }
+    }
+    Ok(params)
+}
+
+/// Fields every ln config starts with
+#[derive(Deserialize, Serialize)]
+struct HullParamsV1 {
+    bow_type: usize,
+    core_type: usize,
+    stern_type: usize,
+    superstructure_loc: usize,
+    superstructure_type: usize,
+    bow_dressings: [u8; 8],
+    core_dressings: [u8; 8],
+}
+
+impl From<HullParamsV1> for EditableHullParams {
+    fn from(params: HullParamsV1) -> Self {
+        EditableHullParams {
+            bow_type: params.bow_type,
+            core_type: params.core_type,
+            stern_type: params.stern_type,
+            superstructure_loc: params.superstructure_loc,
+            superstructure_type: params.superstructure_type,
+            bow_dressings: params.bow_dressings,
+            core_dressings: params.core_dressings,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&EditableHullParams> for HullParamsV1 {
+    fn from(params: &EditableHullParams) -> Self {
+        HullParamsV1 {
+            bow_type: params.bow_type,
+            core_type: params.core_type,
+            stern_type: params.stern_type,
+            superstructure_loc: params.superstructure_loc,
+            superstructure_type: params.superstructure_type,
+            bow_dressings: params.bow_dressings,
+            core_dressings: params.core_dressings,
+        }
+    }
 }
 
 pub fn export_formation(formation: &FormationTemplate) -> Result<String> {
@@ -73,6 +135,9 @@ fn hex_to_ser<T>(s: &str) -> Result<T>
 where
     T: for<'a> Deserialize<'a>,
 {
+    Ok(postcard::from_bytes(&hex_to_bytes(s)?)?)
+}
+fn hex_to_bytes(s: &str) -> Result<Vec<u8>> {
     let mut real = String::new();
     let mut iter = s.chars();
     while let Some(char) = iter.next() {
@@ -98,17 +163,23 @@ where
             .wrap_err("Invalid hex code")?;
         bytes.push(value);
     }
-    Ok(postcard::from_bytes(&bytes)?)
+    Ok(bytes)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
     use crate::{
         export::{
             export_formation, export_hull_config, import_formation,
             import_hull_config,
         },
-        fleet_edit::EditableHullParams,
+        fleet_edit::{
+            EditableHullParams, HullPaint, get_ln_editable_hull_params,
+            set_ln_hull_config,
+        },
+        system::fleet_io::read_fleet,
         ui::formations::{FormationTemplate, Point3Serde},
     };
 
@@ -122,11 +193,108 @@ mod tests {
             superstructure_type: 1,
             bow_dressings: [1; 8],
             core_dressings: [1; 8],
+            stern_dressings: Some([1; 8]),
+            paint: Some(HullPaint {
+                tint: [100, 110, 120, 255],
+                texture_variation: [0.25, 0.5, 0.0],
+            }),
         };
         let ser = export_hull_config(&params).unwrap();
         let des = import_hull_config(&ser).unwrap();
-
         assert_eq!(params, des);
+
+        let unpainted = EditableHullParams {
+            paint: None,
+            ..params
+        };
+        let ser = export_hull_config(&unpainted).unwrap();
+        assert_eq!(import_hull_config(&ser).unwrap(), unpainted);
+
+        let undressed = EditableHullParams {
+            stern_dressings: None,
+            ..unpainted
+        };
+        let ser = export_hull_config(&undressed).unwrap();
+        assert_eq!(import_hull_config(&ser).unwrap(), undressed);
+    }
+
+    #[test]
+    fn ln_import_older_configs() {
+        // Shared before stern dressings were added
+        let des = import_hull_config(
+            "LNCONFIG:010101010101010101010101010101010101010101",
+        )
+        .unwrap();
+        assert_eq!(des.bow_dressings, [1; 8]);
+        assert_eq!(des.stern_dressings, None);
+
+        // Shared before hull paint was added
+        let des = import_hull_config(
+            "LNCONFIG:0101010101010101010101010101010101010101010202020202020202",
+        )
+        .unwrap();
+        assert_eq!(des.stern_dressings, Some([2; 8]));
+        assert_eq!(des.paint, None);
+    }
+
+    #[test]
+    fn ln_paste_older_config() {
+        let fleet = read_fleet(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/golden/liner-escort.fleet"),
+        )
+        .unwrap();
+        let ship = &fleet.ships.as_ref().unwrap().ship.as_ref().unwrap()[0];
+        let params = get_ln_editable_hull_params(ship).unwrap();
+
+        // Pasting a config copied before hull paint was added keeps the
+        // ship's tint and texture
+        let mut pasted = ship.clone();
+        let old = import_hull_config(
+            "LNCONFIG:0101010101010101010101010101010101010101010202020202020202",
+        )
+        .unwrap();
+        set_ln_hull_config(&mut pasted, old).unwrap();
+        let pasted_params = get_ln_editable_hull_params(&pasted).unwrap();
+        assert_eq!(pasted_params.paint, params.paint);
+        assert_eq!(
+            pasted.hull_config.as_ref().unwrap().hull_tint,
+            ship.hull_config.as_ref().unwrap().hull_tint
+        );
+
+        // Pasting a config copied before stern dressings were added keeps the
+        // ship's stern dressings, unless the stern is replaced
+        let mut dressed = ship.clone();
+        let stern_dressings = Some([0, 2, 0, 0, 0, 0, 0, 0]);
+        set_ln_hull_config(
+            &mut dressed,
+            EditableHullParams {
+                stern_dressings,
+                ..params.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(params.stern_type, 2);
+        let mut pasted = dressed.clone();
+        let same_stern = import_hull_config(&format!(
+            "LNCONFIG:0101020101{}",
+            "01".repeat(16)
+        ))
+        .unwrap();
+        assert_eq!(same_stern.stern_dressings, None);
+        set_ln_hull_config(&mut pasted, same_stern).unwrap();
+        let pasted_params = get_ln_editable_hull_params(&pasted).unwrap();
+        assert_eq!(pasted_params.stern_dressings, stern_dressings);
+        assert_eq!(pasted_params.bow_dressings, [1; 8]);
+
+        let mut pasted = dressed.clone();
+        let other_stern = import_hull_config(
+            "LNCONFIG:010101010101010101010101010101010101010101",
+        )
+        .unwrap();
+        set_ln_hull_config(&mut pasted, other_stern).unwrap();
+        let pasted_params = get_ln_editable_hull_params(&pasted).unwrap();
+        assert_eq!(pasted_params.stern_dressings, Some([0; 8]));
     }
 
     #[test]
@@ -0,0 +1,285 @@
+//! Apply a saved formation around a chosen lead ship in many fleets at once.
+
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{
+    Report, Result,
+    eyre::{Context, bail},
+};
+use glob::{MatchOptions, Pattern};
+use schemas::{Fleet, InitialFormation, RelativePosition};
+use tracing::{info, warn};
+
+use crate::{
+    formation_templates::{SavedFormation, place_escorts},
+    system::fleet_io::{read_fleet, write_fleet},
+};
+
+/// How to pick the lead ship in each fleet.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LeadMatch {
+    /// The first ship with a name matching a glob pattern such as `Flag*`,
+    /// ignoring case
+    Name(String),
+    /// The first ship with this `HullType`
+    Hull(String),
+}
+
+impl Display for LeadMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeadMatch::Name(pattern) => write!(f, "named '{pattern}'"),
+            LeadMatch::Hull(hull_type) => write!(f, "with hull '{hull_type}'"),
+        }
+    }
+}
+
+/// What applying a formation did to one fleet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormationAssignment {
+    pub lead: String,
+    /// Whether the lead had no formation before
+    pub created: bool,
+    pub placed: Vec<String>,
+    /// Escorts the formation had no slot for. Those already in the lead's
+    /// formation keep their old position, others stay out of formation.
+    pub unplaced: Vec<String>,
+}
+
+/// Fleets that were rewritten with what changed, and those that failed with
+/// the reason.
+pub type AppliedFormations =
+    (Vec<(PathBuf, FormationAssignment)>, Vec<(PathBuf, Report)>);
+
+/// Apply `formation` around the lead ship in `fleet`. The escorts are the
+/// ships already following the lead and any ships in no formation at all.
+pub fn apply_formation_to_fleet(
+    fleet: &mut Fleet,
+    formation: &SavedFormation,
+    lead: &LeadMatch,
+) -> Result<FormationAssignment> {
+    let name_pattern = match lead {
+        LeadMatch::Name(pattern) => {
+            Some(Pattern::new(pattern).wrap_err_with(|| {
+                format!("Invalid name pattern '{pattern}'")
+            })?)
+        }
+        LeadMatch::Hull(_) => None,
+    };
+    let options = MatchOptions {
+        case_sensitive: false,
+        ..Default::default()
+    };
+    let Some(ships) =
+        fleet.ships.as_mut().and_then(|ships| ships.ship.as_mut())
+    else {
+        bail!("The fleet has no ships");
+    };
+    let Some(lead_idx) = ships.iter().position(|ship| match lead {
+        LeadMatch::Name(_) => name_pattern
+            .as_ref()
+            .is_some_and(|pattern| pattern.matches_with(&ship.name, options)),
+        LeadMatch::Hull(hull_type) => ship.hull_type == *hull_type,
+    }) else {
+        bail!("No ship {lead}");
+    };
+    let lead_key = ships[lead_idx].key.clone();
+
+    // Ships leading a formation can't be moved into another one
+    let guides = ships
+        .iter()
+        .filter_map(|ship| ship.initial_formation.as_ref())
+        .map(|formation| formation.guide_key.clone())
+        .collect::<HashSet<_>>();
+    let escorts = (0..ships.len())
+        .filter(|&idx| idx != lead_idx)
+        .filter(|&idx| match &ships[idx].initial_formation {
+            Some(formation) => formation.guide_key == lead_key,
+            None => !guides.contains(&ships[idx].key),
+        })
+        .collect::<Vec<_>>();
+    let hulls = escorts
+        .iter()
+        .map(|&idx| ships[idx].hull_type.clone())
+        .collect::<Vec<_>>();
+    let positions = place_escorts(formation, &hulls);
+
+    let mut assignment = FormationAssignment {
+        lead: ships[lead_idx].name.clone(),
+        created: !guides.contains(&lead_key),
+        placed: Vec::new(),
+        unplaced: Vec::new(),
+    };
+    // The lead leaves any formation it was following
+    ships[lead_idx].initial_formation = None;
+    for (idx, position) in escorts.into_iter().zip(positions) {
+        let ship = &mut ships[idx];
+        let Some(position) = position else {
+            assignment.unplaced.push(ship.name.clone());
+            continue;
+        };
+        ship.initial_formation = Some(InitialFormation {
+            guide_key: lead_key.clone(),
+            relative_position: RelativePosition {
+                x: position.x / 10.0,
+                y: position.y / 10.0,
+                z: position.z / 10.0,
+            },
+        });
+        assignment.placed.push(ship.name.clone());
+    }
+    Ok(assignment)
+}
+
+/// Apply `formation` around the lead ship of each fleet in `paths`.
+pub fn apply_formation_batch(
+    formation: &SavedFormation,
+    lead: &LeadMatch,
+    paths: &[PathBuf],
+) -> AppliedFormations {
+    let mut changed = Vec::new();
+    let mut failed = Vec::new();
+    for path in paths {
+        match apply_to_file(formation, lead, path) {
+            Ok(assignment) => changed.push((path.clone(), assignment)),
+            Err(err) => {
+                warn!(?err, "Failed to apply formation to fleet");
+                failed.push((path.clone(), err));
+            }
+        }
+    }
+    info!(
+        "Applied formation '{}' to {} fleets",
+        formation.name,
+        changed.len()
+    );
+    (changed, failed)
+}
+
+fn apply_to_file(
+    formation: &SavedFormation,
+    lead: &LeadMatch,
+    path: &Path,
+) -> Result<FormationAssignment> {
+    let mut fleet = read_fleet(path)?;
+    let assignment = apply_formation_to_fleet(&mut fleet, formation, lead)?;
+    write_fleet(path, &fleet)?;
+    Ok(assignment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::formations::{FormationTemplate, Point3Serde};
+
+    const GOLDEN_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    fn positions(fleet: &Fleet) -> Vec<(String, Option<(String, f64)>)> {
+        fleet
+            .ships
+            .iter()
+            .flat_map(|ships| ships.ship.iter().flatten())
+            .map(|ship| {
+                let formation = ship
+                    .initial_formation
+                    .as_ref()
+                    .map(|f| (f.guide_key.clone(), f.relative_position.x));
+                (ship.name.clone(), formation)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batch_formations() {
+        let path = std::env::temp_dir().join(format!(
+            "nebtools-{}-formation-batch.fleet",
+            std::process::id()
+        ));
+        std::fs::copy(
+            Path::new(GOLDEN_DIR).join("corvette-screen.fleet"),
+            &path,
+        )
+        .unwrap();
+        let fleet = read_fleet(&path).unwrap();
+        let ships = fleet.ships.as_ref().unwrap().ship.as_ref().unwrap();
+        let lead_key = ships[0].key.clone();
+
+        let point = |x| Point3Serde { x, y: 0.0, z: 0.0 };
+        let screen = SavedFormation {
+            name: "Screen".to_string(),
+            description: String::new(),
+            template: FormationTemplate {
+                escorts: vec![point(100.0), point(-200.0)],
+            },
+            hulls: vec![
+                "Stock/Raines Frigate".to_string(),
+                "Stock/Sprinter Corvette".to_string(),
+            ],
+        };
+
+        let missing = LeadMatch::Name("Flagship".to_string());
+        let (changed, failed) = apply_formation_batch(
+            &screen,
+            &missing,
+            std::slice::from_ref(&path),
+        );
+        assert_eq!((changed.len(), failed.len()), (0, 1));
+        assert_eq!(read_fleet(&path).unwrap(), fleet);
+
+        // The existing formation is rewritten, with escorts in their slots
+        let sprinters = LeadMatch::Hull("Stock/Sprinter Corvette".to_string());
+        let (changed, failed) = apply_formation_batch(
+            &screen,
+            &sprinters,
+            std::slice::from_ref(&path),
+        );
+        assert!(failed.is_empty());
+        let assignment = &changed[0].1;
+        assert_eq!(assignment.lead, "Sprinter 1");
+        assert!(!assignment.created);
+        assert_eq!(assignment.placed, ["Sprinter 2", "Raines"]);
+        assert_eq!(
+            positions(&read_fleet(&path).unwrap()),
+            [
+                ("Sprinter 1".to_string(), None),
+                ("Sprinter 2".to_string(), Some((lead_key.clone(), -20.0))),
+                ("Raines".to_string(), Some((lead_key, 10.0))),
+            ]
+        );
+
+        // A new formation takes in the ships that aren't in one
+        let mut unformed = fleet.clone();
+        for ship in unformed.ships.as_mut().unwrap().ship.as_mut().unwrap() {
+            ship.initial_formation = None;
+        }
+        let single = SavedFormation {
+            template: FormationTemplate {
+                escorts: vec![point(-200.0)],
+            },
+            hulls: vec!["Stock/Sprinter Corvette".to_string()],
+            ..screen
+        };
+        let raines = LeadMatch::Name("rain*".to_string());
+        let assignment =
+            apply_formation_to_fleet(&mut unformed, &single, &raines).unwrap();
+        assert!(assignment.created);
+        assert_eq!(assignment.placed, ["Sprinter 1"]);
+        assert_eq!(assignment.unplaced, ["Sprinter 2"]);
+        let raines_key = ships[2].key.clone();
+        assert_eq!(
+            positions(&unformed),
+            [
+                ("Sprinter 1".to_string(), Some((raines_key, -20.0))),
+                ("Sprinter 2".to_string(), None),
+                ("Raines".to_string(), None),
+            ]
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
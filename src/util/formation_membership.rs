@@ -0,0 +1,188 @@
+//! Which ships follow which in a fleet's formations. A formation only exists
+//! through its escorts' `InitialFormation`, so a guide with no escorts leads
+//! nothing.
+
+use std::collections::HashSet;
+
+use color_eyre::{Result, eyre::bail};
+use schemas::{Fleet, InitialFormation, RelativePosition, Ship};
+use tracing::warn;
+
+/// Distance behind the rearmost escort that a newly attached escort is
+/// placed, in fleet file units
+const NEW_ESCORT_SPACING: f64 = 20.0;
+
+fn ships_mut(fleet: &mut Fleet) -> &mut [Ship] {
+    fleet
+        .ships
+        .as_mut()
+        .and_then(|ships| ships.ship.as_mut())
+        .map(Vec::as_mut_slice)
+        .unwrap_or_default()
+}
+
+/// Make the ship with key `escort` follow `guide`, or leave its formation if
+/// `guide` is `None`. An escort moving to a new guide is placed behind that
+/// guide's other escorts, while one staying with its guide keeps its place.
+///
+/// Ships leading a formation can't follow another ship, and ships following
+/// another can't lead one.
+pub fn set_guide(
+    fleet: &mut Fleet,
+    escort: &str,
+    guide: Option<&str>,
+) -> Result<()> {
+    let ships = ships_mut(fleet);
+    let Some(escort_idx) = ships.iter().position(|ship| ship.key == escort)
+    else {
+        bail!("No ship with key '{escort}' in the fleet");
+    };
+    let Some(guide) = guide else {
+        ships[escort_idx].initial_formation = None;
+        return Ok(());
+    };
+
+    let Some(guide_ship) = ships.iter().find(|ship| ship.key == guide) else {
+        bail!("No ship with key '{guide}' in the fleet");
+    };
+    if guide == escort {
+        bail!("'{}' can't follow itself", guide_ship.name);
+    }
+    if guide_ship.initial_formation.is_some() {
+        bail!(
+            "'{}' follows another ship, so it can't lead a formation",
+            guide_ship.name
+        );
+    }
+    let escort_ship = &ships[escort_idx];
+    if ships.iter().any(|ship| {
+        ship.initial_formation
+            .as_ref()
+            .is_some_and(|formation| formation.guide_key == escort)
+    }) {
+        bail!(
+            "'{}' leads a formation, so it can't follow another ship",
+            escort_ship.name
+        );
+    }
+    if escort_ship
+        .initial_formation
+        .as_ref()
+        .is_some_and(|formation| formation.guide_key == guide)
+    {
+        return Ok(());
+    }
+
+    let rearmost = ships
+        .iter()
+        .filter_map(|ship| ship.initial_formation.as_ref())
+        .filter(|formation| formation.guide_key == guide)
+        .map(|formation| formation.relative_position.z)
+        .fold(0.0, f64::min);
+    ships[escort_idx].initial_formation = Some(InitialFormation {
+        guide_key: guide.to_string(),
+        relative_position: RelativePosition {
+            x: 0.0,
+            y: 0.0,
+            z: rearmost - NEW_ESCORT_SPACING,
+        },
+    });
+    Ok(())
+}
+
+/// Remove formation entries that follow a ship missing from the fleet, or the
+/// ship itself. Returns the names of the ships taken out of formation.
+pub fn clean_formations(fleet: &mut Fleet) -> Vec<String> {
+    let ships = ships_mut(fleet);
+    let keys = ships
+        .iter()
+        .map(|ship| ship.key.clone())
+        .collect::<HashSet<_>>();
+    let mut cleaned = Vec::new();
+    for ship in ships {
+        let Some(formation) = &ship.initial_formation else {
+            continue;
+        };
+        if formation.guide_key == ship.key
+            || !keys.contains(&formation.guide_key)
+        {
+            warn!(
+                "Removing '{}' from formation with invalid guide '{}'",
+                ship.name, formation.guide_key
+            );
+            ship.initial_formation = None;
+            cleaned.push(ship.name.clone());
+        }
+    }
+    cleaned
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::system::fleet_io::read_fleet;
+
+    const GOLDEN_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    fn guides(fleet: &Fleet) -> Vec<Option<(String, f64)>> {
+        fleet
+            .ships
+            .iter()
+            .flat_map(|ships| ships.ship.iter().flatten())
+            .map(|ship| {
+                ship.initial_formation
+                    .as_ref()
+                    .map(|f| (f.guide_key.clone(), f.relative_position.z))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn editing_membership() {
+        let mut fleet =
+            read_fleet(Path::new(GOLDEN_DIR).join("corvette-screen.fleet"))
+                .unwrap();
+        let keys = fleet.ships.as_ref().unwrap().ship.as_ref().unwrap()[..3]
+            .iter()
+            .map(|ship| ship.key.clone())
+            .collect::<Vec<_>>();
+        let [lead, sprinter, raines] = [&keys[0], &keys[1], &keys[2]];
+        let before = guides(&fleet);
+
+        // Chains of guides and self-guides are refused
+        assert!(set_guide(&mut fleet, raines, Some(sprinter)).is_err());
+        assert!(set_guide(&mut fleet, lead, Some(raines)).is_err());
+        assert!(set_guide(&mut fleet, raines, Some(raines)).is_err());
+        assert!(set_guide(&mut fleet, "missing", None).is_err());
+        // Staying with the same guide keeps the escort's place
+        set_guide(&mut fleet, raines, Some(lead)).unwrap();
+        assert_eq!(guides(&fleet), before);
+
+        // Detach both escorts, then start a new formation around Raines
+        set_guide(&mut fleet, sprinter, None).unwrap();
+        set_guide(&mut fleet, raines, None).unwrap();
+        assert_eq!(guides(&fleet), [None, None, None]);
+        set_guide(&mut fleet, lead, Some(raines)).unwrap();
+        set_guide(&mut fleet, sprinter, Some(raines)).unwrap();
+        assert_eq!(
+            guides(&fleet),
+            [
+                Some((raines.clone(), -20.0)),
+                Some((raines.clone(), -40.0)),
+                None
+            ]
+        );
+
+        // Entries following missing ships or themselves are removed
+        let ships = fleet.ships.as_mut().unwrap().ship.as_mut().unwrap();
+        ships[0].initial_formation.as_mut().unwrap().guide_key =
+            "missing".to_string();
+        ships[1].initial_formation.as_mut().unwrap().guide_key =
+            sprinter.clone();
+        assert_eq!(clean_formations(&mut fleet), ["Sprinter 1", "Sprinter 2"]);
+        assert_eq!(guides(&fleet), [None, None, None]);
+    }
+}
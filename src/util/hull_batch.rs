@@ -0,0 +1,370 @@
+//! Apply a liner hull config, or some parts of it, to every matching liner in
+//! many fleets at once.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{
+    Report, Result,
+    eyre::{Context, bail, eyre},
+};
+use schemas::Fleet;
+use tracing::{info, warn};
+
+use crate::{
+    fleet_edit::{
+        EditableHullParams, LinerHull, SEGMENT_NAMES,
+        get_ln_editable_hull_params, set_ln_hull_config, variant_label,
+    },
+    system::{
+        backup::{backup_fleet, batch_backup_dir},
+        fleet_io::{read_fleet, write_fleet},
+    },
+};
+
+/// Parts of a hull config that a batch edit can change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HullPart {
+    Bow,
+    Core,
+    Stern,
+    Bridge,
+    Dressings,
+    Paint,
+}
+
+impl HullPart {
+    pub const ALL: [HullPart; 6] = [
+        HullPart::Bow,
+        HullPart::Core,
+        HullPart::Stern,
+        HullPart::Bridge,
+        HullPart::Dressings,
+        HullPart::Paint,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HullPart::Bow => "Bow",
+            HullPart::Core => "Core",
+            HullPart::Stern => "Stern",
+            HullPart::Bridge => "Bridge",
+            HullPart::Dressings => "Dressings",
+            HullPart::Paint => "Paint",
+        }
+    }
+}
+
+/// A batch edit: the `parts` of `params` to copy to every liner with
+/// `hull_type`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HullBatch {
+    pub hull_type: String,
+    pub params: EditableHullParams,
+    pub parts: Vec<HullPart>,
+    /// Keys of the ships to change, by fleet path. Every liner with
+    /// `hull_type` is changed if `None`, while a chosen ship with another
+    /// hull fails its fleet.
+    pub ships: Option<HashMap<PathBuf, Vec<String>>>,
+}
+
+impl HullBatch {
+    /// `current` with this batch's parts copied in. A segment that changes
+    /// type loses its dressings unless dressings are copied too.
+    pub fn apply_to(&self, current: &EditableHullParams) -> EditableHullParams {
+        let mut out = current.clone();
+        let from = &self.params;
+        for (segment, part) in [HullPart::Bow, HullPart::Core, HullPart::Stern]
+            .into_iter()
+            .enumerate()
+        {
+            if !self.parts.contains(&part)
+                || from.segment_type(segment) == out.segment_type(segment)
+            {
+                continue;
+            }
+            match segment {
+                0 => out.bow_type = from.bow_type,
+                1 => out.core_type = from.core_type,
+                _ => out.stern_type = from.stern_type,
+            }
+            *out.dressings_mut(segment) = Default::default();
+        }
+        if self.parts.contains(&HullPart::Bridge) {
+            out.superstructure_type = from.superstructure_type;
+            out.superstructure_loc = from.superstructure_loc;
+        }
+        if self.parts.contains(&HullPart::Dressings) {
+            out.bow_dressings = from.bow_dressings;
+            out.core_dressings = from.core_dressings;
+            if from.stern_dressings.is_some() {
+                out.stern_dressings = from.stern_dressings;
+            }
+        }
+        if self.parts.contains(&HullPart::Paint) && from.paint.is_some() {
+            out.paint = from.paint;
+        }
+        out
+    }
+
+    /// Apply the batch to every matching liner in `fleet`, stored at `path`,
+    /// returning what changed. Nothing is changed if any liner can't take the
+    /// new config.
+    fn apply_to_fleet(
+        &self,
+        fleet: &mut Fleet,
+        path: &Path,
+    ) -> Result<Vec<ShipHullChange>> {
+        let keys = self.ships.as_ref().map(|ships| {
+            ships.get(path).map(Vec::as_slice).unwrap_or_default()
+        });
+        let mut edited = fleet.clone();
+        let mut changes = Vec::new();
+        let ships = edited
+            .ships
+            .iter_mut()
+            .flat_map(|ships| ships.ship.iter_mut().flatten());
+        for ship in ships {
+            match keys {
+                Some(keys) if !keys.contains(&ship.key) => continue,
+                Some(_) if ship.hull_type != self.hull_type => bail!(
+                    "'{}' is a {}, but the hull is for a {}",
+                    ship.name,
+                    ship.hull_type,
+                    self.hull_type
+                ),
+                None if ship.hull_type != self.hull_type => continue,
+                _ => {}
+            }
+            let before =
+                get_ln_editable_hull_params(ship).ok_or_else(|| {
+                    eyre!("Couldn't read the hull of '{}'", ship.name)
+                })?;
+            let after = self.apply_to(&before);
+            if after == before {
+                continue;
+            }
+            LinerHull::of(ship)
+                .ok_or_else(|| eyre!("'{}' isn't a liner", ship.name))?
+                .validate(&after)
+                .wrap_err_with(|| {
+                    format!("'{}' can't take the new hull", ship.name)
+                })?;
+            set_ln_hull_config(ship, after.clone()).ok_or_else(|| {
+                eyre!("Couldn't change the hull of '{}'", ship.name)
+            })?;
+            changes.push(ShipHullChange {
+                name: ship.name.clone(),
+                before,
+                after,
+            });
+        }
+        *fleet = edited;
+        Ok(changes)
+    }
+}
+
+/// How a batch edit changes one ship.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShipHullChange {
+    pub name: String,
+    pub before: EditableHullParams,
+    pub after: EditableHullParams,
+}
+
+impl ShipHullChange {
+    /// The parts that change, e.g. `bow A → B, paint`.
+    pub fn describe(&self) -> String {
+        let (before, after) = (&self.before, &self.after);
+        let mut parts = Vec::new();
+        for (segment, name) in SEGMENT_NAMES.iter().enumerate() {
+            let (from, to) =
+                (before.segment_type(segment), after.segment_type(segment));
+            if from != to {
+                parts.push(format!(
+                    "{name} {} → {}",
+                    variant_label(from),
+                    variant_label(to)
+                ));
+            }
+        }
+        let bridge = |params: &EditableHullParams| {
+            format!(
+                "{} on the {}",
+                variant_label(params.superstructure_type),
+                SEGMENT_NAMES
+                    .get(params.superstructure_loc)
+                    .copied()
+                    .unwrap_or("unknown segment")
+            )
+        };
+        if bridge(before) != bridge(after) {
+            parts.push(format!(
+                "bridge {} → {}",
+                bridge(before),
+                bridge(after)
+            ));
+        }
+        if (0..3).any(|segment| {
+            before.dressings(segment) != after.dressings(segment)
+        }) {
+            parts.push("dressings".to_string());
+        }
+        if before.paint != after.paint {
+            parts.push("paint".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+/// What a batch edit would change in each fleet, without writing anything.
+pub fn preview_hull_batch(
+    batch: &HullBatch,
+    paths: &[PathBuf],
+) -> Vec<(PathBuf, Result<Vec<ShipHullChange>>)> {
+    paths
+        .iter()
+        .map(|path| {
+            let changes = read_fleet(path)
+                .and_then(|mut fleet| batch.apply_to_fleet(&mut fleet, path));
+            (path.clone(), changes)
+        })
+        .collect()
+}
+
+/// Fleets that were rewritten, those that failed with the reason, and the
+/// folder the rewritten fleets were backed up to.
+pub type HullBatchResult =
+    (Vec<PathBuf>, Vec<(PathBuf, Report)>, Option<PathBuf>);
+
+/// Apply a batch edit, backing up each fleet before it is rewritten.
+pub fn apply_hull_batch(
+    batch: &HullBatch,
+    paths: &[PathBuf],
+) -> HullBatchResult {
+    let mut changed = Vec::new();
+    let mut failed = Vec::new();
+    let backup_dir = match batch_backup_dir() {
+        Ok(dir) => dir,
+        Err(err) => {
+            let failed = paths
+                .iter()
+                .map(|path| (path.clone(), eyre!("{err}")))
+                .collect();
+            return (changed, failed, None);
+        }
+    };
+    for path in paths {
+        match apply_to_file(batch, path, &backup_dir) {
+            Ok(true) => changed.push(path.clone()),
+            Ok(false) => {}
+            Err(err) => {
+                warn!(?err, "Failed to apply hull batch to fleet");
+                failed.push((path.clone(), err));
+            }
+        }
+    }
+    if changed.is_empty() {
+        // Nothing was backed up, so don't leave an empty folder behind
+        let _ = std::fs::remove_dir(&backup_dir);
+        return (changed, failed, None);
+    }
+    info!(
+        "Rewrote {} fleets, backed up to '{}'",
+        changed.len(),
+        backup_dir.display()
+    );
+    (changed, failed, Some(backup_dir))
+}
+
+fn apply_to_file(
+    batch: &HullBatch,
+    path: &Path,
+    backup_dir: &Path,
+) -> Result<bool> {
+    let mut fleet = read_fleet(path)?;
+    if batch.apply_to_fleet(&mut fleet, path)?.is_empty() {
+        return Ok(false);
+    }
+    backup_fleet(path, backup_dir)?;
+    write_fleet(path, &fleet)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOLDEN_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    #[test]
+    fn partial_batches() {
+        let golden = Path::new(GOLDEN_DIR).join("liner-escort.fleet");
+        let name = format!("nebtools-{}-hull-batch.fleet", std::process::id());
+        let path = std::env::temp_dir().join(&name);
+        std::fs::copy(&golden, &path).unwrap();
+        let fleet = read_fleet(&path).unwrap();
+        let ships = fleet.ships.as_ref().unwrap().ship.as_ref().unwrap();
+        let bulk = get_ln_editable_hull_params(&ships[0]).unwrap();
+
+        let mut params = bulk.clone();
+        params.bow_type = 0;
+        params.core_type = 2;
+        params.paint.as_mut().unwrap().tint = [0, 0, 0, 255];
+        let batch = HullBatch {
+            hull_type: ships[0].hull_type.clone(),
+            params,
+            parts: vec![HullPart::Bow],
+            ships: None,
+        };
+
+        // Only the bow changes, and its dressings are cleared
+        let after = batch.apply_to(&bulk);
+        assert_eq!(after.bow_type, 0);
+        assert_eq!(after.bow_dressings, [0; 8]);
+        assert_eq!(
+            (after.core_type, after.paint),
+            (bulk.core_type, bulk.paint)
+        );
+
+        let preview = preview_hull_batch(&batch, std::slice::from_ref(&path));
+        let changes = preview[0].1.as_ref().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].describe(), "bow B → A, dressings");
+        // Previewing writes nothing
+        assert_eq!(read_fleet(&path).unwrap(), fleet);
+
+        // Bow C can't carry a bridge
+        let mut bridge_on_bow = bulk.clone();
+        bridge_on_bow.bow_type = 2;
+        bridge_on_bow.superstructure_loc = 0;
+        let invalid = HullBatch {
+            params: bridge_on_bow,
+            parts: vec![HullPart::Bow, HullPart::Bridge],
+            ..batch.clone()
+        };
+        assert!(
+            preview_hull_batch(&invalid, std::slice::from_ref(&path))[0]
+                .1
+                .is_err()
+        );
+
+        let backup_dir = std::env::temp_dir()
+            .join(format!("nebtools-{}-hull-batch", std::process::id()));
+        let _ = std::fs::remove_dir_all(&backup_dir);
+        let mut edited = read_fleet(&path).unwrap();
+        batch.apply_to_fleet(&mut edited, &path).unwrap();
+        assert!(apply_to_file(&batch, &path, &backup_dir).unwrap());
+        assert_eq!(read_fleet(&path).unwrap(), edited);
+        let backup = backup_dir.join(name);
+        assert_eq!(read_fleet(&backup).unwrap(), fleet);
+        // Nothing left to change
+        assert!(!apply_to_file(&batch, &path, &backup_dir).unwrap());
+
+        std::fs::remove_dir_all(backup_dir).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use color_eyre::{Result, eyre::bail};
+use schemas::Fleet;
+use tracing::{debug, warn};
+
+/// Merge the ships, missiles, craft and mod dependencies of several fleets
+/// into a single new fleet called `name`.
+pub fn merge_fleets(name: String, fleets: Vec<Fleet>) -> Result<Fleet> {
+    let mut out = Fleet {
+        xmlns_xsd: "".to_string(),
+        xmlns_xsi: "".to_string(),
+        text: None,
+        name,
+        description: Some(format!(
+            "Merged fleets:\n{}",
+            fleets
+                .iter()
+                .map(|f| format!(" - {}", f.name))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )),
+        version: "".to_string(),
+        total_points: 0,
+        faction_key: "".to_string(),
+        sort_override_order: None,
+        ships: Some(schemas::Ships {
+            text: None,
+            ship: Some(Vec::new()),
+        }),
+        missile_types: Some(schemas::MissileTypes {
+            text: None,
+            missile_template: Some(Vec::new()),
+        }),
+        craft_types: Some(schemas::CraftTypes {
+            text: None,
+            craft_template: Some(Vec::new()),
+        }),
+        mod_dependencies: Some(schemas::ModDependencies {
+            unsigned_long: Some(Vec::new()),
+        }),
+    };
+    for fleet in fleets {
+        debug!("Pulling data from '{}'", fleet.name);
+        if out.xmlns_xsd.is_empty() {
+            out.xmlns_xsd = fleet.xmlns_xsd;
+        }
+        if out.xmlns_xsi.is_empty() {
+            out.xmlns_xsi = fleet.xmlns_xsi;
+        }
+        if out.text.is_none() && fleet.text.is_some() {
+            out.text = fleet.text;
+        }
+        if out.version.is_empty() {
+            out.version = fleet.version;
+        } else if out.version != fleet.version {
+            warn!(
+                "Merging fleets made in different fleet editor versions. This \
+                 may cause issues"
+            );
+        }
+        out.total_points += fleet.total_points;
+        if out.faction_key.is_empty() {
+            out.faction_key = fleet.faction_key;
+        } else if out.faction_key != fleet.faction_key {
+            bail!("Merging fleets of different factions");
+        }
+        for ship in fleet
+            .ships
+            .map(|ships| ships.ship)
+            .flatten()
+            .unwrap_or_default()
+        {
+            out.ships
+                .as_mut()
+                .unwrap()
+                .ship
+                .as_mut()
+                .unwrap()
+                .push(ship);
+        }
+        for missile in fleet
+            .missile_types
+            .map(|missile_types| missile_types.missile_template)
+            .flatten()
+            .unwrap_or_default()
+        {
+            out.missile_types
+                .as_mut()
+                .unwrap()
+                .missile_template
+                .as_mut()
+                .unwrap()
+                .push(missile);
+        }
+        for craft in fleet
+            .craft_types
+            .map(|craft_types| craft_types.craft_template)
+            .flatten()
+            .unwrap_or_default()
+        {
+            out.craft_types
+                .as_mut()
+                .unwrap()
+                .craft_template
+                .as_mut()
+                .unwrap()
+                .push(craft);
+        }
+        for mod_dep in fleet
+            .mod_dependencies
+            .map(|mod_dep| mod_dep.unsigned_long)
+            .flatten()
+            .unwrap_or_default()
+        {
+            out.mod_dependencies
+                .as_mut()
+                .unwrap()
+                .unsigned_long
+                .as_mut()
+                .unwrap()
+                .push(mod_dep);
+        }
+    }
+
+    Ok(out)
+}
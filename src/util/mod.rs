@@ -1,3 +1,7 @@
 pub mod export;
+pub mod formation_batch;
+pub mod formation_membership;
+pub mod hull_batch;
+pub mod merge;
 pub mod search;
 pub mod spawn_async;
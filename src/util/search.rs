@@ -1,51 +1,756 @@
+//! Search queries for the fleet list and the `search` command.
+//!
+//! Words and quoted phrases match a fleet's name, path or description. Terms
+//! can be restricted to a single field with `field:value`, fleet and ship
+//! counts compared with e.g. `points>=2500`, and terms combined with `AND`,
+//! `OR`, `NOT` (in any case) and parentheses. Terms next to each other must
+//! all match.
+//!
+//! Fleet and ship names also match words with a typo or two. Matching fleets
+//! are ranked so that name matches come before ship, path and description
+//! matches.
+
+use std::{cmp::Reverse, fmt::Display, ops::Range};
+
+use chumsky::{error::SimpleReason, prelude::*};
+
 use crate::fleet_data::FleetData;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextField {
+    Name,
+    Description,
+    Path,
+    Faction,
+    Hull,
+    Ship,
+    Missile,
+    Mod,
+    Tag,
+}
+
+impl TextField {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "name" => Self::Name,
+            "desc" | "description" => Self::Description,
+            "path" => Self::Path,
+            "faction" => Self::Faction,
+            "hull" => Self::Hull,
+            "ship" => Self::Ship,
+            "missile" => Self::Missile,
+            "mod" => Self::Mod,
+            "tag" => Self::Tag,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberField {
+    Points,
+    Ships,
+}
+
+impl NumberField {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "points" => Self::Points,
+            "ships" => Self::Ships,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn compare(self, lhs: usize, rhs: usize) -> bool {
+        match self {
+            Self::Eq => lhs == rhs,
+            Self::Lt => lhs < rhs,
+            Self::Le => lhs <= rhs,
+            Self::Gt => lhs > rhs,
+            Self::Ge => lhs >= rhs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Query {
+    /// Matches every fleet, from an empty search.
+    All,
+    /// Text in the name, path or description.
+    Text(String),
+    Field(TextField, String),
+    Number(NumberField, Comparison, usize),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    /// A term that couldn't be understood, reported once parsing finishes.
+    Invalid(SearchError),
+}
+
+/// How relevant each kind of match is. Fuzzy matches score less the more
+/// typos they need.
+const NAME_SCORE: u32 = 100;
+const NAME_PREFIX_BONUS: u32 = 20;
+const SHIP_SCORE: u32 = 40;
+const PATH_SCORE: u32 = 20;
+const DESCRIPTION_SCORE: u32 = 10;
+const FIELD_SCORE: u32 = 10;
+const TYPO_PENALTY: u32 = 15;
+
+/// Abbreviations players use for the stock factions.
+const FACTION_ALIASES: [(&str, &str); 2] =
+    [("osp", "protectorate"), ("ans", "alliance")];
+
+/// Whether `faction_key` names the faction `text`, which is lowercase and may
+/// be an abbreviation.
+pub fn faction_matches(faction_key: &str, text: &str) -> bool {
+    let faction = FACTION_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == text)
+        .map_or(text, |(_, faction)| faction);
+    faction_key.to_lowercase().contains(faction)
+}
+
+/// The parts of a fleet a query is matched against, lowercased.
+struct Haystack {
+    /// One lowercase character per character of the name, so match positions
+    /// line up with [`FleetData::name`].
+    name: Vec<char>,
+    path: Vec<char>,
+    description: String,
+    ship_names: Vec<Vec<char>>,
+    tags: Vec<String>,
+}
+
+/// A fleet matching a search, and how well it matches.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchMatch {
+    /// Higher is more relevant.
+    pub score: u32,
+    /// Character ranges of [`FleetData::name`] that matched.
+    pub name_spans: Vec<Range<usize>>,
+    /// Character ranges of [`FleetData::short_path`] that matched.
+    pub path_spans: Vec<Range<usize>>,
+}
+
+impl SearchMatch {
+    fn scored(score: u32) -> Self {
+        SearchMatch {
+            score,
+            ..Default::default()
+        }
+    }
+
+    fn merge(mut self, other: SearchMatch) -> Self {
+        self.score += other.score;
+        self.name_spans.extend(other.name_spans);
+        self.path_spans.extend(other.path_spans);
+        self
+    }
+}
+
+fn lowercase_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// Every non-overlapping occurrence of `needle` in `haystack`.
+fn find_all(haystack: &[char], needle: &[char]) -> Vec<Range<usize>> {
+    let mut found = Vec::new();
+    if needle.is_empty() {
+        return found;
+    }
+    let mut start = 0;
+    while start + needle.len() <= haystack.len() {
+        if haystack[start..].starts_with(needle) {
+            found.push(start..start + needle.len());
+            start += needle.len();
+        } else {
+            start += 1;
+        }
+    }
+    found
+}
+
+/// Number of single character insertions, deletions, substitutions or swaps
+/// of adjacent characters needed to turn `a` into `b`.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution =
+                rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = substitution.min(rows[i - 1][j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Find `term` in `text`, allowing typos within a single word. Returns the
+/// number of typos and the matching character ranges.
+fn fuzzy_find(
+    text: &[char],
+    term: &[char],
+) -> Option<(u32, Vec<Range<usize>>)> {
+    let exact = find_all(text, term);
+    if !exact.is_empty() {
+        return Some((0, exact));
+    }
+    // Short words have too many near misses, and phrases are only matched
+    // exactly
+    let max_typos = match term.len() {
+        0..4 => return None,
+        4..8 => 1,
+        _ => 2,
+    };
+    if term.iter().any(|c| !c.is_alphanumeric()) {
+        return None;
+    }
+
+    let mut best: Option<(usize, Vec<Range<usize>>)> = None;
+    let mut start = 0;
+    for (idx, c) in text.iter().chain([&' ']).enumerate() {
+        if c.is_alphanumeric() {
+            continue;
+        }
+        let word = start..idx;
+        start = idx + 1;
+        if word.is_empty() {
+            continue;
+        }
+        // Also compare against the start of longer words, so a typo in a
+        // partly typed word still matches
+        let prefix = word.start..word.end.min(word.start + term.len());
+        let typos = edit_distance(term, &text[word.clone()])
+            .min(edit_distance(term, &text[prefix]));
+        match &mut best {
+            _ if typos > max_typos => {}
+            Some((best_typos, spans)) if *best_typos == typos => {
+                spans.push(word)
+            }
+            Some((best_typos, _)) if *best_typos < typos => {}
+            _ => best = Some((typos, vec![word])),
+        }
+    }
+    best.map(|(typos, spans)| (typos as u32, spans))
+}
+
+fn fuzzy_score(score: u32, typos: u32) -> u32 {
+    score.saturating_sub(typos * TYPO_PENALTY).max(1)
+}
+
+fn search_name(haystack: &Haystack, term: &[char]) -> Option<SearchMatch> {
+    let (typos, name_spans) = fuzzy_find(&haystack.name, term)?;
+    let prefix_bonus = if typos == 0 && haystack.name.starts_with(term) {
+        NAME_PREFIX_BONUS
+    } else {
+        0
+    };
+    Some(SearchMatch {
+        score: fuzzy_score(NAME_SCORE, typos) + prefix_bonus,
+        name_spans,
+        path_spans: Vec::new(),
+    })
+}
+
+fn search_ships(haystack: &Haystack, term: &[char]) -> Option<SearchMatch> {
+    let typos = haystack
+        .ship_names
+        .iter()
+        .filter_map(|name| fuzzy_find(name, term))
+        .map(|(typos, _)| typos)
+        .min()?;
+    Some(SearchMatch::scored(fuzzy_score(SHIP_SCORE, typos)))
+}
+
+fn search_path(haystack: &Haystack, term: &[char]) -> Option<SearchMatch> {
+    let path_spans = find_all(&haystack.path, term);
+    (!path_spans.is_empty()).then(|| SearchMatch {
+        score: PATH_SCORE,
+        name_spans: Vec::new(),
+        path_spans,
+    })
+}
+
+fn search_description(haystack: &Haystack, text: &str) -> Option<SearchMatch> {
+    haystack
+        .description
+        .contains(text)
+        .then(|| SearchMatch::scored(DESCRIPTION_SCORE))
+}
+
+impl Query {
+    fn search(
+        &self,
+        fleet: &FleetData,
+        haystack: &Haystack,
+    ) -> Option<SearchMatch> {
+        let any_contains = |values: &[String], needle: &str| {
+            values
+                .iter()
+                .any(|value| value.to_lowercase().contains(needle))
+        };
+        let field_match =
+            |matched: bool| matched.then(|| SearchMatch::scored(FIELD_SCORE));
+        match self {
+            Query::All => Some(SearchMatch::default()),
+            Query::Text(text) => {
+                let term = text.chars().collect::<Vec<_>>();
+                [
+                    search_name(haystack, &term),
+                    search_ships(haystack, &term),
+                    search_path(haystack, &term),
+                    search_description(haystack, text),
+                ]
+                .into_iter()
+                .flatten()
+                .max_by_key(|found| found.score)
+            }
+            Query::Field(field, text) => {
+                let term = text.chars().collect::<Vec<_>>();
+                match field {
+                    TextField::Name => search_name(haystack, &term),
+                    TextField::Description => {
+                        search_description(haystack, text)
+                    }
+                    TextField::Path => search_path(haystack, &term),
+                    TextField::Faction => {
+                        field_match(faction_matches(&fleet.faction_key, text))
+                    }
+                    TextField::Hull => {
+                        field_match(any_contains(&fleet.hull_types, text))
+                    }
+                    TextField::Ship => search_ships(haystack, &term),
+                    TextField::Missile => {
+                        field_match(any_contains(&fleet.missile_names, text))
+                    }
+                    TextField::Mod => {
+                        field_match(any_contains(&fleet.mod_dependencies, text))
+                    }
+                    TextField::Tag => field_match(
+                        haystack
+                            .tags
+                            .iter()
+                            .any(|tag| crate::tags::tag_is_under(tag, text)),
+                    ),
+                }
+            }
+            Query::Number(field, comparison, value) => {
+                let lhs = match field {
+                    NumberField::Points => fleet.total_points,
+                    NumberField::Ships => fleet.ship_count,
+                };
+                field_match(comparison.compare(lhs, *value))
+            }
+            Query::Not(query) => match query.search(fleet, haystack) {
+                Some(_) => None,
+                None => Some(SearchMatch::default()),
+            },
+            Query::And(lhs, rhs) => Some(
+                lhs.search(fleet, haystack)?
+                    .merge(rhs.search(fleet, haystack)?),
+            ),
+            Query::Or(lhs, rhs) => {
+                match (lhs.search(fleet, haystack), rhs.search(fleet, haystack))
+                {
+                    (Some(lhs), Some(rhs)) => {
+                        let score = lhs.score.max(rhs.score);
+                        Some(SearchMatch {
+                            score,
+                            ..lhs.merge(rhs)
+                        })
+                    }
+                    (lhs, rhs) => lhs.or(rhs),
+                }
+            }
+            Query::Invalid(_) => None,
+        }
+    }
+
+    /// The first invalid term in the query, if any.
+    fn error(&self) -> Option<&SearchError> {
+        match self {
+            Query::Invalid(err) => Some(err),
+            Query::Not(query) => query.error(),
+            Query::And(lhs, rhs) | Query::Or(lhs, rhs) => {
+                lhs.error().or_else(|| rhs.error())
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct SearchFilters {
-    contains: Vec<String>,
-    tags: Vec<String>,
+    query: Query,
 }
 impl SearchFilters {
     pub fn matches(&self, fleet_data: &FleetData) -> bool {
-        if self.contains.is_empty() && self.tags.is_empty() {
-            return true;
+        self.search(fleet_data).is_some()
+    }
+
+    /// Match a fleet against the search, returning how well it matches.
+    pub fn search(&self, fleet_data: &FleetData) -> Option<SearchMatch> {
+        if self.query == Query::All {
+            return Some(SearchMatch::default());
         }
-        let (tags, desc) = crate::tags::get_tags_from_description(
+        let (_, desc) = crate::tags::get_tags_from_description(
             fleet_data.description.as_str(),
-        )
-        .unwrap();
-
-        (self.contains.is_empty()
-            || self.contains.iter().any(|c| {
-                fleet_data.name.to_lowercase().contains(c)
-                    || fleet_data
-                        .short_path
-                        .display()
-                        .to_string()
-                        .to_lowercase()
-                        .contains(c)
-                    || desc.to_lowercase().contains(c)
-            }))
-            && (self.tags.is_empty()
-                || self.tags.iter().any(|tag_match| {
-                    tags.iter().any(|tag| &tag.name.to_lowercase() == tag_match)
-                }))
+        );
+        let tags = crate::tags::fleet_tags(fleet_data);
+
+        let haystack = Haystack {
+            name: lowercase_chars(&fleet_data.name),
+            path: lowercase_chars(&fleet_data.short_path.display().to_string()),
+            description: desc.to_lowercase(),
+            ship_names: fleet_data
+                .ship_names
+                .iter()
+                .map(|name| lowercase_chars(name))
+                .collect(),
+            tags: tags.iter().map(|tag| tag.name.to_lowercase()).collect(),
+        };
+        self.query.search(fleet_data, &haystack)
+    }
+
+    /// Indexes of the fleets matching the search, most relevant first. Fleets
+    /// that match equally well keep their order.
+    pub fn rank<'a>(
+        &self,
+        fleets: impl IntoIterator<Item = &'a FleetData>,
+    ) -> Vec<(usize, SearchMatch)> {
+        let mut results = fleets
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, fleet)| Some((idx, self.search(fleet)?)))
+            .collect::<Vec<_>>();
+        results.sort_by_key(|(_, found)| Reverse(found.score));
+        results
     }
 }
 
-pub fn parse_search_text(text: String) -> SearchFilters {
-    let words = text.split_whitespace();
-    let mut tags = Vec::new();
-    let contains = words
-        .filter_map(|word| {
-            if word.starts_with("tag:") {
-                tags.push(word.strip_prefix("tag:").unwrap().to_lowercase());
-                None
+/// A syntax error in a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchError {
+    pub message: String,
+    /// Character offset of the error in the search text.
+    pub position: usize,
+}
+
+impl Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+impl From<Simple<char>> for SearchError {
+    fn from(err: Simple<char>) -> Self {
+        let message = match err.reason() {
+            SimpleReason::Custom(message) => message.clone(),
+            _ => {
+                let found = match err.found() {
+                    Some(c) => format!("Unexpected '{}'", c),
+                    None => "Unexpected end of search".to_string(),
+                };
+                // Only worth mentioning when it closes something, anything
+                // else could usually start a new term too
+                let expected =
+                    err.expected().flatten().copied().collect::<Vec<_>>();
+                if expected.contains(&')') {
+                    format!("{}, expected ')'", found)
+                } else if expected == ['"'] {
+                    format!("{}, expected '\"'", found)
+                } else {
+                    found
+                }
+            }
+        };
+        SearchError {
+            message,
+            position: err.span().start,
+        }
+    }
+}
+
+pub fn parse_search_text(text: &str) -> Result<SearchFilters, SearchError> {
+    let query = query_parser()
+        .parse(text)
+        .map_err(|mut errs| SearchError::from(errs.remove(0)))?;
+    match query.error() {
+        Some(err) => Err(err.clone()),
+        None => Ok(SearchFilters { query }),
+    }
+}
+
+const KEYWORDS: [&str; 3] = ["AND", "OR", "NOT"];
+
+/// `name`, in any case.
+fn keyword(
+    name: &'static str,
+) -> impl Parser<char, (), Error = Simple<char>> + Clone {
+    text::ident().try_map(move |word: String, span| {
+        if word.eq_ignore_ascii_case(name) {
+            Ok(())
+        } else {
+            Err(Simple::custom(span, format!("Expected {}", name)))
+        }
+    })
+}
+
+fn query_parser() -> impl Parser<char, Query, Error = Simple<char>> {
+    let is_word_char = |c: &char| {
+        !c.is_whitespace()
+            && !matches!(c, '(' | ')' | '"' | ':' | '<' | '>' | '=')
+    };
+    let phrase = just('"')
+        .ignore_then(
+            filter(|c: &char| *c != '"').repeated().collect::<String>(),
+        )
+        .then_ignore(just('"'));
+    let word = filter(is_word_char)
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .try_map(|word, span| {
+            if KEYWORDS.iter().any(|k| word.eq_ignore_ascii_case(k)) {
+                Err(Simple::custom(span, format!("Unexpected {}", word)))
             } else {
-                Some(word.to_lowercase())
+                Ok(word)
             }
-        })
-        .collect::<Vec<String>>();
+        });
+    // Field values may contain characters that separate words elsewhere
+    let value = phrase.or(filter(|c: &char| {
+        !c.is_whitespace() && !matches!(c, '(' | ')' | '"')
+    })
+    .repeated()
+    .at_least(1)
+    .collect::<String>());
+
+    let comparison = choice((
+        just(">=").to(Comparison::Ge),
+        just("<=").to(Comparison::Le),
+        just('>').to(Comparison::Gt),
+        just('<').to(Comparison::Lt),
+        just('=').to(Comparison::Eq),
+        just(':').to(Comparison::Eq),
+    ));
+    let number = text::int(10).try_map(|number: String, span| {
+        number
+            .parse::<usize>()
+            .map_err(|err| Simple::custom(span, err.to_string()))
+    });
+    // Unknown field names are reported after parsing, otherwise the error
+    // would be replaced by one for parsing the field name as a word instead
+    let text_filter = text::ident().then_ignore(just(':')).then(value).try_map(
+        |(name, value): (String, String), span: Range<usize>| {
+            if let Some(field) = TextField::from_name(&name) {
+                Ok(Query::Field(field, value.to_lowercase()))
+            } else if NumberField::from_name(&name).is_some() {
+                // Left to `number_filter`
+                Err(Simple::custom(span, "Expected a number"))
+            } else {
+                Ok(Query::Invalid(SearchError {
+                    message: format!("Unknown field '{}'", name),
+                    position: span.start,
+                }))
+            }
+        },
+    );
+    let number_filter = text::ident()
+        .then(comparison)
+        .then(number)
+        .map_with_span(|((name, comparison), number), span: Range<usize>| {
+            match NumberField::from_name(&name) {
+                Some(field) => Query::Number(field, comparison, number),
+                None => Query::Invalid(SearchError {
+                    message: format!(
+                        "'{}' can't be compared to a number",
+                        name
+                    ),
+                    position: span.start,
+                }),
+            }
+        });
+
+    let query = recursive(|query| {
+        let atom = query
+            .delimited_by(just('('), just(')'))
+            .or(text_filter)
+            .or(number_filter)
+            .or(phrase.or(word).map(|text| Query::Text(text.to_lowercase())))
+            .padded();
+
+        let not = keyword("NOT")
+            .padded()
+            .repeated()
+            .then(atom)
+            .foldr(|_, query| Query::Not(Box::new(query)));
+
+        let and = not
+            .clone()
+            .then(keyword("AND").padded().or_not().ignore_then(not).repeated())
+            .foldl(|lhs, rhs| Query::And(Box::new(lhs), Box::new(rhs)));
+
+        and.clone()
+            .then(keyword("OR").padded().ignore_then(and).repeated())
+            .foldl(|lhs, rhs| Query::Or(Box::new(lhs), Box::new(rhs)))
+    });
+
+    query
+        .or_not()
+        .map(|query| query.unwrap_or(Query::All))
+        .padded()
+        .then_ignore(end())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn fleet() -> FleetData {
+        FleetData {
+            path: PathBuf::from("Fleets/Tournament/Screen.fleet"),
+            short_path: PathBuf::from("Tournament"),
+            name: "Corvette Screen".to_string(),
+            description: "Tags: <color=#ff0000>Meta</color>\nFast picket"
+                .to_string(),
+            unknown_fields: Vec::new(),
+            total_points: 2980,
+            faction_key: "Stock/Alliance".to_string(),
+            ship_count: 4,
+            ship_names: vec!["Sprinter 1".to_string()],
+            hull_types: vec!["Stock/Sprinter Corvette".to_string()],
+            missile_names: vec!["SGM-2 Tempest".to_string()],
+            mod_dependencies: vec!["2960504230".to_string()],
+            modified: None,
+        }
+    }
 
-    SearchFilters { contains, tags }
+    fn matches(query: &str) -> bool {
+        parse_search_text(query).unwrap().matches(&fleet())
+    }
+
+    #[test]
+    fn boolean_operators() {
+        assert!(matches(""));
+        assert!(matches("corvette screen"));
+        assert!(!matches("corvette battleship"));
+        assert!(matches("corvette AND screen"));
+        assert!(matches("battleship OR corvette"));
+        assert!(!matches("NOT corvette"));
+        assert!(!matches("not corvette"));
+        assert!(matches("NOT (battleship OR cruiser) picket"));
+        assert!(matches("\"corvette screen\""));
+        assert!(!matches("\"screen corvette\""));
+    }
+
+    #[test]
+    fn field_filters() {
+        assert!(matches("name:screen"));
+        assert!(!matches("name:picket"));
+        assert!(matches("desc:picket"));
+        assert!(!matches("desc:meta"));
+        assert!(matches("path:tournament"));
+        assert!(matches("faction:alliance"));
+        assert!(matches("faction:ANS"));
+        assert!(!matches("faction:osp"));
+        assert!(matches("hull:sprinter"));
+        assert!(matches("ship:\"sprinter 1\""));
+        assert!(matches("missile:tempest"));
+        assert!(matches("mod:2960504230"));
+        assert!(matches("tag:meta"));
+        assert!(!matches("tag:met"));
+    }
+
+    #[test]
+    fn tag_hierarchies() {
+        let mut fleet = fleet();
+        fleet.description =
+            "Tags: <color=#ff0000>Tournament/2026/Week3</color>".to_string();
+        let matches =
+            |query: &str| parse_search_text(query).unwrap().matches(&fleet);
+        assert!(matches("tag:tournament"));
+        assert!(matches("tag:tournament/2026"));
+        assert!(matches("tag:tournament/2026/week3"));
+        assert!(!matches("tag:tournament/2025"));
+        assert!(!matches("tag:tourn"));
+        assert!(!matches("tag:week3"));
+    }
+
+    #[test]
+    fn number_comparisons() {
+        assert!(matches("points>=2500"));
+        assert!(matches("points:2980"));
+        assert!(!matches("points<2980"));
+        assert!(matches("ships=4 AND points<=3000"));
+        assert!(!matches("ships>4"));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for query in ["(corvette", "corvette)", "\"screen", "nmae:screen", "OR"]
+        {
+            assert!(
+                parse_search_text(query).is_err(),
+                "'{}' should not parse",
+                query
+            );
+        }
+        assert_eq!(
+            parse_search_text("nmae:screen").unwrap_err().message,
+            "Unknown field 'nmae'"
+        );
+    }
+
+    #[test]
+    fn typos_in_names() {
+        assert!(matches("corvete"));
+        assert!(matches("name:scren"));
+        assert!(matches("ship:sprniter"));
+        assert!(!matches("scn"));
+        assert!(!matches("name:cruiser"));
+    }
+
+    #[test]
+    fn ranking() {
+        let in_description = FleetData {
+            name: "Heavy Line".to_string(),
+            description: "Screened by corvettes".to_string(),
+            ..fleet()
+        };
+        let with_typo = FleetData {
+            name: "Corvete Swarm".to_string(),
+            ..fleet()
+        };
+        let fleets = [in_description, with_typo, fleet()];
+
+        let results = parse_search_text("corvette").unwrap().rank(&fleets);
+        let order = results.iter().map(|(idx, _)| *idx).collect::<Vec<_>>();
+        assert_eq!(order, [2, 1, 0]);
+        let first_span =
+            |idx: usize| results[idx].1.name_spans.first().cloned();
+        assert_eq!(first_span(0), Some(0..8));
+        assert_eq!(first_span(1), Some(0..7));
+    }
 }